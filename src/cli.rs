@@ -1,5 +1,8 @@
-use clap::{Parser, Subcommand, ValueEnum};
-use std::path::PathBuf;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::{Path, PathBuf};
+
+use crate::models::units::parse_size;
+use crate::models::{CleanConfig, ConfigFile, DeletionLimits, Preset, DEFAULT_XATTR_PATTERNS};
 
 #[derive(Parser)]
 #[command(name = "ds-store-no-more")]
@@ -15,6 +18,148 @@ pub enum LogFormat {
     Json,
 }
 
+/// Logging options shared by every subcommand
+#[derive(Args)]
+pub struct LogArgs {
+    /// Enable verbose logging
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// Log format
+    #[arg(long, value_enum, default_value = "human")]
+    pub log_format: LogFormat,
+}
+
+/// Options controlling which files are matched
+#[derive(Args)]
+pub struct MatchArgs {
+    /// Additional file pattern: a glob, or a regex prefixed with "re:" (can be repeated)
+    #[arg(short = 'p', long = "additional-pattern")]
+    pub additional_patterns: Vec<String>,
+
+    /// TOML file with conditional patterns, rules and their actions
+    #[arg(long = "config", value_name = "FILE", value_parser = parse_config_arg)]
+    pub config_file: Option<ConfigFile>,
+
+    /// Directory to ignore during traversal (can be repeated)
+    #[arg(long = "ignore")]
+    pub ignore_patterns: Vec<String>,
+
+    /// Match the default, -p, -d and preset patterns regardless of case, e.g. THUMBS.DB
+    #[arg(long)]
+    pub ignore_case: bool,
+
+    /// Add the patterns for an environment: samba, windows (can be repeated)
+    #[arg(long = "preset", value_name = "NAME", value_parser = parse_preset_arg)]
+    pub presets: Vec<Preset>,
+
+    /// Allow system directories, the home directory or very broad patterns
+    #[arg(long)]
+    pub allow_dangerous: bool,
+
+    /// Skip .DS_Store, ._* and Thumbs.db files whose header doesn't match the real format
+    #[arg(long)]
+    pub verify_signatures: bool,
+
+    /// Delete vim swap and Emacs lock/auto-save files whose editor has exited on this machine
+    #[arg(long)]
+    pub editor_artifacts: bool,
+
+    /// Skip files that any process holds open (reads /proc; needs root to see other users)
+    #[arg(long)]
+    pub skip_in_use: bool,
+
+    /// Delete ._* files only when their base file is gone or they hold no metadata
+    #[arg(long)]
+    pub apple_double: bool,
+
+    /// Directory name pattern to remove with all its contents, e.g. __MACOSX (can be repeated)
+    #[arg(short = 'd', long = "dir-pattern")]
+    pub dir_patterns: Vec<String>,
+
+    /// Remove directories left empty by this cleanup (never the root, ignored or already-empty ones)
+    #[arg(long)]
+    pub prune_empty_dirs: bool,
+
+    /// Strip Apple extended attributes (user.com.apple.*, user.DosStream.*AFP_*) from kept
+    /// regular files; directories are left alone
+    #[arg(long)]
+    pub strip_xattrs: bool,
+
+    /// Extended attribute name pattern to strip from kept regular files (can be repeated)
+    #[arg(long = "xattr-pattern")]
+    pub xattr_patterns: Vec<String>,
+}
+
+impl MatchArgs {
+    pub fn into_config(self, root_dir: PathBuf, dry_run: bool) -> CleanConfig {
+        let mut xattr_patterns = Vec::new();
+        if self.strip_xattrs {
+            xattr_patterns.extend(DEFAULT_XATTR_PATTERNS.iter().map(|p| p.to_string()));
+        }
+        xattr_patterns.extend(self.xattr_patterns);
+        let config_file = self.config_file.unwrap_or_default();
+
+        CleanConfig::new(
+            root_dir,
+            self.additional_patterns,
+            self.ignore_patterns,
+            dry_run,
+        )
+        .with_allow_dangerous(self.allow_dangerous)
+        .with_ignore_case(self.ignore_case)
+        .with_signature_checks(self.verify_signatures)
+        .with_apple_double(self.apple_double)
+        .with_in_use_check(self.skip_in_use)
+        .with_editor_artifacts(self.editor_artifacts)
+        .with_dir_patterns(self.dir_patterns)
+        .with_prune_empty_dirs(self.prune_empty_dirs)
+        .with_xattr_patterns(xattr_patterns)
+        .with_conditional_patterns(config_file.patterns)
+        .with_rules(config_file.rules)
+        .with_quarantine_dir(config_file.quarantine_dir)
+        .with_presets(&self.presets)
+    }
+}
+
+/// Safety caps on how much a single run may delete
+#[derive(Args)]
+pub struct LimitArgs {
+    /// Abort before deleting anything if more than N files match
+    #[arg(long, value_name = "N")]
+    pub max_deletions: Option<usize>,
+
+    /// Abort before deleting anything if matches total more than SIZE (e.g. 500M)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size_arg)]
+    pub max_bytes: Option<u64>,
+
+    /// Delete even if --max-deletions or --max-bytes is exceeded
+    #[arg(long)]
+    pub force: bool,
+}
+
+impl From<LimitArgs> for DeletionLimits {
+    fn from(args: LimitArgs) -> Self {
+        DeletionLimits {
+            max_deletions: args.max_deletions,
+            max_bytes: args.max_bytes,
+            force: args.force,
+        }
+    }
+}
+
+fn parse_preset_arg(value: &str) -> Result<Preset, String> {
+    value.parse().map_err(|e: anyhow::Error| e.to_string())
+}
+
+fn parse_config_arg(value: &str) -> Result<ConfigFile, String> {
+    ConfigFile::load(Path::new(value)).map_err(|e| format!("{:#}", e))
+}
+
+fn parse_size_arg(value: &str) -> Result<u64, String> {
+    parse_size(value).map_err(|e| e.to_string())
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
     /// Run a one-time cleanup
@@ -22,25 +167,22 @@ pub enum Commands {
         /// Root directory to clean
         root_dir: PathBuf,
 
-        /// Additional file pattern (can be repeated)
-        #[arg(short = 'p', long = "additional-pattern")]
-        additional_patterns: Vec<String>,
-
-        /// Directory to ignore during traversal (can be repeated)
-        #[arg(long = "ignore")]
-        ignore_patterns: Vec<String>,
+        #[command(flatten)]
+        matching: MatchArgs,
 
         /// Show what would be deleted without deleting
         #[arg(short = 'n', long)]
         dry_run: bool,
 
-        /// Enable verbose logging
-        #[arg(short, long)]
-        verbose: bool,
+        /// Write a leak audit of .DS_Store files to this path before cleaning
+        #[arg(long, value_name = "FILE")]
+        audit_leaks: Option<PathBuf>,
+
+        #[command(flatten)]
+        limits: LimitArgs,
 
-        /// Log format
-        #[arg(long, value_enum, default_value = "human")]
-        log_format: LogFormat,
+        #[command(flatten)]
+        logging: LogArgs,
     },
 
     /// Monitor directory and clean periodically
@@ -56,24 +198,152 @@ pub enum Commands {
         #[arg(short, long)]
         timeout: Option<u64>,
 
-        /// Additional file pattern (can be repeated)
-        #[arg(short = 'p', long = "additional-pattern")]
-        additional_patterns: Vec<String>,
-
-        /// Directory to ignore during traversal (can be repeated)
-        #[arg(long = "ignore")]
-        ignore_patterns: Vec<String>,
+        #[command(flatten)]
+        matching: MatchArgs,
 
         /// Show what would be deleted without deleting
         #[arg(short = 'n', long)]
         dry_run: bool,
 
-        /// Enable verbose logging
+        #[command(flatten)]
+        limits: LimitArgs,
+
+        #[command(flatten)]
+        logging: LogArgs,
+    },
+
+    /// Write the actions a cleanup would take to a plan file for review
+    Plan {
+        /// Root directory to scan
+        root_dir: PathBuf,
+
+        /// Path of the plan file to write
         #[arg(short, long)]
-        verbose: bool,
+        output: PathBuf,
+
+        #[command(flatten)]
+        matching: MatchArgs,
+
+        #[command(flatten)]
+        logging: LogArgs,
+    },
+
+    /// Execute a reviewed plan file, skipping files changed since it was written
+    Apply {
+        /// Plan file produced by the plan command
+        plan: PathBuf,
+
+        /// Show what would be deleted without deleting
+        #[arg(short = 'n', long)]
+        dry_run: bool,
 
-        /// Log format
-        #[arg(long, value_enum, default_value = "human")]
-        log_format: LogFormat,
+        #[command(flatten)]
+        limits: LimitArgs,
+
+        /// Allow a plan for a system directory, the home directory or very broad patterns
+        #[arg(long)]
+        allow_dangerous: bool,
+
+        #[command(flatten)]
+        logging: LogArgs,
     },
+
+    /// Report names recorded in .DS_Store files that are absent, ignored or sensitive
+    AuditLeaks {
+        /// Root directory to audit
+        root_dir: PathBuf,
+
+        /// Directory whose contents count as ignored (can be repeated)
+        #[arg(long = "ignore")]
+        ignore_patterns: Vec<String>,
+
+        #[command(flatten)]
+        logging: LogArgs,
+    },
+
+    /// Print the paths a cleanup would act on, for use with other tools
+    List {
+        /// Root directory to scan
+        root_dir: PathBuf,
+
+        #[command(flatten)]
+        matching: MatchArgs,
+
+        /// Separate paths with NUL instead of newline, for xargs -0
+        #[arg(short = '0', long = "null")]
+        null_separated: bool,
+
+        /// Print paths relative to the root directory
+        #[arg(long)]
+        relative: bool,
+
+        #[command(flatten)]
+        logging: LogArgs,
+    },
+
+    /// Show whether a cleanup would act on a path, and which setting decides it
+    Explain {
+        /// File or directory to explain
+        path: PathBuf,
+
+        /// Root directory the cleanup would run on
+        #[arg(long = "root", value_name = "DIR", default_value = ".")]
+        root_dir: PathBuf,
+
+        #[command(flatten)]
+        matching: MatchArgs,
+
+        #[command(flatten)]
+        logging: LogArgs,
+    },
+
+    /// Judge relative paths read from stdin by name alone
+    TestPatterns {
+        #[command(flatten)]
+        matching: MatchArgs,
+
+        /// Read paths separated by NUL instead of newline, as from find -print0
+        #[arg(short = '0', long = "null")]
+        null_separated: bool,
+
+        #[command(flatten)]
+        logging: LogArgs,
+    },
+
+    /// Print the records stored in a .DS_Store file as JSON
+    Inspect {
+        /// .DS_Store file to parse
+        file: PathBuf,
+
+        #[command(flatten)]
+        logging: LogArgs,
+    },
+}
+
+impl Commands {
+    pub fn logging(&self) -> &LogArgs {
+        match self {
+            Commands::Run { logging, .. }
+            | Commands::Monitor { logging, .. }
+            | Commands::Plan { logging, .. }
+            | Commands::Apply { logging, .. }
+            | Commands::AuditLeaks { logging, .. }
+            | Commands::List { logging, .. }
+            | Commands::Explain { logging, .. }
+            | Commands::TestPatterns { logging, .. }
+            | Commands::Inspect { logging, .. } => logging,
+        }
+    }
+
+    /// Whether the command's results go to stdout, so its logs must not
+    pub fn prints_results(&self) -> bool {
+        matches!(
+            self,
+            Commands::AuditLeaks { .. }
+                | Commands::List { .. }
+                | Commands::Explain { .. }
+                | Commands::TestPatterns { .. }
+                | Commands::Inspect { .. }
+        )
+    }
 }
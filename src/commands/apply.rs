@@ -0,0 +1,63 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::core::Cleaner;
+use crate::fs::FileSystem;
+use crate::models::{DeletionLimits, Plan, ScanReport};
+
+pub async fn execute<F: FileSystem>(
+    fs: F,
+    plan_path: &Path,
+    dry_run: bool,
+    limits: DeletionLimits,
+    allow_dangerous: bool,
+) -> Result<()> {
+    let json = tokio::fs::read_to_string(plan_path)
+        .await
+        .with_context(|| format!("Failed to read plan {}", plan_path.display()))?;
+    let plan =
+        Plan::from_json(&json).with_context(|| format!("Invalid plan {}", plan_path.display()))?;
+    plan.check_paths()
+        .with_context(|| format!("Invalid plan {}", plan_path.display()))?;
+    let config = plan
+        .to_config(dry_run)
+        .with_limits(limits)
+        .with_allow_dangerous(allow_dangerous);
+    config.validate()?;
+
+    tracing::info!(
+        plan = %plan_path.display(),
+        root = %plan.root_dir.display(),
+        actions = plan.actions.len(),
+        "Applying plan"
+    );
+
+    let cleaner = Cleaner::from_config(fs, &config)?;
+    let report = ScanReport {
+        matches: plan.actions.clone(),
+        dirs: plan.dir_deletions.clone(),
+        xattrs: plan.xattr_removals.clone(),
+        skipped: Vec::new(),
+    };
+    cleaner.check_limits(&report.matches, &report.dirs, dry_run)?;
+    let result = cleaner.apply(&plan.root_dir, report, dry_run).await;
+
+    tracing::info!(
+        found = result.files_found,
+        deleted = result.files_deleted,
+        quarantined = result.files_quarantined,
+        reported = result.files_reported,
+        renamed = result.files_renamed,
+        failed = result.files_failed.len(),
+        skipped = result.files_skipped.len(),
+        dirs_found = result.dirs_found,
+        dirs_deleted = result.dirs_deleted.len(),
+        dirs_pruned = result.dirs_pruned.len(),
+        xattrs_found = result.xattrs_found,
+        xattrs_removed = result.xattrs_removed,
+        dry_run = result.dry_run,
+        "Apply complete"
+    );
+
+    Ok(())
+}
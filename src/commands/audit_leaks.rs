@@ -0,0 +1,30 @@
+use anyhow::Result;
+use std::path::Path;
+
+use crate::core::LeakAuditor;
+use crate::fs::FileSystem;
+use crate::models::LeakReport;
+
+pub async fn execute<F: FileSystem>(fs: F, root: &Path, ignore_patterns: &[String]) -> Result<()> {
+    let report = audit(&fs, root, ignore_patterns).await?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+/// Audit `root` and log a summary
+pub async fn audit<F: FileSystem>(
+    fs: &F,
+    root: &Path,
+    ignore_patterns: &[String],
+) -> Result<LeakReport> {
+    let report = LeakAuditor::new(fs, ignore_patterns)?.audit(root).await?;
+
+    tracing::info!(
+        root = %root.display(),
+        ds_store_files = report.files.len(),
+        leaks = report.total_leaks(),
+        "Leak audit complete"
+    );
+
+    Ok(report)
+}
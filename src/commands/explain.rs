@@ -0,0 +1,63 @@
+use anyhow::Result;
+use std::path::Path;
+
+use crate::core::Cleaner;
+use crate::fs::FileSystem;
+use crate::models::{CleanConfig, EscapePath};
+
+pub async fn execute<F: FileSystem>(fs: F, config: CleanConfig, path: &Path) -> Result<()> {
+    let cleaner = Cleaner::from_config(fs, &config)?;
+    let explanation = cleaner.explain(&config.root_dir, path).await?;
+
+    print_config(&config);
+    println!();
+    println!("{}", explanation);
+    Ok(())
+}
+
+/// Print the settings that decide what gets cleaned, leaving out empty ones
+fn print_config(config: &CleanConfig) {
+    println!("Config:");
+    println!("  root: {}", config.root_dir.escaped());
+    println!("  patterns: {}", config.patterns.join(", "));
+    print_list("validated patterns", &config.validated_patterns);
+    for pattern in &config.conditional_patterns {
+        let mut details = vec![pattern.action.to_string()];
+        if !pattern.conditions.is_empty() {
+            details.push(pattern.conditions.to_string());
+        }
+        if pattern.ignore_case {
+            details.push("ignore case".to_string());
+        }
+        println!("  pattern '{}': {}", pattern.pattern, details.join(", "));
+    }
+    for rule in &config.rules {
+        println!("  rule '{}': {}", rule.when, rule.action);
+    }
+    print_list("dir patterns", &config.dir_patterns);
+    print_list("ignore", &config.ignore_patterns);
+    print_list("xattr patterns", &config.xattr_patterns);
+    if let Some(dir) = &config.quarantine_dir {
+        println!("  quarantine dir: {}", dir.escaped());
+    }
+
+    let flags: Vec<&str> = [
+        (config.ignore_case, "ignore case"),
+        (config.decode_names, "decode server names"),
+        (config.verify_signatures, "verify signatures"),
+        (config.apple_double, "AppleDouble handling"),
+        (config.editor_artifacts, "editor artifacts"),
+        (config.skip_in_use, "skip in use"),
+    ]
+    .into_iter()
+    .filter_map(|(on, name)| on.then_some(name))
+    .collect();
+    print_list("options", &flags);
+}
+
+fn print_list<T: AsRef<str>>(label: &str, items: &[T]) {
+    if !items.is_empty() {
+        let items: Vec<&str> = items.iter().map(AsRef::as_ref).collect();
+        println!("  {}: {}", label, items.join(", "));
+    }
+}
@@ -0,0 +1,22 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::path::Path;
+
+use crate::dsstore;
+use crate::fs::FileSystem;
+
+pub async fn execute<F: FileSystem>(fs: F, path: &Path) -> Result<()> {
+    let data = fs
+        .read_head(path, dsstore::MAX_FILE_SIZE)
+        .await
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let records =
+        dsstore::parse(&data).with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    tracing::debug!(path = %path.display(), records = records.len(), "Parsed .DS_Store");
+
+    let json = Value::Array(records.iter().map(|r| r.to_json()).collect());
+    println!("{}", serde_json::to_string_pretty(&json)?);
+
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use anyhow::Result;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::core::Cleaner;
+use crate::fs::FileSystem;
+use crate::models::CleanConfig;
+
+/// Print the files and directories a cleanup would act on, exactly as named on
+/// disk, so another tool can take the action instead. Files a cleanup would
+/// skip for their contents or for being open are left out.
+pub async fn execute<F: FileSystem>(
+    fs: F,
+    config: CleanConfig,
+    null_separated: bool,
+    relative: bool,
+) -> Result<()> {
+    config.validate()?;
+    let cleaner = Cleaner::from_config(fs, &config)?;
+    let report = cleaner.scan_report(&config.root_dir).await?;
+    let matches = cleaner.screen_matches(report.matches).await?;
+
+    let paths = matches
+        .iter()
+        .map(|m| m.path.as_path())
+        .chain(report.dirs.iter().map(|d| d.path.as_path()));
+    let separator = if null_separated { b'\0' } else { b'\n' };
+
+    let mut stdout = BufWriter::new(std::io::stdout().lock());
+    let mut listed = 0;
+    for path in paths {
+        let path = if relative {
+            path.strip_prefix(&config.root_dir).unwrap_or(path)
+        } else {
+            path
+        };
+        stdout.write_all(&path_bytes(path))?;
+        stdout.write_all(&[separator])?;
+        listed += 1;
+    }
+    stdout.flush()?;
+
+    tracing::info!(listed, "Listed matches");
+    Ok(())
+}
+
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> std::borrow::Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().into()
+}
+
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> std::borrow::Cow<'_, [u8]> {
+    match path.to_string_lossy() {
+        std::borrow::Cow::Borrowed(text) => text.as_bytes().into(),
+        std::borrow::Cow::Owned(text) => text.into_bytes().into(),
+    }
+}
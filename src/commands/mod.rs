@@ -1,2 +1,9 @@
+pub mod apply;
+pub mod audit_leaks;
+pub mod explain;
+pub mod inspect;
+pub mod list;
 pub mod monitor;
+pub mod plan;
 pub mod run;
+pub mod test_patterns;
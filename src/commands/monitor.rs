@@ -11,7 +11,8 @@ pub async fn execute<F: FileSystem>(
     interval_duration: Duration,
     timeout: Option<Duration>,
 ) -> Result<()> {
-    let cleaner = Cleaner::new(fs, &config.patterns, config.ignore_patterns.clone())?;
+    config.validate()?;
+    let cleaner = Cleaner::from_config(fs, &config)?;
 
     tracing::info!(
         root = %config.root_dir.display(),
@@ -43,7 +44,14 @@ async fn run_cleanup_cycle<F: FileSystem>(cleaner: &Cleaner<F>, config: &CleanCo
             tracing::info!(
                 found = result.files_found,
                 deleted = result.files_deleted,
+                quarantined = result.files_quarantined,
+                reported = result.files_reported,
+                renamed = result.files_renamed,
                 failed = result.files_failed.len(),
+                skipped = result.files_skipped.len(),
+                dirs_deleted = result.dirs_deleted.len(),
+                dirs_pruned = result.dirs_pruned.len(),
+                xattrs_removed = result.xattrs_removed,
                 "Cleanup cycle complete"
             );
         }
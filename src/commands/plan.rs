@@ -0,0 +1,52 @@
+use anyhow::Result;
+use std::path::Path;
+
+use crate::core::Cleaner;
+use crate::fs::FileSystem;
+use crate::models::{CleanConfig, EscapePath, Plan, ScanReport};
+
+pub async fn execute<F: FileSystem>(fs: F, config: CleanConfig, output: &Path) -> Result<()> {
+    config.validate()?;
+    let cleaner = Cleaner::from_config(fs, &config)?;
+    let ScanReport {
+        matches,
+        dirs,
+        xattrs,
+        ..
+    } = cleaner.scan_report(&config.root_dir).await?;
+
+    for file in &matches {
+        tracing::debug!(path = %file.path.escaped(), "Planned delete");
+    }
+    for dir in &dirs {
+        tracing::debug!(path = %dir.path.escaped(), files = dir.files, "Planned directory delete");
+    }
+
+    let plan = Plan::new(
+        config.root_dir,
+        config.patterns,
+        config.ignore_patterns,
+        matches,
+    )
+    .with_signature_checks(config.verify_signatures)
+    .with_validated_patterns(config.validated_patterns)
+    .with_conditional_patterns(config.conditional_patterns)
+    .with_rules(config.rules, config.quarantine_dir)
+    .with_name_decoding(config.decode_names)
+    .with_ignore_case(config.ignore_case)
+    .with_in_use_check(config.skip_in_use)
+    .with_prune_empty_dirs(config.prune_empty_dirs)
+    .with_dirs(config.dir_patterns, dirs)
+    .with_xattrs(config.xattr_patterns, xattrs);
+    tokio::fs::write(output, plan.to_json()?).await?;
+
+    tracing::info!(
+        planned = plan.actions.len(),
+        planned_dirs = plan.dir_deletions.len(),
+        planned_xattrs = plan.xattr_removals.len(),
+        output = %output.display(),
+        "Plan written"
+    );
+
+    Ok(())
+}
@@ -1,17 +1,35 @@
 use anyhow::Result;
 
+use super::audit_leaks;
 use crate::core::Cleaner;
 use crate::fs::FileSystem;
 use crate::models::CleanConfig;
 
 pub async fn execute<F: FileSystem>(fs: F, config: CleanConfig) -> Result<()> {
-    let cleaner = Cleaner::new(fs, &config.patterns, config.ignore_patterns)?;
+    config.validate()?;
+
+    // Record what the .DS_Store files expose before cleanup destroys the evidence
+    if let Some(report_path) = &config.audit_report {
+        let report = audit_leaks::audit(&fs, &config.root_dir, &config.ignore_patterns).await?;
+        tokio::fs::write(report_path, serde_json::to_string_pretty(&report)?).await?;
+    }
+
+    let cleaner = Cleaner::from_config(fs, &config)?;
     let result = cleaner.clean(&config.root_dir, config.dry_run).await?;
 
     tracing::info!(
         found = result.files_found,
         deleted = result.files_deleted,
+        quarantined = result.files_quarantined,
+        reported = result.files_reported,
+        renamed = result.files_renamed,
         failed = result.files_failed.len(),
+        skipped = result.files_skipped.len(),
+        dirs_found = result.dirs_found,
+        dirs_deleted = result.dirs_deleted.len(),
+        dirs_pruned = result.dirs_pruned.len(),
+        xattrs_found = result.xattrs_found,
+        xattrs_removed = result.xattrs_removed,
         dry_run = result.dry_run,
         "Cleanup complete"
     );
@@ -0,0 +1,53 @@
+use anyhow::Result;
+use std::io::Write;
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::core::Cleaner;
+use crate::fs::FileSystem;
+use crate::models::{CleanConfig, Outcome};
+
+/// Judge each relative path read from stdin as it arrives, one tab-separated
+/// verdict per line. Paths are separated by newlines, or by NUL as from
+/// `find -print0` when `null_separated` is set.
+pub async fn execute<F: FileSystem>(
+    fs: F,
+    config: CleanConfig,
+    null_separated: bool,
+) -> Result<()> {
+    let cleaner = Cleaner::from_config(fs, &config)?;
+    let separator = if null_separated { 0 } else { b'\n' };
+
+    let mut entries = BufReader::new(tokio::io::stdin()).split(separator);
+    let mut stdout = std::io::stdout().lock();
+    let (mut tested, mut matched) = (0, 0);
+    while let Some(entry) = entries.next_segment().await? {
+        let entry = match separator {
+            b'\n' => entry.strip_suffix(b"\r").unwrap_or(&entry),
+            _ => &entry,
+        };
+        if entry.is_empty() {
+            continue;
+        }
+        let verdict = cleaner.name_verdict(&path_from_bytes(entry));
+        tested += 1;
+        if verdict.outcome != Outcome::Keep {
+            matched += 1;
+        }
+        writeln!(stdout, "{}", verdict)?;
+    }
+
+    tracing::info!(tested, matched, "Patterns tested");
+    Ok(())
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
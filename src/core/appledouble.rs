@@ -0,0 +1,180 @@
+//! Inspection of AppleDouble (`._name`) files.
+//!
+//! An AppleDouble file carries the Finder info, resource fork and extended
+//! attributes of `name` for filesystems that can't store them natively.
+
+use anyhow::{bail, Result};
+
+const MAGIC: u32 = 0x0005_1607;
+const HEADER_LEN: usize = 26;
+const ENTRY_LEN: usize = 12;
+
+const RESOURCE_FORK: u32 = 2;
+const FINDER_INFO: u32 = 9;
+
+/// Bytes of classic Finder info at the start of the Finder info entry
+const FINDER_INFO_LEN: usize = 32;
+/// macOS appends an extended attribute block ("ATTR") after 2 bytes of padding
+const ATTR_HEADER_OFFSET: usize = FINDER_INFO_LEN + 2;
+const ATTR_NUM_ATTRS_OFFSET: usize = 34;
+/// macOS writes this placeholder as the resource fork when there is none
+const BLANK_RESOURCE_FORK: &[u8] = b"This resource fork intentionally left blank";
+const BLANK_RESOURCE_FORK_LEN: usize = 286;
+
+/// How many leading bytes to read before deciding; entries beyond this count as data
+pub const INSPECT_LEN: usize = 64 * 1024;
+
+pub fn is_apple_double_name(name: &str) -> bool {
+    name.len() > 2 && name.starts_with("._")
+}
+
+/// True when the AppleDouble holds nothing worth keeping: only zeroed Finder
+/// info with no extended attributes and, at most, a blank resource fork.
+/// `head` is the start of the file; data that lies beyond it is assumed to matter.
+pub fn is_empty(head: &[u8]) -> Result<bool> {
+    if head.len() < HEADER_LEN || be_u32(&head[0..4]) != MAGIC {
+        bail!("Not an AppleDouble file");
+    }
+    let count = u16::from_be_bytes([head[24], head[25]]) as usize;
+
+    for i in 0..count {
+        let start = HEADER_LEN + i * ENTRY_LEN;
+        let Some(entry) = head.get(start..start + ENTRY_LEN) else {
+            bail!("Truncated AppleDouble entry table");
+        };
+        let id = be_u32(&entry[0..4]);
+        let offset = be_u32(&entry[4..8]) as usize;
+        let len = be_u32(&entry[8..12]) as usize;
+        let Some(data) = head.get(offset..offset.saturating_add(len)) else {
+            return Ok(false);
+        };
+
+        let empty = match id {
+            FINDER_INFO => is_empty_finder_info(data),
+            RESOURCE_FORK => is_blank_resource_fork(data),
+            _ => data.is_empty(),
+        };
+        if !empty {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn is_empty_finder_info(data: &[u8]) -> bool {
+    let info = &data[..data.len().min(FINDER_INFO_LEN)];
+    if info.iter().any(|b| *b != 0) {
+        return false;
+    }
+    let attrs = data.get(ATTR_HEADER_OFFSET..).unwrap_or_default();
+    if attrs.starts_with(b"ATTR") {
+        return attrs.get(ATTR_NUM_ATTRS_OFFSET..ATTR_NUM_ATTRS_OFFSET + 2) == Some(&[0, 0][..]);
+    }
+    attrs.iter().all(|b| *b == 0)
+}
+
+fn is_blank_resource_fork(data: &[u8]) -> bool {
+    data.is_empty()
+        || (data.len() == BLANK_RESOURCE_FORK_LEN
+            && data
+                .windows(BLANK_RESOURCE_FORK.len())
+                .any(|w| w == BLANK_RESOURCE_FORK))
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Build an AppleDouble file from `(entry id, data)` pairs
+    pub(crate) fn build(entries: &[(u32, Vec<u8>)]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&MAGIC.to_be_bytes());
+        out.extend_from_slice(&0x0002_0000u32.to_be_bytes());
+        out.extend_from_slice(b"Mac OS X        ");
+        out.extend_from_slice(&(entries.len() as u16).to_be_bytes());
+        let mut offset = HEADER_LEN + entries.len() * ENTRY_LEN;
+        for (id, data) in entries {
+            out.extend_from_slice(&id.to_be_bytes());
+            out.extend_from_slice(&(offset as u32).to_be_bytes());
+            out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            offset += data.len();
+        }
+        for (_, data) in entries {
+            out.extend_from_slice(data);
+        }
+        out
+    }
+
+    /// Finder info followed by an ATTR block declaring `num_attrs` attributes
+    pub(crate) fn finder_info(num_attrs: u16) -> Vec<u8> {
+        let mut data = vec![0u8; ATTR_HEADER_OFFSET];
+        let mut attr = b"ATTR".to_vec();
+        attr.resize(ATTR_NUM_ATTRS_OFFSET, 0);
+        attr.extend_from_slice(&num_attrs.to_be_bytes());
+        data.extend(attr);
+        data
+    }
+
+    fn blank_resource_fork() -> Vec<u8> {
+        let mut fork = vec![0u8; BLANK_RESOURCE_FORK_LEN];
+        fork[16..16 + BLANK_RESOURCE_FORK.len()].copy_from_slice(BLANK_RESOURCE_FORK);
+        fork
+    }
+
+    #[test]
+    fn test_name_detection() {
+        assert!(is_apple_double_name("._photo.jpg"));
+        assert!(!is_apple_double_name("._"));
+        assert!(!is_apple_double_name("photo.jpg"));
+    }
+
+    #[test]
+    fn test_empty_finder_info_only() {
+        assert!(is_empty(&build(&[(FINDER_INFO, vec![0; 32])])).unwrap());
+        assert!(is_empty(&build(&[(FINDER_INFO, finder_info(0))])).unwrap());
+    }
+
+    #[test]
+    fn test_blank_resource_fork_is_empty() {
+        let data = build(&[
+            (FINDER_INFO, finder_info(0)),
+            (RESOURCE_FORK, blank_resource_fork()),
+        ]);
+        assert!(is_empty(&data).unwrap());
+    }
+
+    #[test]
+    fn test_finder_flags_are_data() {
+        let mut info = vec![0u8; 32];
+        info[8] = 0x40; // a Finder flag such as a colour label
+        assert!(!is_empty(&build(&[(FINDER_INFO, info)])).unwrap());
+    }
+
+    #[test]
+    fn test_extended_attributes_are_data() {
+        assert!(!is_empty(&build(&[(FINDER_INFO, finder_info(1))])).unwrap());
+    }
+
+    #[test]
+    fn test_resource_fork_is_data() {
+        let data = build(&[(FINDER_INFO, vec![0; 32]), (RESOURCE_FORK, vec![1; 512])]);
+        assert!(!is_empty(&data).unwrap());
+    }
+
+    #[test]
+    fn test_entry_beyond_head_is_data() {
+        let mut data = build(&[(FINDER_INFO, vec![0; 32])]);
+        data.truncate(HEADER_LEN + ENTRY_LEN + 4);
+        assert!(!is_empty(&data).unwrap());
+    }
+
+    #[test]
+    fn test_rejects_non_apple_double() {
+        assert!(is_empty(b"hello").is_err());
+        assert!(is_empty(&[0u8; 64]).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+use anyhow::Result;
+use std::collections::BTreeSet;
+use std::path::{Component, Path};
+
+use crate::dsstore;
+use crate::fs::FileSystem;
+use crate::models::{DsStoreAudit, EscapePath, Leak, LeakReason, LeakReport};
+
+use super::NameGlob;
+
+/// Names that suggest credentials or private data (matched case-insensitively)
+const SENSITIVE_PATTERNS: &[&str] = &[
+    ".aws",
+    ".env",
+    ".env.*",
+    ".git",
+    ".gnupg",
+    ".htpasswd",
+    ".netrc",
+    ".ssh",
+    "*.kdbx",
+    "*.key",
+    "*.p12",
+    "*.pem",
+    "*.pfx",
+    "*password*",
+    "*private*",
+    "*secret*",
+    "credentials*",
+    "id_dsa*",
+    "id_ecdsa*",
+    "id_ed25519*",
+    "id_rsa*",
+];
+
+/// Reports file names recorded in `.DS_Store` files that reveal more than the
+/// published tree does: files that are gone, or that live in ignored or
+/// sensitive directories.
+pub struct LeakAuditor<'a, F: FileSystem> {
+    fs: &'a F,
+    ignore_patterns: &'a [String],
+    sensitive: Vec<NameGlob>,
+}
+
+impl<'a, F: FileSystem> LeakAuditor<'a, F> {
+    pub fn new(fs: &'a F, ignore_patterns: &'a [String]) -> Result<Self> {
+        let sensitive: Result<Vec<_>> = SENSITIVE_PATTERNS
+            .iter()
+            .map(|p| NameGlob::new(p, true))
+            .collect();
+        Ok(Self {
+            fs,
+            ignore_patterns,
+            sensitive: sensitive?,
+        })
+    }
+
+    /// Audit every `.DS_Store` under `root`. Ignored directories are still
+    /// traversed, since a `.DS_Store` inside them is exactly what leaks.
+    pub async fn audit(&self, root: &Path) -> Result<LeakReport> {
+        let files = self.fs.walk_dir(root, &[]).await?;
+        let mut audits = Vec::new();
+        for path in files {
+            if path.file_name().is_some_and(|n| n == ".DS_Store") {
+                audits.push(self.audit_file(root, &path).await);
+            }
+        }
+        Ok(LeakReport {
+            root: root.to_path_buf(),
+            files: audits,
+        })
+    }
+
+    async fn audit_file(&self, root: &Path, path: &Path) -> DsStoreAudit {
+        let mut audit = DsStoreAudit {
+            path: path.to_path_buf(),
+            recorded_names: 0,
+            leaks: Vec::new(),
+            error: None,
+        };
+
+        let records = match self.fs.read_head(path, dsstore::MAX_FILE_SIZE).await {
+            Ok(data) => dsstore::parse(&data),
+            Err(e) => Err(e),
+        };
+        let records = match records {
+            Ok(records) => records,
+            Err(e) => {
+                tracing::warn!(path = %path.escaped(), error = %e, "Failed to read .DS_Store");
+                audit.error = Some(e.to_string());
+                return audit;
+            }
+        };
+
+        let names: BTreeSet<String> = records
+            .into_iter()
+            .map(|r| r.filename)
+            .filter(|name| name != ".")
+            .collect();
+        audit.recorded_names = names.len();
+
+        let dir = path.parent().unwrap_or(root);
+        let ancestors: Vec<String> = dir
+            .strip_prefix(root)
+            .unwrap_or(dir)
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => name.to_str().map(String::from),
+                _ => None,
+            })
+            .collect();
+        let ancestor_ignored = ancestors.iter().any(|a| self.is_ignored(a));
+        let ancestor_sensitive = ancestors.iter().any(|a| self.is_sensitive(a));
+
+        for name in names {
+            let mut reasons = Vec::new();
+            if !self.exists_beside(dir, &name).await {
+                reasons.push(LeakReason::Absent);
+            }
+            if ancestor_ignored || self.is_ignored(&name) {
+                reasons.push(LeakReason::Ignored);
+            }
+            if ancestor_sensitive || self.is_sensitive(&name) {
+                reasons.push(LeakReason::Sensitive);
+            }
+            if !reasons.is_empty() {
+                audit.leaks.push(Leak { name, reasons });
+            }
+        }
+        audit
+    }
+
+    /// Names that would escape the folder can't refer to a sibling, so count them as absent
+    async fn exists_beside(&self, dir: &Path, name: &str) -> bool {
+        if name.contains('/') || name == ".." {
+            return false;
+        }
+        match self.fs.exists(&dir.join(name)).await {
+            Ok(exists) => exists,
+            Err(e) => {
+                tracing::debug!(name, error = %e, "Could not check recorded name");
+                true
+            }
+        }
+    }
+
+    fn is_ignored(&self, name: &str) -> bool {
+        self.ignore_patterns.iter().any(|p| p == name)
+    }
+
+    fn is_sensitive(&self, name: &str) -> bool {
+        self.sensitive.iter().any(|p| p.matches(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsstore::tests::build;
+    use crate::dsstore::RecordValue;
+    use crate::fs::MockFileSystem;
+    use std::path::PathBuf;
+
+    fn ds_store(names: &[&str]) -> Vec<u8> {
+        let records: Vec<_> = names
+            .iter()
+            .map(|n| (*n, "Iloc", RecordValue::Blob(vec![0; 16])))
+            .collect();
+        build(&records)
+    }
+
+    #[tokio::test]
+    async fn test_audit_reports_absent_and_sensitive_names() {
+        let fs = MockFileSystem::with_files(vec![
+            PathBuf::from("/site/.DS_Store"),
+            PathBuf::from("/site/index.html"),
+            PathBuf::from("/site/.env"),
+        ]);
+        fs.set_contents(
+            PathBuf::from("/site/.DS_Store"),
+            ds_store(&["index.html", "old-draft.html", ".env", "."]),
+        );
+        let auditor = LeakAuditor::new(&fs, &[]).unwrap();
+
+        let report = auditor.audit(Path::new("/site")).await.unwrap();
+
+        assert_eq!(report.files.len(), 1);
+        let audit = &report.files[0];
+        assert_eq!(audit.recorded_names, 3);
+        assert_eq!(
+            audit.leaks,
+            vec![
+                Leak {
+                    name: ".env".to_string(),
+                    reasons: vec![LeakReason::Sensitive],
+                },
+                Leak {
+                    name: "old-draft.html".to_string(),
+                    reasons: vec![LeakReason::Absent],
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_audit_flags_ignored_directories() {
+        let ignore = vec!["build".to_string()];
+        let fs = MockFileSystem::with_files(vec![
+            PathBuf::from("/site/.DS_Store"),
+            PathBuf::from("/site/build/app.js"),
+            PathBuf::from("/site/build/.DS_Store"),
+        ]);
+        fs.set_contents(PathBuf::from("/site/.DS_Store"), ds_store(&["build"]));
+        fs.set_contents(
+            PathBuf::from("/site/build/.DS_Store"),
+            ds_store(&["app.js"]),
+        );
+        let auditor = LeakAuditor::new(&fs, &ignore).unwrap();
+
+        let report = auditor.audit(Path::new("/site")).await.unwrap();
+
+        // The ignored directory is still audited
+        assert_eq!(report.files.len(), 2);
+        assert!(report
+            .files
+            .iter()
+            .all(|f| f.leaks.len() == 1 && f.leaks[0].reasons == vec![LeakReason::Ignored]));
+    }
+
+    #[tokio::test]
+    async fn test_audit_records_parse_errors() {
+        let fs = MockFileSystem::with_files(vec![PathBuf::from("/site/.DS_Store")]);
+        fs.set_contents(PathBuf::from("/site/.DS_Store"), b"garbage".to_vec());
+        let auditor = LeakAuditor::new(&fs, &[]).unwrap();
+
+        let report = auditor.audit(Path::new("/site")).await.unwrap();
+
+        assert!(report.files[0].error.is_some());
+        assert_eq!(report.total_leaks(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_audit_treats_path_like_names_as_absent() {
+        let fs = MockFileSystem::with_files(vec![
+            PathBuf::from("/site/.DS_Store"),
+            PathBuf::from("/etc/passwd"),
+        ]);
+        fs.set_contents(
+            PathBuf::from("/site/.DS_Store"),
+            ds_store(&["../etc/passwd"]),
+        );
+        let auditor = LeakAuditor::new(&fs, &[]).unwrap();
+
+        let report = auditor.audit(Path::new("/site")).await.unwrap();
+
+        assert_eq!(report.files[0].leaks[0].reasons, vec![LeakReason::Absent]);
+    }
+}
@@ -1,15 +1,39 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashSet};
+use std::ffi::{OsStr, OsString};
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 use crate::fs::FileSystem;
-use crate::models::CleanResult;
+use crate::models::{
+    Action, CleanConfig, CleanResult, ConditionalPattern, DeletionLimits, DirMatch, EntryKind,
+    EscapePath, Explanation, FileMatch, FileMetadata, NameVerdict, Outcome, PatternCheck, RenameTo,
+    RuleCheck, RuleConfig, ScanReport, SkipReason, Verdict, XattrMatch,
+};
 
-use super::PatternMatcher;
+use super::editor::{self, EditorArtifact, Owner};
+use super::patterns::{lossy_text, Rule};
+use super::rules::{CompiledRule, Facts, Needs, CONTENT_SCAN_LEN};
+use super::server_names::decode_server_name;
+use super::{appledouble, PatternMatcher, Signature};
 
 pub struct Cleaner<F: FileSystem> {
     fs: F,
     matcher: PatternMatcher,
+    dir_matcher: PatternMatcher,
+    xattr_matcher: PatternMatcher,
     ignore_patterns: Vec<String>,
+    limits: DeletionLimits,
+    verify_signatures: bool,
+    apple_double: bool,
+    prune_empty_dirs: bool,
+    decode_names: bool,
+    ignore_case: bool,
+    skip_in_use: bool,
+    editor_artifacts: bool,
+    rules: Vec<CompiledRule>,
+    quarantine_dir: Option<PathBuf>,
 }
 
 impl<F: FileSystem> Cleaner<F> {
@@ -18,151 +42,3551 @@ impl<F: FileSystem> Cleaner<F> {
         Ok(Self {
             fs,
             matcher,
+            dir_matcher: PatternMatcher::new(&[])?,
+            xattr_matcher: PatternMatcher::new(&[])?,
             ignore_patterns,
+            limits: DeletionLimits::default(),
+            verify_signatures: false,
+            apple_double: false,
+            prune_empty_dirs: false,
+            decode_names: false,
+            ignore_case: false,
+            skip_in_use: false,
+            editor_artifacts: false,
+            rules: Vec::new(),
+            quarantine_dir: None,
         })
     }
 
+    /// Build a cleaner with every option from `config` applied
+    pub fn from_config(fs: F, config: &CleanConfig) -> Result<Self> {
+        Self::new(fs, &config.patterns, config.ignore_patterns.clone())?
+            .with_ignore_case(config.ignore_case)?
+            .with_limits(config.limits.clone())
+            .with_signature_checks(config.verify_signatures)
+            .with_apple_double(config.apple_double)
+            .with_prune_empty_dirs(config.prune_empty_dirs)
+            .with_name_decoding(config.decode_names)
+            .with_in_use_check(config.skip_in_use)
+            .with_editor_artifacts(config.editor_artifacts)
+            .with_quarantine_dir(config.quarantine_dir.clone())
+            .with_rules(&config.rules)?
+            .with_dir_patterns(&config.dir_patterns)?
+            .with_validated_patterns(&config.validated_patterns)?
+            .with_conditional_patterns(&config.conditional_patterns)?
+            .with_xattr_patterns(&config.xattr_patterns)
+    }
+
+    /// After removing files, remove directories this run left empty.
+    /// Directories that were already empty are never touched.
+    pub fn with_prune_empty_dirs(mut self, prune_empty_dirs: bool) -> Self {
+        self.prune_empty_dirs = prune_empty_dirs;
+        self
+    }
+
+    /// Match `patterns` only when the file's header carries the signature known for
+    /// the pattern, e.g. `*:Zone.Identifier` files starting with `[ZoneTransfer]`
+    pub fn with_validated_patterns(mut self, patterns: &[String]) -> Result<Self> {
+        let matcher = std::mem::replace(&mut self.matcher, PatternMatcher::new(&[])?);
+        self.matcher = matcher.with_validated(patterns)?;
+        Ok(self)
+    }
+
+    /// Match `patterns` only when the file's age and size meet the pattern's
+    /// conditions, e.g. `*.tmp` files older than a day
+    pub fn with_conditional_patterns(mut self, patterns: &[ConditionalPattern]) -> Result<Self> {
+        let matcher = std::mem::replace(&mut self.matcher, PatternMatcher::new(&[])?);
+        self.matcher = matcher.with_conditions(patterns)?;
+        Ok(self)
+    }
+
+    /// Check `rules` in order before any pattern; the first rule a file meets
+    /// decides its action
+    pub fn with_rules(mut self, rules: &[RuleConfig]) -> Result<Self> {
+        self.rules = rules
+            .iter()
+            .map(CompiledRule::compile)
+            .collect::<Result<_>>()?;
+        Ok(self)
+    }
+
+    /// Move quarantined files below `dir`, mirroring their absolute path
+    pub fn with_quarantine_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.quarantine_dir = dir;
+        self
+    }
+
+    /// Delete vim swap files and Emacs auto-save and lock files only when the
+    /// editing session that owns them ran on this machine and has exited;
+    /// auto-saves without a lock are kept
+    pub fn with_editor_artifacts(mut self, editor_artifacts: bool) -> Self {
+        self.editor_artifacts = editor_artifacts;
+        self
+    }
+
+    /// Just before removal, skip files that any process holds open
+    pub fn with_in_use_check(mut self, skip_in_use: bool) -> Self {
+        self.skip_in_use = skip_in_use;
+        self
+    }
+
+    /// Also match names as a Mac client sees them on Samba and netatalk shares,
+    /// e.g. `:2eDS_Store` as `.DS_Store`
+    pub fn with_name_decoding(mut self, decode_names: bool) -> Self {
+        self.decode_names = decode_names;
+        self
+    }
+
+    /// Remove directories whose name matches one of `patterns`, with everything in them
+    pub fn with_dir_patterns(mut self, patterns: &[String]) -> Result<Self> {
+        self.dir_matcher = PatternMatcher::new(patterns)?.with_ignore_case(self.ignore_case)?;
+        Ok(self)
+    }
+
+    /// Match the patterns given to `new`, directory patterns and validated patterns
+    /// regardless of case. Conditional patterns choose for themselves.
+    pub fn with_ignore_case(mut self, ignore_case: bool) -> Result<Self> {
+        self.ignore_case = ignore_case;
+        let matcher = std::mem::replace(&mut self.matcher, PatternMatcher::new(&[])?);
+        self.matcher = matcher.with_ignore_case(ignore_case)?;
+        let dir_matcher = std::mem::replace(&mut self.dir_matcher, PatternMatcher::new(&[])?);
+        self.dir_matcher = dir_matcher.with_ignore_case(ignore_case)?;
+        Ok(self)
+    }
+
+    /// Cap how much a single clean may delete
+    pub fn with_limits(mut self, limits: DeletionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Check the header of well-known artifacts (`.DS_Store`, `._*`, `Thumbs.db`)
+    /// before deleting them, skipping files that don't look like the real thing
+    pub fn with_signature_checks(mut self, verify_signatures: bool) -> Self {
+        self.verify_signatures = verify_signatures;
+        self
+    }
+
+    /// Treat every `._name` as an AppleDouble: delete it only when `name` is gone
+    /// or it holds no metadata, and report paired files that carry data
+    pub fn with_apple_double(mut self, apple_double: bool) -> Self {
+        self.apple_double = apple_double;
+        self
+    }
+
+    /// Strip extended attributes whose name matches one of `patterns` from files
+    /// that are kept, found during the same walk
+    pub fn with_xattr_patterns(mut self, patterns: &[String]) -> Result<Self> {
+        self.xattr_matcher = PatternMatcher::new(patterns)?;
+        Ok(self)
+    }
+
     /// Scan and return matching files
     pub async fn scan(&self, root: &Path) -> Result<Vec<PathBuf>> {
+        let matches = self.scan_matches(root).await?;
+        Ok(matches.into_iter().map(|m| m.path).collect())
+    }
+
+    /// Scan and return matching files along with their identity at scan time
+    pub async fn scan_matches(&self, root: &Path) -> Result<Vec<FileMatch>> {
+        Ok(self.scan_report(root).await?.matches)
+    }
+
+    /// Scan, returning files and directories to clean and matched files that must be kept
+    pub async fn scan_report(&self, root: &Path) -> Result<ScanReport> {
+        let mut report = ScanReport {
+            dirs: self.scan_dirs(root).await?,
+            ..Default::default()
+        };
+        let matched_dirs: BTreeSet<PathBuf> = report.dirs.iter().map(|d| d.path.clone()).collect();
         let all_files = self.fs.walk_dir(root, &self.ignore_patterns).await?;
-        let matching: Vec<PathBuf> = all_files
-            .into_iter()
-            .filter(|path| {
-                path.file_name()
-                    .and_then(|n| n.to_str())
-                    .map(|name| self.matcher.matches(name))
-                    .unwrap_or(false)
-            })
-            .collect();
-        Ok(matching)
+        for path in all_files {
+            // Files inside a matched directory go with it
+            if within_any(&matched_dirs, &path) {
+                continue;
+            }
+            self.scan_file(root, path, &mut report).await;
+        }
+
+        // Emacs locks are symlinks, which the file walk leaves out
+        if self.editor_artifacts {
+            for path in self.fs.walk_symlinks(root, &self.ignore_patterns).await? {
+                if within_any(&matched_dirs, &path) {
+                    continue;
+                }
+                let Some(name) = path.file_name().map(lossy_text) else {
+                    continue;
+                };
+                if EditorArtifact::from_name(&name) == Some(EditorArtifact::EmacsLock)
+                    && self
+                        .scan_editor_artifact(&path, EditorArtifact::EmacsLock, &mut report)
+                        .await
+                {
+                    self.push_match(path, Action::Delete, None, &mut report)
+                        .await;
+                }
+            }
+        }
+        Ok(report)
     }
 
-    /// Clean files (delete or dry-run)
-    pub async fn clean(&self, root: &Path, dry_run: bool) -> Result<CleanResult> {
-        let files = self.scan(root).await?;
-        let mut result = CleanResult::new(files.len(), dry_run);
+    /// Decide what to do with one regular file found by the walk
+    async fn scan_file(&self, root: &Path, path: PathBuf, report: &mut ScanReport) {
+        let Some(file_name) = path.file_name() else {
+            return;
+        };
+        let text = lossy_text(file_name);
+        let name = text.as_ref();
 
-        for path in files {
-            if dry_run {
-                tracing::info!(path = %path.display(), "Would delete");
-                result.files_deleted += 1;
-            } else {
-                match self.fs.remove_file(&path).await {
-                    Ok(()) => {
-                        tracing::info!(path = %path.display(), "Deleted");
-                        result.files_deleted += 1;
+        // AppleDouble files and editor artifacts are checked whatever matches them
+        let checked = if self.apple_double && appledouble::is_apple_double_name(name) {
+            match self.apple_double_skip_reason(&path).await {
+                Ok(None) => true,
+                Ok(Some(reason)) => {
+                    tracing::info!(path = %path.escaped(), reason = %reason, "Keeping AppleDouble file");
+                    report.skipped.push((path, reason));
+                    return;
+                }
+                Err(e) => {
+                    tracing::debug!(path = %path.escaped(), error = %e, "Skipping unreadable AppleDouble file");
+                    return;
+                }
+            }
+        } else if let Some(artifact) = self.editor_artifact(name) {
+            if !self.scan_editor_artifact(&path, artifact, report).await {
+                return;
+            }
+            true
+        } else {
+            false
+        };
+
+        if let Some(rule) = self.config_rule(root, &path, file_name).await {
+            self.push_match(path, rule.action(), rule.rename_to(), report)
+                .await;
+            return;
+        }
+
+        let mut rule = None;
+        if !checked {
+            rule = self.file_rule(&path, file_name).await;
+            if rule.is_none() {
+                self.scan_xattrs(path, report).await;
+                return;
+            }
+        }
+
+        let (action, rename_to) =
+            rule.map_or((Action::Delete, None), |r| (r.action(), r.rename_to()));
+        self.push_match(path, action, rename_to, report).await;
+    }
+
+    async fn push_match(
+        &self,
+        path: PathBuf,
+        action: Action,
+        rename_to: Option<&RenameTo>,
+        report: &mut ScanReport,
+    ) {
+        let rename_to = rename_to.zip(path.file_name());
+        let rename_to = rename_to.map(|(to, name)| path.with_file_name(to.apply(name)));
+        match self.fs.metadata(&path).await {
+            Ok(metadata) => report.matches.push(FileMatch {
+                action,
+                path,
+                metadata,
+                rename_to,
+            }),
+            Err(e) => {
+                tracing::debug!(path = %path.escaped(), error = %e, "Skipping unreadable match");
+            }
+        }
+    }
+
+    /// First config rule the file meets. Metadata, owner and content are
+    /// loaded only while a rule can't be decided without them.
+    async fn config_rule(&self, root: &Path, path: &Path, name: &OsStr) -> Option<&CompiledRule> {
+        if self.rules.is_empty() {
+            return None;
+        }
+        let relative = rule_path(path.strip_prefix(root).ok()?);
+
+        let mut facts = Facts::new(name, &*relative, SystemTime::now());
+        for rule in &self.rules {
+            loop {
+                match rule.evaluate(&facts) {
+                    Some(true) => {
+                        tracing::debug!(path = %path.escaped(), rule = rule.source(), "Rule matched");
+                        return Some(rule);
                     }
-                    Err(e) => {
-                        tracing::warn!(path = %path.display(), error = %e, "Failed to delete");
-                        result.files_failed.push((path, e.to_string()));
+                    Some(false) => break,
+                    None => {
+                        if !self.load_fact(path, rule.needs(), &mut facts).await {
+                            // Unreadable facts leave the rule undecided: not a match
+                            break;
+                        }
                     }
                 }
             }
         }
-        Ok(result)
+        None
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::fs::MockFileSystem;
+    /// Load the next fact a rule needs that hasn't been loaded yet.
+    /// Returns false when there is nothing left to load.
+    async fn load_fact(&self, path: &Path, needs: Needs, facts: &mut Facts<'_>) -> bool {
+        fn loaded<T>(path: &Path, result: Result<T>) -> Option<T> {
+            result
+                .map_err(
+                    |e| tracing::debug!(path = %path.escaped(), error = %e, "Rule can't read file"),
+                )
+                .ok()
+        }
 
-    #[tokio::test]
-    async fn test_cleaner_scan() {
-        let fs = MockFileSystem::with_files(vec![
-            PathBuf::from("/test/.DS_Store"),
-            PathBuf::from("/test/file.txt"),
-        ]);
-        let cleaner = Cleaner::new(fs, &[".DS_Store".to_string()], vec![]).unwrap();
+        if needs.metadata && facts.metadata.is_none() {
+            facts.metadata = Some(loaded(path, self.fs.metadata(path).await));
+        } else if needs.owner && facts.owner.is_none() {
+            facts.owner = Some(loaded(path, self.fs.owner(path).await));
+        } else if needs.content && facts.head.is_none() {
+            facts.head = Some(loaded(
+                path,
+                self.fs.read_head(path, CONTENT_SCAN_LEN).await,
+            ));
+        } else {
+            return false;
+        }
+        true
+    }
 
-        let found = cleaner.scan(Path::new("/test")).await.unwrap();
+    fn editor_artifact(&self, name: &str) -> Option<EditorArtifact> {
+        if !self.editor_artifacts {
+            return None;
+        }
+        EditorArtifact::from_name(name)
+    }
 
-        assert_eq!(found.len(), 1);
-        assert_eq!(found[0], PathBuf::from("/test/.DS_Store"));
+    /// Decide whether an editor artifact is stale and should be deleted.
+    /// Live ones are recorded as skipped; unrecognisable ones are left alone.
+    async fn scan_editor_artifact(
+        &self,
+        path: &Path,
+        artifact: EditorArtifact,
+        report: &mut ScanReport,
+    ) -> bool {
+        match self.editor_skip_reason(path, artifact).await {
+            Ok(None) => true,
+            Ok(Some(reason)) => {
+                tracing::info!(path = %path.escaped(), reason = %reason, "Keeping editor artifact");
+                report.skipped.push((path.to_path_buf(), reason));
+                false
+            }
+            Err(e) => {
+                tracing::debug!(path = %path.escaped(), error = %e, "Skipping unrecognised editor artifact");
+                false
+            }
+        }
     }
 
-    #[tokio::test]
-    async fn test_cleaner_clean() {
-        let fs = MockFileSystem::with_files(vec![
-            PathBuf::from("/test/.DS_Store"),
-            PathBuf::from("/test/file.txt"),
-        ]);
-        let fs_clone = fs.clone();
-        let cleaner = Cleaner::new(fs, &[".DS_Store".to_string()], vec![]).unwrap();
+    async fn editor_skip_reason(
+        &self,
+        path: &Path,
+        artifact: EditorArtifact,
+    ) -> Result<Option<SkipReason>> {
+        let owner = match artifact {
+            EditorArtifact::VimSwap => {
+                let head = self.fs.read_head(path, editor::VIM_HEADER_LEN).await?;
+                editor::parse_vim_swap(&head)?
+            }
+            EditorArtifact::EmacsLock => self.emacs_lock_owner(path).await?,
+            EditorArtifact::EmacsAutoSave => {
+                // Without a lock there is no owner to check, and the file may be
+                // all that is left of a crashed session's edits
+                let mut lock_name = OsString::from(".#");
+                lock_name.push(strip_affixes(path, "#", "#"));
+                let lock = path.with_file_name(lock_name);
+                if !self.fs.exists(&lock).await? {
+                    return Ok(Some(SkipReason::UnownedAutoSave));
+                }
+                self.emacs_lock_owner(&lock).await?
+            }
+        };
 
-        let result = cleaner.clean(Path::new("/test"), false).await.unwrap();
+        if owner.is_on(&self.fs.hostname().await?) {
+            if self.fs.process_alive(owner.pid).await {
+                return Ok(Some(SkipReason::LiveEditorSession(owner.to_string())));
+            }
+            return Ok(None);
+        }
+        // A process on another machine can't be checked from here
+        Ok(Some(SkipReason::LiveEditorSession(owner.to_string())))
+    }
 
-        assert_eq!(result.files_found, 1);
-        assert_eq!(result.files_deleted, 1);
-        assert!(fs_clone.was_deleted(Path::new("/test/.DS_Store")));
+    /// Emacs writes the lock as a symlink, or as a plain file where symlinks aren't supported
+    async fn emacs_lock_owner(&self, lock: &Path) -> Result<Owner> {
+        let target = match self.fs.read_link(lock).await {
+            Ok(target) => target.to_string_lossy().into_owned(),
+            Err(_) => {
+                let head = self.fs.read_head(lock, editor::EMACS_LOCK_MAX_LEN).await?;
+                String::from_utf8_lossy(&head).into_owned()
+            }
+        };
+        editor::parse_emacs_lock(&target)
     }
 
-    #[tokio::test]
-    async fn test_cleaner_dry_run() {
-        let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/.DS_Store")]);
-        let fs_clone = fs.clone();
-        let cleaner = Cleaner::new(fs, &[".DS_Store".to_string()], vec![]).unwrap();
+    /// Record extended attributes of a kept file that match an xattr pattern
+    async fn scan_xattrs(&self, path: PathBuf, report: &mut ScanReport) {
+        if self.xattr_matcher.is_empty() {
+            return;
+        }
 
-        let result = cleaner.clean(Path::new("/test"), true).await.unwrap();
+        match self.fs.list_xattrs(&path).await {
+            Ok(names) => {
+                let names: Vec<OsString> = names
+                    .into_iter()
+                    .filter(|n| self.xattr_matcher.matches(n))
+                    .collect();
+                if !names.is_empty() {
+                    report.xattrs.push(XattrMatch { path, names });
+                }
+            }
+            Err(e) => {
+                tracing::debug!(path = %path.escaped(), error = %e, "Skipping unreadable xattrs");
+            }
+        }
+    }
 
-        assert_eq!(result.files_deleted, 1);
-        assert!(result.dry_run);
-        assert!(!fs_clone.was_deleted(Path::new("/test/.DS_Store"))); // Not actually deleted
+    /// Find directories matching a directory pattern. Nested matches are dropped
+    /// since removing the outer directory takes them along.
+    async fn scan_dirs(&self, root: &Path) -> Result<Vec<DirMatch>> {
+        if self.dir_matcher.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut dirs: Vec<DirMatch> = Vec::new();
+        let mut matched = BTreeSet::new();
+        for path in self.fs.walk_dirs(root, &self.ignore_patterns).await? {
+            if within_any(&matched, &path) {
+                continue;
+            }
+            let Some(name) = path.file_name() else {
+                continue;
+            };
+            if self.matching_rule(&self.dir_matcher, name).is_none() {
+                continue;
+            }
+
+            match self.dir_match(path.clone()).await {
+                Ok(dir) => {
+                    matched.insert(path);
+                    dirs.push(dir);
+                }
+                Err(e) => {
+                    tracing::debug!(path = %path.escaped(), error = %e, "Skipping unreadable directory");
+                }
+            }
+        }
+        Ok(dirs)
     }
 
-    #[tokio::test]
-    async fn test_cleaner_handles_deletion_error() {
-        let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/.DS_Store")]);
-        fs.set_fail_on(PathBuf::from("/test/.DS_Store"));
+    /// Count the files below `path` and their total size
+    async fn dir_match(&self, path: PathBuf) -> Result<DirMatch> {
+        let files = self.fs.walk_dir(&path, &[]).await?;
+        let mut bytes = 0;
+        for file in &files {
+            bytes += self.fs.metadata(file).await?.size;
+        }
+        Ok(DirMatch {
+            path,
+            files: files.len(),
+            bytes,
+        })
+    }
 
-        let cleaner = Cleaner::new(fs, &[".DS_Store".to_string()], vec![]).unwrap();
+    /// An AppleDouble is junk when its base file is gone or it holds no metadata.
+    /// Files that aren't really AppleDouble are always kept.
+    async fn apple_double_skip_reason(&self, path: &Path) -> Result<Option<SkipReason>> {
+        let head = self.fs.read_head(path, appledouble::INSPECT_LEN).await?;
+        let is_empty = match appledouble::is_empty(&head) {
+            Ok(is_empty) => is_empty,
+            Err(_) => {
+                return Ok(Some(SkipReason::SignatureMismatch(
+                    Signature::AppleDouble.description().to_string(),
+                )))
+            }
+        };
+        if is_empty
+            || !self
+                .fs
+                .exists(&path.with_file_name(strip_affixes(path, "._", "")))
+                .await?
+        {
+            return Ok(None);
+        }
+        Ok(Some(SkipReason::PairedAppleDouble))
+    }
 
-        let result = cleaner.clean(Path::new("/test"), false).await.unwrap();
+    /// Work out what a cleanup of `root` would do with `path`, and why
+    pub async fn explain(&self, root: &Path, path: &Path) -> Result<Explanation> {
+        if !path.starts_with(root) {
+            bail!("{} is not under {}", path.escaped(), root.escaped());
+        }
+        let kind = if self.fs.read_link(path).await.is_ok() {
+            EntryKind::Symlink
+        } else if self
+            .fs
+            .is_dir(path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.escaped()))?
+        {
+            EntryKind::Directory
+        } else {
+            EntryKind::File
+        };
 
-        assert_eq!(result.files_found, 1);
-        assert_eq!(result.files_deleted, 0);
-        assert_eq!(result.files_failed.len(), 1);
-        assert!(result.files_failed[0].1.contains("Permission denied"));
+        let Ancestry {
+            ignored_by,
+            removed_with,
+        } = self.ancestry(root, path, kind == EntryKind::Directory);
+
+        let file_name = path.file_name().unwrap_or_default();
+        let name = lossy_text(file_name);
+        let (rules, patterns) = match kind {
+            EntryKind::File => (
+                self.rule_checks(root, path, file_name).await,
+                self.pattern_checks(path, file_name).await?,
+            ),
+            _ => Default::default(),
+        };
+        let handled_as = match kind {
+            EntryKind::File if self.apple_double && appledouble::is_apple_double_name(&name) => {
+                Some("AppleDouble file".to_string())
+            }
+            EntryKind::File | EntryKind::Symlink => self
+                .editor_artifact(&name)
+                .filter(|a| (*a == EditorArtifact::EmacsLock) == (kind == EntryKind::Symlink))
+                .map(|a| a.description().to_string()),
+            EntryKind::Directory => None,
+        };
+
+        let mut report = ScanReport::default();
+        let verdict = if ignored_by.is_some() {
+            Verdict::Keep("the scan never enters ignored directories".to_string())
+        } else if removed_with.is_some() {
+            Verdict::Act {
+                action: Action::Delete,
+                target: None,
+            }
+        } else {
+            match kind {
+                EntryKind::Directory => Verdict::Keep("no directory pattern matches".to_string()),
+                EntryKind::Symlink if handled_as.is_some() => {
+                    if self
+                        .scan_editor_artifact(path, EditorArtifact::EmacsLock, &mut report)
+                        .await
+                    {
+                        self.push_match(path.to_path_buf(), Action::Delete, None, &mut report)
+                            .await;
+                    }
+                    self.verdict(report, "not a recognisable Emacs lock").await
+                }
+                EntryKind::Symlink => {
+                    Verdict::Keep("symlinks other than Emacs locks are left alone".to_string())
+                }
+                EntryKind::File => {
+                    self.scan_file(root, path.to_path_buf(), &mut report).await;
+                    let unmatched = if patterns.is_empty() {
+                        "no pattern or rule matches"
+                    } else {
+                        "no matching pattern's conditions are met"
+                    };
+                    self.verdict(report, unmatched).await
+                }
+            }
+        };
+
+        Ok(Explanation {
+            path: path.to_path_buf(),
+            kind,
+            ignored_by,
+            removed_with,
+            rules,
+            patterns,
+            handled_as,
+            verdict,
+        })
     }
 
-    #[tokio::test]
-    async fn test_cleaner_with_ignore_patterns() {
-        let fs = MockFileSystem::with_files(vec![
-            PathBuf::from("/test/.DS_Store"),
-            PathBuf::from("/test/node_modules/.DS_Store"),
-            PathBuf::from("/test/src/.DS_Store"),
-        ]);
-        let cleaner = Cleaner::new(
-            fs,
-            &[".DS_Store".to_string()],
-            vec!["node_modules".to_string()],
-        )
-        .unwrap();
+    /// What a cleanup would do with a file at relative `path`, judged by the path alone
+    /// without reading the file. Outcomes that depend on the file's age, size,
+    /// owner or content are reported as such.
+    pub fn name_verdict(&self, path: &Path) -> NameVerdict {
+        // As the scan would see it: `./a/b` and `a/b` are the same file
+        let relative: PathBuf = path
+            .components()
+            .filter(|c| matches!(c, std::path::Component::Normal(_)))
+            .collect();
+        let relative = relative.as_path();
+        let Ancestry {
+            ignored_by,
+            removed_with,
+        } = self.ancestry(Path::new(""), relative, false);
+        let file_name = relative.file_name().unwrap_or_default();
+        let name = lossy_text(file_name);
 
-        let found = cleaner.scan(Path::new("/test")).await.unwrap();
+        let (outcome, reason) = if let Some((_, entry)) = ignored_by {
+            (Outcome::Keep, format!("ignored: {}", entry))
+        } else if let Some((dir, pattern)) = removed_with {
+            let reason = format!("dir pattern '{}' on {}", pattern, dir.escaped());
+            (Outcome::Act(Action::Delete), reason)
+        } else if let Some(decided) = self.name_rule(relative, file_name) {
+            let decided = self.unless_live(&name, decided);
+            self.unless_signature_fails(relative, decided)
+        } else if self.apple_double && appledouble::is_apple_double_name(&name) {
+            let reason = "AppleDouble file, depends on its contents".to_string();
+            (Outcome::Depends(Action::Delete), reason)
+        } else if let Some(artifact) = self.editor_artifact(&name) {
+            let reason = format!("{}, depends on its editor", artifact.description());
+            (Outcome::Depends(Action::Delete), reason)
+        } else {
+            let decoded = self.decoded_name(file_name);
+            let rule = self
+                .matcher
+                .matching_rules(file_name)
+                .chain(decoded.iter().flat_map(|d| self.matcher.matching_rules(d)))
+                .next();
+            match rule {
+                Some(rule) if rule.conditions().is_empty() => {
+                    let reason = format!("{} '{}'", rule.kind(), rule.pattern());
+                    self.unless_signature_fails(relative, (Outcome::Act(rule.action()), reason))
+                }
+                Some(rule) => {
+                    let reason = format!(
+                        "{} '{}' if {}",
+                        rule.kind(),
+                        rule.pattern(),
+                        rule.conditions()
+                    );
+                    (Outcome::Depends(rule.action()), reason)
+                }
+                None => (Outcome::Keep, "no match".to_string()),
+            }
+        };
 
-        assert_eq!(found.len(), 2);
-        assert!(found.contains(&PathBuf::from("/test/.DS_Store")));
-        assert!(found.contains(&PathBuf::from("/test/src/.DS_Store")));
-        assert!(!found.contains(&PathBuf::from("/test/node_modules/.DS_Store")));
+        NameVerdict {
+            path: path.to_path_buf(),
+            outcome,
+            reason,
+        }
     }
 
-    #[tokio::test]
-    async fn test_cleaner_ignores_ds_store_in_ignored_dir() {
-        let fs = MockFileSystem::with_files(vec![
-            PathBuf::from("/test/.DS_Store"),
-            PathBuf::from("/test/.git/objects/.DS_Store"),
-        ]);
-        let fs_clone = fs.clone();
-        let cleaner =
-            Cleaner::new(fs, &[".DS_Store".to_string()], vec![".git".to_string()]).unwrap();
+    /// First config rule decided by the name and path alone, or the first one
+    /// that can't be decided without reading the file
+    fn name_rule(&self, relative: &Path, name: &OsStr) -> Option<(Outcome, String)> {
+        let path = rule_path(relative);
+        let facts = Facts::new(name, &*path, SystemTime::now());
+        for rule in &self.rules {
+            match rule.evaluate(&facts) {
+                Some(true) => {
+                    let reason = format!("rule '{}'", rule.source());
+                    return Some((Outcome::Act(rule.action()), reason));
+                }
+                Some(false) => {}
+                None => {
+                    let reason = format!("rule '{}' if the file meets it", rule.source());
+                    return Some((Outcome::Depends(rule.action()), reason));
+                }
+            }
+        }
+        None
+    }
 
-        let result = cleaner.clean(Path::new("/test"), false).await.unwrap();
+    /// Qualify a decided outcome with the AppleDouble or editor check the scan
+    /// runs on the file whatever matched it
+    fn unless_live(&self, name: &str, (outcome, reason): (Outcome, String)) -> (Outcome, String) {
+        let depends_on = if self.apple_double && appledouble::is_apple_double_name(name) {
+            "its contents"
+        } else if self.editor_artifact(name).is_some() {
+            "its editor"
+        } else {
+            return (outcome, reason);
+        };
+        match outcome {
+            Outcome::Act(action) => {
+                let reason = format!("{}, depends on {}", reason, depends_on);
+                (Outcome::Depends(action), reason)
+            }
+            outcome => (outcome, reason),
+        }
+    }
 
-        assert_eq!(result.files_found, 1);
-        assert_eq!(result.files_deleted, 1);
-        assert!(fs_clone.was_deleted(Path::new("/test/.DS_Store")));
-        assert!(!fs_clone.was_deleted(Path::new("/test/.git/objects/.DS_Store")));
+    /// Qualify a decided outcome with the signature checked just before removal
+    fn unless_signature_fails(
+        &self,
+        relative: &Path,
+        (outcome, reason): (Outcome, String),
+    ) -> (Outcome, String) {
+        match (outcome, self.signature_for(relative)) {
+            (Outcome::Act(action), Some(signature)) if action != Action::ReportOnly => {
+                let reason = format!("{} if content is {}", reason, signature.description());
+                (Outcome::Depends(action), reason)
+            }
+            decided => (decided.0, reason),
+        }
+    }
+
+    /// Walk down from `root` to `path` as the scan does: ignored directories
+    /// aren't entered, and the first directory matching a dir pattern goes whole.
+    /// `path` itself only counts when it is a directory.
+    fn ancestry(&self, root: &Path, path: &Path, is_dir: bool) -> Ancestry {
+        let mut ancestry = Ancestry::default();
+        let Ok(relative) = path.strip_prefix(root) else {
+            return ancestry;
+        };
+        let mut dirs: Vec<_> = relative
+            .components()
+            .filter(|c| matches!(c, std::path::Component::Normal(_)))
+            .collect();
+        if !is_dir {
+            dirs.pop();
+        }
+
+        let mut dir = root.to_path_buf();
+        for component in dirs {
+            dir.push(component);
+            let name = component.as_os_str();
+            if let Some(entry) = self.ignore_patterns.iter().find(|p| name == OsStr::new(p)) {
+                ancestry.ignored_by = Some((dir, entry.clone()));
+                break;
+            }
+            if ancestry.removed_with.is_none() {
+                if let Some((pattern, _)) = self.matching_rule(&self.dir_matcher, name) {
+                    ancestry.removed_with = Some((dir.clone(), pattern.to_string()));
+                }
+            }
+        }
+        ancestry
+    }
+
+    /// Every config rule evaluated against the file with all its facts loaded
+    async fn rule_checks(&self, root: &Path, path: &Path, name: &OsStr) -> Vec<RuleCheck> {
+        if self.rules.is_empty() {
+            return Vec::new();
+        }
+        let relative = rule_path(path.strip_prefix(root).unwrap_or(Path::new("")));
+
+        let mut facts = Facts::new(name, &*relative, SystemTime::now());
+        let everything = Needs {
+            metadata: true,
+            owner: true,
+            content: true,
+        };
+        while self.load_fact(path, everything, &mut facts).await {}
+
+        self.rules
+            .iter()
+            .map(|rule| RuleCheck {
+                when: rule.source().to_string(),
+                action: rule.action(),
+                matched: rule.evaluate(&facts),
+            })
+            .collect()
+    }
+
+    /// Every pattern matching the name as stored or decoded, with how the file
+    /// fares against its conditions and signature
+    async fn pattern_checks(&self, path: &Path, name: &OsStr) -> Result<Vec<PatternCheck>> {
+        let decoded = self.decoded_name(name);
+        let rules = self
+            .matcher
+            .matching_rules(name)
+            .map(|rule| (rule, None))
+            .chain(decoded.iter().flat_map(|d| {
+                self.matcher
+                    .matching_rules(d)
+                    .map(move |rule| (rule, Some(d.clone())))
+            }));
+
+        let mut checks = Vec::new();
+        let mut metadata = None;
+        for (rule, decoded_name) in rules {
+            let conditions = if rule.conditions().is_empty() {
+                Vec::new()
+            } else {
+                if metadata.is_none() {
+                    metadata = Some(self.fs.metadata(path).await?);
+                }
+                let metadata = metadata.as_ref().expect("fetched above");
+                rule.conditions().checks(metadata, SystemTime::now())
+            };
+            let signature = match rule.signature() {
+                Some(signature) => Some((
+                    signature.description().to_string(),
+                    self.has_signature(path, signature).await,
+                )),
+                None => None,
+            };
+            checks.push(PatternCheck {
+                pattern: rule.pattern().to_string(),
+                kind: rule.kind().to_string(),
+                decoded_name,
+                action: rule.action(),
+                conditions,
+                signature,
+            });
+        }
+        Ok(checks)
+    }
+
+    /// What applying `report`, scanned from a single path, would do with it
+    async fn verdict(&self, mut report: ScanReport, unmatched: &str) -> Verdict {
+        if let Some(m) = report.matches.pop() {
+            if m.action != Action::ReportOnly {
+                let open_files = match self.open_files().await {
+                    Ok(ids) => ids,
+                    Err(e) => return Verdict::Keep(format!("cannot check open files: {}", e)),
+                };
+                match self
+                    .check_before_removal(&m.path, &m.metadata, true, open_files.as_ref())
+                    .await
+                {
+                    Ok(None) => {}
+                    Ok(Some(reason)) => return Verdict::Skip(reason),
+                    Err(e) => return Verdict::Keep(format!("unreadable: {}", e)),
+                }
+            }
+            let target = match m.action {
+                Action::Quarantine => self.quarantine_target(&m.path),
+                Action::Rename => m.rename_to,
+                Action::Delete | Action::ReportOnly => None,
+            };
+            return Verdict::Act {
+                action: m.action,
+                target,
+            };
+        }
+        if let Some((_, reason)) = report.skipped.pop() {
+            return Verdict::Skip(reason);
+        }
+        if let Some(xattrs) = report.xattrs.pop() {
+            return Verdict::StripXattrs(xattrs.names);
+        }
+        Verdict::Keep(unmatched.to_string())
+    }
+
+    /// Clean files (delete or dry-run)
+    pub async fn clean(&self, root: &Path, dry_run: bool) -> Result<CleanResult> {
+        let report = self.scan_report(root).await?;
+        self.check_limits(&report.matches, &report.dirs, dry_run)?;
+        Ok(self.apply(root, report, dry_run).await)
+    }
+
+    /// Remove everything a scan of `root` selected: files first, then whole directories
+    pub async fn apply(&self, root: &Path, report: ScanReport, dry_run: bool) -> CleanResult {
+        let ScanReport {
+            matches,
+            dirs,
+            xattrs,
+            skipped,
+        } = report;
+        let removed: Vec<PathBuf> = matches
+            .iter()
+            .filter(|m| m.action.removes_file())
+            .map(|m| m.path.clone())
+            .chain(dirs.iter().map(|d| d.path.clone()))
+            .collect();
+
+        let mut result = self.remove_matches(matches, dry_run).await;
+        self.remove_dirs(dirs, dry_run, &mut result).await;
+        self.strip_xattrs(xattrs, dry_run, &mut result).await;
+        if self.prune_empty_dirs {
+            self.prune_empty_dirs(root, &removed, dry_run, &mut result)
+                .await;
+        }
+        result.files_found += skipped.len();
+        result.files_skipped.extend(skipped);
+        result
+    }
+
+    /// Enforce deletion limits before anything is removed.
+    /// In dry-run mode a violation is only logged.
+    pub fn check_limits(
+        &self,
+        matches: &[FileMatch],
+        dirs: &[DirMatch],
+        dry_run: bool,
+    ) -> Result<()> {
+        match self.limits.check(matches, dirs) {
+            Err(e) if dry_run => {
+                tracing::warn!(error = %e, "Deletion limits would be exceeded");
+                Ok(())
+            }
+            other => other,
+        }
+    }
+
+    /// Delete, quarantine or rename previously scanned files, skipping any whose identity
+    /// changed since the scan, whose contents fail signature verification or, when enabled,
+    /// that are open. Files matched by a report-only rule are only logged.
+    pub async fn remove_matches(&self, matches: Vec<FileMatch>, dry_run: bool) -> CleanResult {
+        let mut result = CleanResult::new(matches.len(), dry_run);
+
+        let (reported, matches): (Vec<_>, Vec<_>) = matches
+            .into_iter()
+            .partition(|m| m.action == Action::ReportOnly);
+        for m in reported {
+            tracing::info!(path = %m.path.escaped(), "Matched (report only)");
+            result.files_reported += 1;
+        }
+
+        let open_files = if self.skip_in_use && !matches.is_empty() {
+            match self.fs.open_file_ids().await {
+                Ok(ids) => Some(ids),
+                Err(e) => {
+                    // Without knowing which files are open, none is safe to delete
+                    tracing::warn!(error = %e, "Failed to list open files");
+                    let error = format!("cannot check open files: {}", e);
+                    result
+                        .files_failed
+                        .extend(matches.into_iter().map(|m| (m.path, error.clone())));
+                    return result;
+                }
+            }
+        } else {
+            None
+        };
+
+        for FileMatch {
+            action,
+            path,
+            metadata,
+            rename_to,
+        } in matches
+        {
+            let check = self
+                .check_before_removal(&path, &metadata, dry_run, open_files.as_ref())
+                .await;
+            match check {
+                Ok(None) => {}
+                Ok(Some(reason)) => {
+                    tracing::warn!(path = %path.escaped(), reason = %reason, "Skipping file");
+                    result.files_skipped.push((path, reason));
+                    continue;
+                }
+                Err(e) => {
+                    tracing::warn!(path = %path.escaped(), error = %e, "Failed to delete");
+                    result.files_failed.push((path, e.to_string()));
+                    continue;
+                }
+            }
+
+            match action {
+                Action::Quarantine => {
+                    self.quarantine(path, dry_run, &mut result).await;
+                    continue;
+                }
+                Action::Rename => {
+                    self.rename(path, rename_to, dry_run, &mut result).await;
+                    continue;
+                }
+                Action::Delete | Action::ReportOnly => {}
+            }
+
+            if dry_run {
+                tracing::info!(path = %path.escaped(), "Would delete");
+                result.files_deleted += 1;
+                continue;
+            }
+
+            match self.fs.remove_file(&path).await {
+                Ok(()) => {
+                    tracing::info!(path = %path.escaped(), "Deleted");
+                    result.files_deleted += 1;
+                }
+                Err(e) => {
+                    tracing::warn!(path = %path.escaped(), error = %e, "Failed to delete");
+                    result.files_failed.push((path, e.to_string()));
+                }
+            }
+        }
+        result
+    }
+
+    /// Move a file below the quarantine directory, mirroring its absolute path
+    /// so files from different roots never collide
+    async fn quarantine(&self, path: PathBuf, dry_run: bool, result: &mut CleanResult) {
+        let Some(target) = self.quarantine_target(&path) else {
+            tracing::warn!(path = %path.escaped(), "No quarantine directory configured");
+            result
+                .files_failed
+                .push((path, "no quarantine directory configured".to_string()));
+            return;
+        };
+
+        if dry_run {
+            tracing::info!(path = %path.escaped(), to = %target.escaped(), "Would quarantine");
+            result.files_quarantined += 1;
+            return;
+        }
+
+        match self.move_file(&path, &target).await {
+            Ok(()) => {
+                tracing::info!(path = %path.escaped(), to = %target.escaped(), "Quarantined");
+                result.files_quarantined += 1;
+            }
+            Err(e) => {
+                tracing::warn!(path = %path.escaped(), error = %e, "Failed to quarantine");
+                result.files_failed.push((path, e.to_string()));
+            }
+        }
+    }
+
+    /// Where `path` goes when quarantined
+    fn quarantine_target(&self, path: &Path) -> Option<PathBuf> {
+        let mirrored: PathBuf = path
+            .components()
+            .filter(|c| matches!(c, std::path::Component::Normal(_)))
+            .collect();
+        Some(self.quarantine_dir.as_ref()?.join(mirrored))
+    }
+
+    /// Rename a file to the name chosen at scan time
+    async fn rename(
+        &self,
+        path: PathBuf,
+        target: Option<PathBuf>,
+        dry_run: bool,
+        result: &mut CleanResult,
+    ) {
+        let Some(target) = target else {
+            tracing::warn!(path = %path.escaped(), "No new name recorded");
+            result
+                .files_failed
+                .push((path, "no new name recorded".to_string()));
+            return;
+        };
+
+        if dry_run {
+            tracing::info!(path = %path.escaped(), to = %target.escaped(), "Would rename");
+            result.files_renamed += 1;
+            return;
+        }
+
+        match self.move_file(&path, &target).await {
+            Ok(()) => {
+                tracing::info!(path = %path.escaped(), to = %target.escaped(), "Renamed");
+                result.files_renamed += 1;
+            }
+            Err(e) => {
+                tracing::warn!(path = %path.escaped(), error = %e, "Failed to rename");
+                result.files_failed.push((path, e.to_string()));
+            }
+        }
+    }
+
+    /// Move a file to `target`, creating its directory but never replacing a file there
+    async fn move_file(&self, path: &Path, target: &Path) -> Result<()> {
+        if self.fs.exists(target).await? {
+            anyhow::bail!("{} already exists", target.escaped());
+        }
+        if let Some(parent) = target.parent() {
+            self.fs.create_dir_all(parent).await?;
+        }
+        self.fs.rename(path, target).await
+    }
+
+    /// Recursively delete previously scanned directories, skipping any whose
+    /// contents changed since the scan
+    async fn remove_dirs(&self, dirs: Vec<DirMatch>, dry_run: bool, result: &mut CleanResult) {
+        result.dirs_found += dirs.len();
+
+        for dir in dirs {
+            if dry_run {
+                tracing::info!(path = %dir.path.escaped(), files = dir.files, bytes = dir.bytes, "Would delete directory");
+                result.dirs_deleted.push(dir);
+                continue;
+            }
+
+            match self.dir_match(dir.path.clone()).await {
+                Ok(current) if current == dir => {}
+                Ok(_) => {
+                    let reason = SkipReason::ModifiedSinceScan;
+                    tracing::warn!(path = %dir.path.escaped(), reason = %reason, "Skipping directory");
+                    result.files_skipped.push((dir.path, reason));
+                    continue;
+                }
+                Err(e) => {
+                    tracing::warn!(path = %dir.path.escaped(), error = %e, "Failed to delete directory");
+                    result.files_failed.push((dir.path, e.to_string()));
+                    continue;
+                }
+            }
+
+            match self.fs.remove_dir_all(&dir.path).await {
+                Ok(()) => {
+                    tracing::info!(path = %dir.path.escaped(), files = dir.files, bytes = dir.bytes, "Deleted directory");
+                    result.dirs_deleted.push(dir);
+                }
+                Err(e) => {
+                    tracing::warn!(path = %dir.path.escaped(), error = %e, "Failed to delete directory");
+                    result.files_failed.push((dir.path, e.to_string()));
+                }
+            }
+        }
+    }
+
+    /// Remove previously scanned extended attributes from kept files
+    async fn strip_xattrs(&self, xattrs: Vec<XattrMatch>, dry_run: bool, result: &mut CleanResult) {
+        for XattrMatch { path, names } in xattrs {
+            result.xattrs_found += names.len();
+            for name in names {
+                if dry_run {
+                    tracing::info!(path = %path.escaped(), xattr = %name.escaped(), "Would remove xattr");
+                    result.xattrs_removed += 1;
+                    continue;
+                }
+
+                match self.fs.remove_xattr(&path, &name).await {
+                    Ok(()) => {
+                        tracing::info!(path = %path.escaped(), xattr = %name.escaped(), "Removed xattr");
+                        result.xattrs_removed += 1;
+                    }
+                    Err(e) => {
+                        tracing::warn!(path = %path.escaped(), xattr = %name.escaped(), error = %e, "Failed to remove xattr");
+                        result
+                            .files_failed
+                            .push((path.clone(), format!("xattr {}: {}", name.escaped(), e)));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Remove directories under `root` emptied by deleting `removed`, deepest first.
+    /// Only ancestors of removed entries are candidates, so directories that were
+    /// empty before the run stay. The root and ignored directories are never pruned.
+    async fn prune_empty_dirs(
+        &self,
+        root: &Path,
+        removed: &[PathBuf],
+        dry_run: bool,
+        result: &mut CleanResult,
+    ) {
+        // In dry-run mode nothing is gone yet, so emptiness is judged against
+        // what the cleanup would have removed
+        let kept: HashSet<&PathBuf> = result
+            .files_skipped
+            .iter()
+            .map(|(path, _)| path)
+            .chain(result.files_failed.iter().map(|(path, _)| path))
+            .collect();
+        let mut gone: BTreeSet<PathBuf> = removed
+            .iter()
+            .filter(|path| !kept.contains(path))
+            .cloned()
+            .collect();
+
+        let mut candidates = BTreeSet::new();
+        for path in removed {
+            for dir in path.ancestors().skip(1) {
+                if dir == root || !dir.starts_with(root) {
+                    break;
+                }
+                candidates.insert(dir.to_path_buf());
+            }
+        }
+
+        // Children before parents, so a parent emptied by pruning goes too
+        let mut candidates: Vec<PathBuf> = candidates.into_iter().collect();
+        candidates.sort_by_key(|d| std::cmp::Reverse(d.components().count()));
+
+        for dir in candidates {
+            if self.is_ignored_below(root, &dir) {
+                continue;
+            }
+            let empty = if dry_run {
+                self.would_be_empty(&dir, &gone).await
+            } else {
+                self.fs.is_empty_dir(&dir).await
+            };
+            match empty {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    tracing::debug!(path = %dir.escaped(), error = %e, "Skipping unreadable directory");
+                    continue;
+                }
+            }
+
+            if dry_run {
+                tracing::info!(path = %dir.escaped(), "Would prune empty directory");
+                gone.insert(dir.clone());
+                result.dirs_pruned.push(dir);
+                continue;
+            }
+            match self.fs.remove_dir(&dir).await {
+                Ok(()) => {
+                    tracing::info!(path = %dir.escaped(), "Pruned empty directory");
+                    result.dirs_pruned.push(dir);
+                }
+                Err(e) => {
+                    tracing::warn!(path = %dir.escaped(), error = %e, "Failed to prune directory");
+                    result.files_failed.push((dir, e.to_string()));
+                }
+            }
+        }
+    }
+
+    /// Whether `dir` would be empty once everything in `gone` is removed
+    async fn would_be_empty(&self, dir: &Path, gone: &BTreeSet<PathBuf>) -> Result<bool> {
+        let files = self.fs.walk_dir(dir, &[]).await?;
+        let links = self.fs.walk_symlinks(dir, &[]).await?;
+        let dirs = self.fs.walk_dirs(dir, &[]).await?;
+        Ok(files
+            .iter()
+            .chain(&links)
+            .chain(&dirs)
+            .all(|path| within_any(gone, path)))
+    }
+
+    /// Whether any directory between `root` and `path` is an ignored directory
+    fn is_ignored_below(&self, root: &Path, path: &Path) -> bool {
+        path.strip_prefix(root)
+            .map(|relative| {
+                relative.components().any(|c| {
+                    self.ignore_patterns
+                        .iter()
+                        .any(|p| c.as_os_str() == OsStr::new(p))
+                })
+            })
+            .unwrap_or(true)
+    }
+
+    /// Drop matches a cleanup would leave in place because their contents fail
+    /// signature verification or, when enabled, they are open. The identity
+    /// check is left to the cleanup itself.
+    pub async fn screen_matches(&self, matches: Vec<FileMatch>) -> Result<Vec<FileMatch>> {
+        let open_files = if matches.is_empty() {
+            None
+        } else {
+            self.open_files()
+                .await
+                .context("Failed to list open files")?
+        };
+
+        let mut kept = Vec::with_capacity(matches.len());
+        for m in matches {
+            if m.action != Action::ReportOnly {
+                let check = self
+                    .check_before_removal(&m.path, &m.metadata, true, open_files.as_ref())
+                    .await;
+                match check {
+                    Ok(None) => {}
+                    Ok(Some(reason)) => {
+                        tracing::warn!(path = %m.path.escaped(), reason = %reason, "Skipping file");
+                        continue;
+                    }
+                    Err(e) => {
+                        tracing::warn!(path = %m.path.escaped(), error = %e, "Failed to check file");
+                        continue;
+                    }
+                }
+            }
+            kept.push(m);
+        }
+        Ok(kept)
+    }
+
+    /// Device and inode of files held open by running processes, when in-use
+    /// checks are on
+    async fn open_files(&self) -> Result<Option<HashSet<(u64, u64)>>> {
+        if !self.skip_in_use {
+            return Ok(None);
+        }
+        self.fs.open_file_ids().await.map(Some)
+    }
+
+    /// Decide whether a scanned file must be left in place.
+    /// The identity check is skipped in dry-run mode since nothing is removed.
+    async fn check_before_removal(
+        &self,
+        path: &Path,
+        scanned: &FileMetadata,
+        dry_run: bool,
+        open_files: Option<&HashSet<(u64, u64)>>,
+    ) -> Result<Option<SkipReason>> {
+        if !dry_run {
+            let current = self.fs.metadata(path).await?;
+            if &current != scanned {
+                return Ok(Some(SkipReason::ModifiedSinceScan));
+            }
+        }
+
+        if let Some(signature) = self.signature_for(path) {
+            let head = self.fs.read_head(path, signature.header_len()).await?;
+            if !signature.verify(&head) {
+                return Ok(Some(SkipReason::SignatureMismatch(
+                    signature.description().to_string(),
+                )));
+            }
+        }
+
+        // Inode numbers repeat across filesystems, so the device must match too
+        if open_files.is_some_and(|open| open.contains(&(scanned.dev, scanned.inode))) {
+            return Ok(Some(SkipReason::InUse));
+        }
+
+        Ok(None)
+    }
+
+    /// Signature a file must carry: always for content-validated rules,
+    /// and for well-known artifacts when signature checks are on
+    fn signature_for(&self, path: &Path) -> Option<Signature> {
+        let (pattern, required) = self.matching_rule(&self.matcher, path.file_name()?)?;
+        required.or_else(|| {
+            self.verify_signatures
+                .then(|| Signature::for_pattern(pattern))
+                .flatten()
+        })
+    }
+
+    /// First pattern rule the file meets by name, conditions and signature
+    async fn file_rule(&self, path: &Path, name: &OsStr) -> Option<&Rule> {
+        let decoded = self.decoded_name(name);
+        let rules = self
+            .matcher
+            .matching_rules(name)
+            .chain(decoded.iter().flat_map(|d| self.matcher.matching_rules(d)));
+
+        let mut metadata = None;
+        for rule in rules {
+            if !rule.conditions().is_empty() {
+                if metadata.is_none() {
+                    match self.fs.metadata(path).await {
+                        Ok(m) => metadata = Some(m),
+                        Err(e) => {
+                            tracing::debug!(path = %path.escaped(), error = %e, "Skipping unreadable match");
+                            return None;
+                        }
+                    }
+                }
+                let metadata = metadata.as_ref().expect("fetched above");
+                if !rule.conditions().is_met(metadata, SystemTime::now()) {
+                    tracing::debug!(path = %path.escaped(), pattern = rule.pattern(), kind = %rule.kind(), conditions = %rule.conditions(), "Name matches but conditions aren't met");
+                    continue;
+                }
+            }
+            if let Some(signature) = rule.signature() {
+                if !self.has_signature(path, signature).await {
+                    continue;
+                }
+            }
+            tracing::debug!(path = %path.escaped(), pattern = rule.pattern(), kind = %rule.kind(), "Pattern matched");
+            return Some(rule);
+        }
+        None
+    }
+
+    /// Whether the file's header carries `signature`; unreadable files don't
+    async fn has_signature(&self, path: &Path, signature: Signature) -> bool {
+        match self.fs.read_head(path, signature.header_len()).await {
+            Ok(head) if signature.verify(&head) => true,
+            Ok(_) => {
+                tracing::debug!(path = %path.escaped(), expected = signature.description(), "Name matches but content doesn't");
+                false
+            }
+            Err(e) => {
+                tracing::debug!(path = %path.escaped(), error = %e, "Skipping unreadable match");
+                false
+            }
+        }
+    }
+
+    /// First rule matching `name` as stored or, with name decoding on, as decoded
+    fn matching_rule<'m>(
+        &self,
+        matcher: &'m PatternMatcher,
+        name: &OsStr,
+    ) -> Option<(&'m str, Option<Signature>)> {
+        matcher.matching_rule(name).or_else(|| {
+            self.decoded_name(name)
+                .and_then(|decoded| matcher.matching_rule(&decoded))
+        })
+    }
+
+    /// `name` as a Mac client sees it, when name decoding is on and changes it
+    fn decoded_name(&self, name: &OsStr) -> Option<String> {
+        if !self.decode_names {
+            return None;
+        }
+        match decode_server_name(&lossy_text(name)) {
+            Cow::Owned(decoded) => Some(decoded),
+            Cow::Borrowed(_) => None,
+        }
+    }
+}
+
+/// Whether `path` is one of `dirs` or lies below one, looking up each of its
+/// ancestors rather than comparing against every directory
+fn within_any(dirs: &BTreeSet<PathBuf>, path: &Path) -> bool {
+    !dirs.is_empty() && path.ancestors().any(|a| dirs.contains(a))
+}
+
+/// `relative` as rules match it: `/`-separated, keeping the bytes of its names
+fn rule_path(relative: &Path) -> Cow<'_, OsStr> {
+    if std::path::MAIN_SEPARATOR == '/' {
+        return Cow::Borrowed(relative.as_os_str());
+    }
+    let text = lossy_text(relative.as_os_str()).replace(std::path::MAIN_SEPARATOR, "/");
+    Cow::Owned(OsString::from(text))
+}
+
+/// Directories above a path that decide its fate before its name does
+#[derive(Default)]
+struct Ancestry {
+    /// First directory the scan doesn't enter, with the ignore entry naming it
+    ignored_by: Option<(PathBuf, String)>,
+    /// Outermost directory removed whole, with the directory pattern it matched
+    removed_with: Option<(PathBuf, String)>,
+}
+
+/// The file name of `path` less an ASCII `prefix` and `suffix` it is known to
+/// carry, keeping any bytes that aren't UTF-8
+#[cfg(unix)]
+fn strip_affixes<'p>(path: &'p Path, prefix: &str, suffix: &str) -> &'p OsStr {
+    use std::os::unix::ffi::OsStrExt;
+    let name = path.file_name().map_or(&[][..], OsStrExt::as_bytes);
+    OsStr::from_bytes(&name[prefix.len()..name.len() - suffix.len()])
+}
+
+#[cfg(not(unix))]
+fn strip_affixes<'p>(path: &'p Path, prefix: &str, suffix: &str) -> &'p OsStr {
+    let name = path.file_name().and_then(OsStr::to_str).unwrap_or_default();
+    OsStr::new(
+        name.get(prefix.len()..name.len().saturating_sub(suffix.len()))
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::MockFileSystem;
+
+    /// A cleaner for `.DS_Store` files, for tests to add the settings they exercise to
+    fn cleaner(fs: MockFileSystem) -> Cleaner<MockFileSystem> {
+        Cleaner::new(fs, &[".DS_Store".to_string()], vec![]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cleaner_scan() {
+        let fs = MockFileSystem::with_files(vec![
+            PathBuf::from("/test/.DS_Store"),
+            PathBuf::from("/test/file.txt"),
+        ]);
+        let cleaner = Cleaner::new(fs, &[".DS_Store".to_string()], vec![]).unwrap();
+
+        let found = cleaner.scan(Path::new("/test")).await.unwrap();
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0], PathBuf::from("/test/.DS_Store"));
+    }
+
+    #[tokio::test]
+    async fn test_cleaner_clean() {
+        let fs = MockFileSystem::with_files(vec![
+            PathBuf::from("/test/.DS_Store"),
+            PathBuf::from("/test/file.txt"),
+        ]);
+        let fs_clone = fs.clone();
+        let cleaner = Cleaner::new(fs, &[".DS_Store".to_string()], vec![]).unwrap();
+
+        let result = cleaner.clean(Path::new("/test"), false).await.unwrap();
+
+        assert_eq!(result.files_found, 1);
+        assert_eq!(result.files_deleted, 1);
+        assert!(fs_clone.was_deleted(Path::new("/test/.DS_Store")));
+    }
+
+    #[tokio::test]
+    async fn test_cleaner_dry_run() {
+        let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/.DS_Store")]);
+        let fs_clone = fs.clone();
+        let cleaner = Cleaner::new(fs, &[".DS_Store".to_string()], vec![]).unwrap();
+
+        let result = cleaner.clean(Path::new("/test"), true).await.unwrap();
+
+        assert_eq!(result.files_deleted, 1);
+        assert!(result.dry_run);
+        assert!(!fs_clone.was_deleted(Path::new("/test/.DS_Store"))); // Not actually deleted
+    }
+
+    #[tokio::test]
+    async fn test_cleaner_handles_deletion_error() {
+        let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/.DS_Store")]);
+        fs.set_fail_on(PathBuf::from("/test/.DS_Store"));
+
+        let cleaner = Cleaner::new(fs, &[".DS_Store".to_string()], vec![]).unwrap();
+
+        let result = cleaner.clean(Path::new("/test"), false).await.unwrap();
+
+        assert_eq!(result.files_found, 1);
+        assert_eq!(result.files_deleted, 0);
+        assert_eq!(result.files_failed.len(), 1);
+        assert!(result.files_failed[0].1.contains("Permission denied"));
+    }
+
+    #[tokio::test]
+    async fn test_cleaner_with_ignore_patterns() {
+        let fs = MockFileSystem::with_files(vec![
+            PathBuf::from("/test/.DS_Store"),
+            PathBuf::from("/test/node_modules/.DS_Store"),
+            PathBuf::from("/test/src/.DS_Store"),
+        ]);
+        let cleaner = Cleaner::new(
+            fs,
+            &[".DS_Store".to_string()],
+            vec!["node_modules".to_string()],
+        )
+        .unwrap();
+
+        let found = cleaner.scan(Path::new("/test")).await.unwrap();
+
+        assert_eq!(found.len(), 2);
+        assert!(found.contains(&PathBuf::from("/test/.DS_Store")));
+        assert!(found.contains(&PathBuf::from("/test/src/.DS_Store")));
+        assert!(!found.contains(&PathBuf::from("/test/node_modules/.DS_Store")));
+    }
+
+    #[tokio::test]
+    async fn test_cleaner_ignores_ds_store_in_ignored_dir() {
+        let fs = MockFileSystem::with_files(vec![
+            PathBuf::from("/test/.DS_Store"),
+            PathBuf::from("/test/.git/objects/.DS_Store"),
+        ]);
+        let fs_clone = fs.clone();
+        let cleaner =
+            Cleaner::new(fs, &[".DS_Store".to_string()], vec![".git".to_string()]).unwrap();
+
+        let result = cleaner.clean(Path::new("/test"), false).await.unwrap();
+
+        assert_eq!(result.files_found, 1);
+        assert_eq!(result.files_deleted, 1);
+        assert!(fs_clone.was_deleted(Path::new("/test/.DS_Store")));
+        assert!(!fs_clone.was_deleted(Path::new("/test/.git/objects/.DS_Store")));
+    }
+
+    #[tokio::test]
+    async fn test_cleaner_ignore_case() {
+        let fs = MockFileSystem::with_files(vec![
+            PathBuf::from("/test/.ds_store"),
+            PathBuf::from("/test/Photos/THUMBS.DB"),
+            PathBuf::from("/test/__macosx/._a.jpg"),
+            PathBuf::from("/test/notes.txt"),
+        ]);
+        let cleaner = Cleaner::new(
+            fs,
+            &[".DS_Store".to_string(), "Thumbs.db".to_string()],
+            vec![],
+        )
+        .unwrap()
+        .with_dir_patterns(&["__MACOSX".to_string()])
+        .unwrap()
+        .with_ignore_case(true)
+        .unwrap();
+
+        let report = cleaner.scan_report(Path::new("/test")).await.unwrap();
+
+        let files: Vec<_> = report.matches.iter().map(|m| m.path.clone()).collect();
+        assert_eq!(
+            files,
+            vec![
+                PathBuf::from("/test/.ds_store"),
+                PathBuf::from("/test/Photos/THUMBS.DB")
+            ]
+        );
+        assert_eq!(report.dirs[0].path, PathBuf::from("/test/__macosx"));
+    }
+
+    #[tokio::test]
+    async fn test_cleaner_scan_matches_records_identity() {
+        let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/.DS_Store")]);
+        let fs_clone = fs.clone();
+        let cleaner = Cleaner::new(fs, &[".DS_Store".to_string()], vec![]).unwrap();
+
+        let matches = cleaner.scan_matches(Path::new("/test")).await.unwrap();
+
+        assert_eq!(matches.len(), 1);
+        let expected = fs_clone
+            .metadata(Path::new("/test/.DS_Store"))
+            .await
+            .unwrap();
+        assert_eq!(matches[0].metadata, expected);
+    }
+
+    #[tokio::test]
+    async fn test_cleaner_skips_file_modified_since_scan() {
+        let fs = MockFileSystem::with_files(vec![
+            PathBuf::from("/test/.DS_Store"),
+            PathBuf::from("/test/sub/.DS_Store"),
+        ]);
+        let fs_clone = fs.clone();
+        let cleaner = Cleaner::new(fs, &[".DS_Store".to_string()], vec![]).unwrap();
+
+        let matches = cleaner.scan_matches(Path::new("/test")).await.unwrap();
+        let original = matches[0].metadata.clone();
+        fs_clone.set_metadata(
+            PathBuf::from("/test/.DS_Store"),
+            FileMetadata {
+                size: original.size + 1,
+                ..original
+            },
+        );
+
+        let result = cleaner.remove_matches(matches, false).await;
+
+        assert_eq!(result.files_found, 2);
+        assert_eq!(result.files_deleted, 1);
+        assert_eq!(
+            result.files_skipped,
+            vec![(
+                PathBuf::from("/test/.DS_Store"),
+                SkipReason::ModifiedSinceScan
+            )]
+        );
+        assert!(!fs_clone.was_deleted(Path::new("/test/.DS_Store")));
+        assert!(fs_clone.was_deleted(Path::new("/test/sub/.DS_Store")));
+    }
+
+    #[tokio::test]
+    async fn test_cleaner_skips_file_replaced_since_scan() {
+        let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/.DS_Store")]);
+        let fs_clone = fs.clone();
+        let cleaner = Cleaner::new(fs, &[".DS_Store".to_string()], vec![]).unwrap();
+
+        let matches = cleaner.scan_matches(Path::new("/test")).await.unwrap();
+        let original = matches[0].metadata.clone();
+        fs_clone.set_metadata(
+            PathBuf::from("/test/.DS_Store"),
+            FileMetadata {
+                inode: original.inode + 100,
+                ..original
+            },
+        );
+
+        let result = cleaner.remove_matches(matches, false).await;
+
+        assert_eq!(result.files_deleted, 0);
+        assert_eq!(result.files_skipped.len(), 1);
+        assert!(!fs_clone.was_deleted(Path::new("/test/.DS_Store")));
+    }
+
+    #[tokio::test]
+    async fn test_cleaner_aborts_when_limit_exceeded() {
+        let fs = MockFileSystem::with_files(vec![
+            PathBuf::from("/test/.DS_Store"),
+            PathBuf::from("/test/a/.DS_Store"),
+            PathBuf::from("/test/b/.DS_Store"),
+        ]);
+        let fs_clone = fs.clone();
+        let cleaner = Cleaner::new(fs, &[".DS_Store".to_string()], vec![])
+            .unwrap()
+            .with_limits(DeletionLimits {
+                max_deletions: Some(2),
+                ..Default::default()
+            });
+
+        let err = cleaner.clean(Path::new("/test"), false).await.unwrap_err();
+
+        assert!(err.to_string().contains("Refusing to delete 3 files"));
+        assert!(fs_clone.get_deleted().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cleaner_limit_only_warns_in_dry_run() {
+        let fs = MockFileSystem::with_files(vec![
+            PathBuf::from("/test/.DS_Store"),
+            PathBuf::from("/test/a/.DS_Store"),
+        ]);
+        let cleaner = Cleaner::new(fs, &[".DS_Store".to_string()], vec![])
+            .unwrap()
+            .with_limits(DeletionLimits {
+                max_deletions: Some(1),
+                ..Default::default()
+            });
+
+        let result = cleaner.clean(Path::new("/test"), true).await.unwrap();
+
+        assert_eq!(result.files_deleted, 2);
+    }
+
+    #[tokio::test]
+    async fn test_cleaner_force_overrides_limit() {
+        let fs = MockFileSystem::with_files(vec![
+            PathBuf::from("/test/.DS_Store"),
+            PathBuf::from("/test/a/.DS_Store"),
+        ]);
+        let cleaner = Cleaner::new(fs, &[".DS_Store".to_string()], vec![])
+            .unwrap()
+            .with_limits(DeletionLimits {
+                max_deletions: Some(1),
+                force: true,
+                ..Default::default()
+            });
+
+        let result = cleaner.clean(Path::new("/test"), false).await.unwrap();
+
+        assert_eq!(result.files_deleted, 2);
+    }
+
+    #[tokio::test]
+    async fn test_cleaner_signature_mismatch_skipped() {
+        let fs = MockFileSystem::with_files(vec![
+            PathBuf::from("/test/.DS_Store"),
+            PathBuf::from("/test/notes/.DS_Store"),
+        ]);
+        fs.set_contents(
+            PathBuf::from("/test/.DS_Store"),
+            b"\x00\x00\x00\x01Bud1\x00\x00\x10\x00".to_vec(),
+        );
+        fs.set_contents(
+            PathBuf::from("/test/notes/.DS_Store"),
+            b"my oddly named notes".to_vec(),
+        );
+        let fs_clone = fs.clone();
+        let cleaner = Cleaner::new(fs, &[".DS_Store".to_string()], vec![])
+            .unwrap()
+            .with_signature_checks(true);
+
+        let result = cleaner.clean(Path::new("/test"), false).await.unwrap();
+
+        assert_eq!(result.files_deleted, 1);
+        assert!(fs_clone.was_deleted(Path::new("/test/.DS_Store")));
+        assert!(!fs_clone.was_deleted(Path::new("/test/notes/.DS_Store")));
+        assert_eq!(result.files_skipped.len(), 1);
+        assert!(matches!(
+            result.files_skipped[0].1,
+            SkipReason::SignatureMismatch(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cleaner_signature_checks_reported_in_dry_run() {
+        let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/Thumbs.db")]);
+        fs.set_contents(PathBuf::from("/test/Thumbs.db"), b"not ole".to_vec());
+        let cleaner = Cleaner::new(fs, &["Thumbs.db".to_string()], vec![])
+            .unwrap()
+            .with_signature_checks(true);
+
+        let result = cleaner.clean(Path::new("/test"), true).await.unwrap();
+
+        assert_eq!(result.files_deleted, 0);
+        assert_eq!(result.files_skipped.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cleaner_signature_checks_ignore_other_patterns() {
+        let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/file.bak")]);
+        let fs_clone = fs.clone();
+        let cleaner = Cleaner::new(fs, &["*.bak".to_string()], vec![])
+            .unwrap()
+            .with_signature_checks(true);
+
+        let result = cleaner.clean(Path::new("/test"), false).await.unwrap();
+
+        assert_eq!(result.files_deleted, 1);
+        assert!(fs_clone.was_deleted(Path::new("/test/file.bak")));
+    }
+
+    mod apple_double {
+        use super::*;
+        use crate::core::appledouble::tests::{build, finder_info};
+
+        #[tokio::test]
+        async fn test_orphan_is_deleted() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/._gone.jpg")]);
+            fs.set_contents(
+                PathBuf::from("/test/._gone.jpg"),
+                build(&[(9, finder_info(3))]),
+            );
+            let fs_clone = fs.clone();
+
+            let result = cleaner(fs)
+                .with_apple_double(true)
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_deleted, 1);
+            assert!(fs_clone.was_deleted(Path::new("/test/._gone.jpg")));
+        }
+
+        #[tokio::test]
+        async fn test_paired_empty_metadata_is_deleted() {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/test/photo.jpg"),
+                PathBuf::from("/test/._photo.jpg"),
+            ]);
+            fs.set_contents(
+                PathBuf::from("/test/._photo.jpg"),
+                build(&[(9, finder_info(0))]),
+            );
+            let fs_clone = fs.clone();
+
+            let result = cleaner(fs)
+                .with_apple_double(true)
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_deleted, 1);
+            assert!(fs_clone.was_deleted(Path::new("/test/._photo.jpg")));
+            assert!(!fs_clone.was_deleted(Path::new("/test/photo.jpg")));
+        }
+
+        #[tokio::test]
+        async fn test_paired_fork_with_data_is_reported() {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/test/font.dfont"),
+                PathBuf::from("/test/._font.dfont"),
+            ]);
+            fs.set_contents(
+                PathBuf::from("/test/._font.dfont"),
+                build(&[(9, vec![0; 32]), (2, vec![7; 1024])]),
+            );
+            let fs_clone = fs.clone();
+
+            let result = cleaner(fs)
+                .with_apple_double(true)
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_found, 1);
+            assert_eq!(result.files_deleted, 0);
+            assert_eq!(
+                result.files_skipped,
+                vec![(
+                    PathBuf::from("/test/._font.dfont"),
+                    SkipReason::PairedAppleDouble
+                )]
+            );
+            assert!(!fs_clone.was_deleted(Path::new("/test/._font.dfont")));
+        }
+
+        #[tokio::test]
+        async fn test_non_apple_double_is_kept() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/._notes")]);
+            fs.set_contents(PathBuf::from("/test/._notes"), b"plain text".to_vec());
+            let fs_clone = fs.clone();
+
+            let result = cleaner(fs)
+                .with_apple_double(true)
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_deleted, 0);
+            assert!(matches!(
+                result.files_skipped[0].1,
+                SkipReason::SignatureMismatch(_)
+            ));
+            assert!(!fs_clone.was_deleted(Path::new("/test/._notes")));
+        }
+
+        #[tokio::test]
+        async fn test_blanket_pattern_is_refined() {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/test/doc.pages"),
+                PathBuf::from("/test/._doc.pages"),
+            ]);
+            fs.set_contents(
+                PathBuf::from("/test/._doc.pages"),
+                build(&[(9, finder_info(2))]),
+            );
+            let cleaner = Cleaner::new(fs, &["._*".to_string()], vec![])
+                .unwrap()
+                .with_apple_double(true);
+
+            let found = cleaner.scan(Path::new("/test")).await.unwrap();
+
+            assert!(found.is_empty());
+        }
+    }
+
+    mod dir_patterns {
+        use super::*;
+
+        fn mac_dirs() -> Vec<String> {
+            vec!["__MACOSX".to_string(), ".Trashes".to_string()]
+        }
+
+        fn archive_fs() -> MockFileSystem {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/test/photos/__MACOSX/._a.jpg"),
+                PathBuf::from("/test/photos/__MACOSX/sub/.DS_Store"),
+                PathBuf::from("/test/photos/__MACOSX/sub/__MACOSX/._b.jpg"),
+                PathBuf::from("/test/photos/a.jpg"),
+                PathBuf::from("/test/.DS_Store"),
+            ]);
+            fs.set_contents(PathBuf::from("/test/photos/__MACOSX/._a.jpg"), vec![0; 100]);
+            fs.set_contents(
+                PathBuf::from("/test/photos/__MACOSX/sub/.DS_Store"),
+                vec![0; 20],
+            );
+            fs
+        }
+
+        #[tokio::test]
+        async fn test_scan_reports_outermost_directory_with_stats() {
+            let report = cleaner(archive_fs())
+                .with_dir_patterns(&mac_dirs())
+                .unwrap()
+                .scan_report(Path::new("/test"))
+                .await
+                .unwrap();
+
+            assert_eq!(
+                report.dirs,
+                vec![DirMatch {
+                    path: PathBuf::from("/test/photos/__MACOSX"),
+                    files: 3,
+                    bytes: 120,
+                }]
+            );
+            // The .DS_Store inside __MACOSX goes with the directory
+            let paths: Vec<_> = report.matches.iter().map(|m| m.path.clone()).collect();
+            assert_eq!(paths, vec![PathBuf::from("/test/.DS_Store")]);
+        }
+
+        #[tokio::test]
+        async fn test_clean_removes_directories_recursively() {
+            let fs = archive_fs();
+            let fs_clone = fs.clone();
+
+            let result = cleaner(fs)
+                .with_dir_patterns(&mac_dirs())
+                .unwrap()
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_deleted, 1);
+            assert_eq!(result.dirs_found, 1);
+            assert_eq!(result.dirs_deleted.len(), 1);
+            assert_eq!(result.dirs_deleted[0].files, 3);
+            assert!(fs_clone.was_dir_removed(Path::new("/test/photos/__MACOSX")));
+            assert!(!fs_clone.was_deleted(Path::new("/test/photos/a.jpg")));
+        }
+
+        #[tokio::test]
+        async fn test_dry_run_keeps_directories() {
+            let fs = archive_fs();
+            let fs_clone = fs.clone();
+
+            let result = cleaner(fs)
+                .with_dir_patterns(&mac_dirs())
+                .unwrap()
+                .clean(Path::new("/test"), true)
+                .await
+                .unwrap();
+
+            assert_eq!(result.dirs_deleted.len(), 1);
+            assert!(!fs_clone.was_dir_removed(Path::new("/test/photos/__MACOSX")));
+        }
+
+        #[tokio::test]
+        async fn test_ignored_and_empty_directories() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from(
+                "/test/node_modules/pkg/__MACOSX/._x",
+            )]);
+            fs.add_dir(PathBuf::from("/test/.Trashes"));
+            let fs_clone = fs.clone();
+
+            let result = Cleaner::new(
+                fs,
+                &[".DS_Store".to_string()],
+                vec!["node_modules".to_string()],
+            )
+            .unwrap()
+            .with_dir_patterns(&mac_dirs())
+            .unwrap()
+            .clean(Path::new("/test"), false)
+            .await
+            .unwrap();
+
+            assert_eq!(result.dirs_deleted.len(), 1);
+            assert_eq!(result.dirs_deleted[0].files, 0);
+            assert!(fs_clone.was_dir_removed(Path::new("/test/.Trashes")));
+            assert!(!fs_clone.was_dir_removed(Path::new("/test/node_modules/pkg/__MACOSX")));
+        }
+
+        #[tokio::test]
+        async fn test_directory_changed_since_scan_is_skipped() {
+            let fs = archive_fs();
+            let fs_clone = fs.clone();
+            let cleaner = cleaner(fs).with_dir_patterns(&mac_dirs()).unwrap();
+            let report = cleaner.scan_report(Path::new("/test")).await.unwrap();
+
+            fs_clone.add_file(PathBuf::from("/test/photos/__MACOSX/new.txt"));
+            let result = cleaner.apply(Path::new("/test"), report, false).await;
+
+            assert!(result.dirs_deleted.is_empty());
+            assert_eq!(
+                result.files_skipped,
+                vec![(
+                    PathBuf::from("/test/photos/__MACOSX"),
+                    SkipReason::ModifiedSinceScan
+                )]
+            );
+            assert!(!fs_clone.was_dir_removed(Path::new("/test/photos/__MACOSX")));
+        }
+
+        #[tokio::test]
+        async fn test_directory_removal_failure_is_reported() {
+            let fs = archive_fs();
+            fs.set_fail_on(PathBuf::from("/test/photos/__MACOSX"));
+
+            let result = cleaner(fs)
+                .with_dir_patterns(&mac_dirs())
+                .unwrap()
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert!(result.dirs_deleted.is_empty());
+            assert_eq!(result.files_failed.len(), 1);
+            assert!(result.files_failed[0].1.contains("Permission denied"));
+        }
+    }
+
+    mod prune_empty_dirs {
+        use super::*;
+
+        #[tokio::test]
+        async fn test_emptied_directories_are_pruned_bottom_up() {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/test/import/2024/01/.DS_Store"),
+                PathBuf::from("/test/import/2024/.DS_Store"),
+                PathBuf::from("/test/photos/.DS_Store"),
+                PathBuf::from("/test/photos/a.jpg"),
+            ]);
+            let fs_clone = fs.clone();
+
+            let result = cleaner(fs)
+                .with_prune_empty_dirs(true)
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(
+                result.dirs_pruned,
+                vec![
+                    PathBuf::from("/test/import/2024/01"),
+                    PathBuf::from("/test/import/2024"),
+                    PathBuf::from("/test/import"),
+                ]
+            );
+            assert!(fs_clone.exists(Path::new("/test/photos")).await.unwrap());
+            assert!(fs_clone.exists(Path::new("/test")).await.unwrap());
+        }
+
+        #[tokio::test]
+        async fn test_root_is_never_pruned() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/.DS_Store")]);
+            let fs_clone = fs.clone();
+
+            let result = cleaner(fs)
+                .with_prune_empty_dirs(true)
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert!(result.dirs_pruned.is_empty());
+            assert!(!fs_clone.was_dir_removed(Path::new("/test")));
+        }
+
+        #[tokio::test]
+        async fn test_already_empty_directories_are_kept() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/a/.DS_Store")]);
+            fs.add_dir(PathBuf::from("/test/a/placeholder"));
+            fs.add_dir(PathBuf::from("/test/empty"));
+            let fs_clone = fs.clone();
+
+            let result = cleaner(fs)
+                .with_prune_empty_dirs(true)
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert!(result.dirs_pruned.is_empty());
+            assert!(fs_clone.exists(Path::new("/test/a")).await.unwrap());
+            assert!(fs_clone.exists(Path::new("/test/empty")).await.unwrap());
+        }
+
+        #[tokio::test]
+        async fn test_ignored_directories_are_kept() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/a/.DS_Store")]);
+            let cleaner = cleaner(fs.clone()).with_prune_empty_dirs(true);
+            let report = cleaner.scan_report(Path::new("/test")).await.unwrap();
+
+            let result = Cleaner::new(fs, &[".DS_Store".to_string()], vec!["a".to_string()])
+                .unwrap()
+                .with_prune_empty_dirs(true)
+                .apply(Path::new("/test"), report, false)
+                .await;
+
+            assert_eq!(result.files_deleted, 1);
+            assert!(result.dirs_pruned.is_empty());
+        }
+
+        #[tokio::test]
+        async fn test_failed_deletion_keeps_directory() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/a/.DS_Store")]);
+            fs.set_fail_on(PathBuf::from("/test/a/.DS_Store"));
+
+            let result = cleaner(fs)
+                .with_prune_empty_dirs(true)
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert!(result.dirs_pruned.is_empty());
+        }
+
+        #[tokio::test]
+        async fn test_dry_run_reports_without_pruning() {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/test/import/2024/01/.DS_Store"),
+                PathBuf::from("/test/import/2024/.DS_Store"),
+                PathBuf::from("/test/busy/.DS_Store"),
+                PathBuf::from("/test/photos/.DS_Store"),
+                PathBuf::from("/test/photos/a.jpg"),
+            ]);
+            fs.set_open(PathBuf::from("/test/busy/.DS_Store"));
+            let fs_clone = fs.clone();
+
+            let result = cleaner(fs)
+                .with_prune_empty_dirs(true)
+                .with_in_use_check(true)
+                .clean(Path::new("/test"), true)
+                .await
+                .unwrap();
+
+            // The open file would be skipped, so its directory would stay
+            assert_eq!(
+                result.dirs_pruned,
+                vec![
+                    PathBuf::from("/test/import/2024/01"),
+                    PathBuf::from("/test/import/2024"),
+                    PathBuf::from("/test/import"),
+                ]
+            );
+            assert!(!fs_clone.was_dir_removed(Path::new("/test/import")));
+            assert!(fs_clone.get_deleted().is_empty());
+        }
+
+        #[tokio::test]
+        async fn test_disabled_by_default() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/a/.DS_Store")]);
+            let cleaner = cleaner(fs);
+
+            let result = cleaner.clean(Path::new("/test"), false).await.unwrap();
+
+            assert!(result.dirs_pruned.is_empty());
+        }
+
+        #[tokio::test]
+        async fn test_parent_of_removed_directory_is_pruned() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/zip/__MACOSX/._a")]);
+
+            let result = cleaner(fs)
+                .with_prune_empty_dirs(true)
+                .with_dir_patterns(&["__MACOSX".to_string()])
+                .unwrap()
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.dirs_pruned, vec![PathBuf::from("/test/zip")]);
+        }
+    }
+
+    mod xattrs {
+        use super::*;
+
+        fn default_patterns() -> Vec<String> {
+            crate::models::DEFAULT_XATTR_PATTERNS
+                .iter()
+                .map(|p| p.to_string())
+                .collect()
+        }
+
+        fn fs_with_xattrs() -> MockFileSystem {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/test/photo.jpg"),
+                PathBuf::from("/test/.DS_Store"),
+                PathBuf::from("/test/plain.txt"),
+            ]);
+            fs.set_xattrs(
+                PathBuf::from("/test/photo.jpg"),
+                &[
+                    "user.com.apple.FinderInfo",
+                    "user.DosStream.AFP_AfpInfo:$DATA",
+                    "user.mime_type",
+                ],
+            );
+            fs.set_xattrs(
+                PathBuf::from("/test/.DS_Store"),
+                &["user.com.apple.FinderInfo"],
+            );
+            fs
+        }
+
+        #[tokio::test]
+        async fn test_scan_reports_matching_xattrs_of_kept_files() {
+            let report = cleaner(fs_with_xattrs())
+                .with_xattr_patterns(&default_patterns())
+                .unwrap()
+                .scan_report(Path::new("/test"))
+                .await
+                .unwrap();
+
+            // The .DS_Store is deleted outright, so its xattrs aren't listed
+            assert_eq!(
+                report.xattrs,
+                vec![XattrMatch {
+                    path: PathBuf::from("/test/photo.jpg"),
+                    names: vec![
+                        OsString::from("user.com.apple.FinderInfo"),
+                        OsString::from("user.DosStream.AFP_AfpInfo:$DATA"),
+                    ],
+                }]
+            );
+        }
+
+        #[tokio::test]
+        async fn test_clean_strips_matching_xattrs() {
+            let fs = fs_with_xattrs();
+            let fs_clone = fs.clone();
+
+            let result = cleaner(fs)
+                .with_xattr_patterns(&default_patterns())
+                .unwrap()
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.xattrs_found, 2);
+            assert_eq!(result.xattrs_removed, 2);
+            assert_eq!(
+                fs_clone.get_xattrs(Path::new("/test/photo.jpg")),
+                vec![OsString::from("user.mime_type")]
+            );
+        }
+
+        #[tokio::test]
+        async fn test_dry_run_keeps_xattrs() {
+            let fs = fs_with_xattrs();
+            let fs_clone = fs.clone();
+
+            let result = cleaner(fs)
+                .with_xattr_patterns(&default_patterns())
+                .unwrap()
+                .clean(Path::new("/test"), true)
+                .await
+                .unwrap();
+
+            assert_eq!(result.xattrs_removed, 2);
+            assert_eq!(fs_clone.get_xattrs(Path::new("/test/photo.jpg")).len(), 3);
+        }
+
+        #[tokio::test]
+        async fn test_xattr_removal_failure_is_reported() {
+            let fs = fs_with_xattrs();
+            fs.set_fail_on(PathBuf::from("/test/photo.jpg"));
+
+            let result = cleaner(fs)
+                .with_xattr_patterns(&default_patterns())
+                .unwrap()
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.xattrs_found, 2);
+            assert_eq!(result.xattrs_removed, 0);
+            assert_eq!(result.files_failed.len(), 2);
+            assert!(result.files_failed[0]
+                .1
+                .contains("user.com.apple.FinderInfo"));
+        }
+
+        #[tokio::test]
+        async fn test_xattrs_untouched_without_patterns() {
+            let fs = fs_with_xattrs();
+            let cleaner = cleaner(fs);
+
+            let report = cleaner.scan_report(Path::new("/test")).await.unwrap();
+
+            assert!(report.xattrs.is_empty());
+        }
+    }
+
+    mod samba {
+        use super::*;
+        use crate::models::Preset;
+
+        #[tokio::test]
+        async fn test_encoded_names_are_matched() {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/share/:2eDS_Store"),
+                PathBuf::from("/share/photos/.DS_Store"),
+                PathBuf::from("/share/photos/a:2ejpg"),
+            ]);
+
+            let config = CleanConfig::new(PathBuf::from("/share"), vec![], vec![], false)
+                .with_presets(&[Preset::Samba]);
+
+            let found = Cleaner::from_config(fs, &config)
+                .unwrap()
+                .scan(Path::new("/share"))
+                .await
+                .unwrap();
+
+            assert_eq!(
+                found,
+                vec![
+                    PathBuf::from("/share/:2eDS_Store"),
+                    PathBuf::from("/share/photos/.DS_Store"),
+                ]
+            );
+        }
+
+        #[tokio::test]
+        async fn test_apple_double_dirs_are_units() {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/share/photos/.AppleDouble/a.jpg"),
+                PathBuf::from("/share/photos/.AppleDouble/.Parent"),
+                PathBuf::from("/share/docs/:2eAppleDouble/report.pdf"),
+                PathBuf::from("/share/.AppleDB/cnid2.db"),
+                PathBuf::from("/share/photos/a.jpg"),
+            ]);
+            let fs_clone = fs.clone();
+
+            let config = CleanConfig::new(PathBuf::from("/share"), vec![], vec![], false)
+                .with_presets(&[Preset::Samba]);
+
+            let result = Cleaner::from_config(fs, &config)
+                .unwrap()
+                .clean(Path::new("/share"), false)
+                .await
+                .unwrap();
+
+            let removed: Vec<_> = result.dirs_deleted.iter().map(|d| d.path.clone()).collect();
+            assert_eq!(
+                removed,
+                vec![
+                    PathBuf::from("/share/.AppleDB"),
+                    PathBuf::from("/share/docs/:2eAppleDouble"),
+                    PathBuf::from("/share/photos/.AppleDouble"),
+                ]
+            );
+            assert_eq!(result.files_deleted, 0);
+            assert!(!fs_clone.was_deleted(Path::new("/share/photos/a.jpg")));
+        }
+
+        #[tokio::test]
+        async fn test_decoding_is_off_without_preset() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/share/:2eDS_Store")]);
+            let cleaner = cleaner(fs);
+
+            let found = cleaner.scan(Path::new("/share")).await.unwrap();
+
+            assert!(found.is_empty());
+        }
+
+        #[tokio::test]
+        async fn test_signature_checked_for_encoded_name() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/share/:2eDS_Store")]);
+            fs.set_contents(
+                PathBuf::from("/share/:2eDS_Store"),
+                b"not a ds_store".to_vec(),
+            );
+
+            let config = CleanConfig::new(PathBuf::from("/share"), vec![], vec![], false)
+                .with_presets(&[Preset::Samba]);
+
+            let result = Cleaner::from_config(fs, &config)
+                .unwrap()
+                .with_signature_checks(true)
+                .clean(Path::new("/share"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_deleted, 0);
+            assert_eq!(result.files_skipped.len(), 1);
+        }
+    }
+
+    mod windows {
+        use super::*;
+        use crate::models::Preset;
+
+        const ZONE_STREAM: &[u8] = b"[ZoneTransfer]\r\nZoneId=3\r\n";
+
+        #[tokio::test]
+        async fn test_zone_identifier_needs_zone_transfer_header() {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/test/setup.exe"),
+                PathBuf::from("/test/setup.exe:Zone.Identifier"),
+                PathBuf::from("/test/notes:Zone.Identifier"),
+                PathBuf::from("/test/desktop.ini"),
+            ]);
+            fs.set_contents(
+                PathBuf::from("/test/setup.exe:Zone.Identifier"),
+                ZONE_STREAM.to_vec(),
+            );
+            fs.set_contents(
+                PathBuf::from("/test/notes:Zone.Identifier"),
+                b"my own notes".to_vec(),
+            );
+            let fs_clone = fs.clone();
+
+            let config = CleanConfig::new(PathBuf::from("/test"), vec![], vec![], false)
+                .with_presets(&[Preset::Windows]);
+
+            let result = Cleaner::from_config(fs, &config)
+                .unwrap()
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_deleted, 2);
+            assert!(result.files_skipped.is_empty());
+            assert!(fs_clone.was_deleted(Path::new("/test/setup.exe:Zone.Identifier")));
+            assert!(fs_clone.was_deleted(Path::new("/test/desktop.ini")));
+            assert!(!fs_clone.was_deleted(Path::new("/test/notes:Zone.Identifier")));
+            assert!(!fs_clone.was_deleted(Path::new("/test/setup.exe")));
+        }
+
+        #[tokio::test]
+        async fn test_zone_identifier_rewritten_after_scan_is_skipped() {
+            let path = PathBuf::from("/test/a.zip:Zone.Identifier");
+            let fs = MockFileSystem::with_files(vec![path.clone()]);
+            fs.set_contents(path.clone(), ZONE_STREAM.to_vec());
+            let fs_clone = fs.clone();
+            let config = CleanConfig::new(PathBuf::from("/test"), vec![], vec![], false)
+                .with_presets(&[Preset::Windows]);
+            let cleaner = Cleaner::from_config(fs, &config).unwrap();
+            let report = cleaner.scan_report(Path::new("/test")).await.unwrap();
+
+            // Same size, so only the content check notices
+            let mut replaced = ZONE_STREAM.to_vec();
+            replaced[0] = b'#';
+            fs_clone.set_contents(path.clone(), replaced);
+            let result = cleaner.apply(Path::new("/test"), report, false).await;
+
+            assert_eq!(result.files_deleted, 0);
+            assert!(matches!(
+                result.files_skipped[0].1,
+                SkipReason::SignatureMismatch(_)
+            ));
+        }
+    }
+
+    mod in_use {
+        use super::*;
+
+        #[tokio::test]
+        async fn test_open_files_are_skipped() {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/test/.notes.txt.swp"),
+                PathBuf::from("/test/.nfs000000000123"),
+            ]);
+            fs.set_open(PathBuf::from("/test/.nfs000000000123"));
+            let fs_clone = fs.clone();
+
+            let result = Cleaner::new(fs, &["*.swp".to_string(), ".nfs*".to_string()], vec![])
+                .unwrap()
+                .with_in_use_check(true)
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_deleted, 1);
+            assert_eq!(
+                result.files_skipped,
+                vec![(PathBuf::from("/test/.nfs000000000123"), SkipReason::InUse)]
+            );
+            assert!(!fs_clone.was_deleted(Path::new("/test/.nfs000000000123")));
+        }
+
+        #[tokio::test]
+        async fn test_same_inode_on_other_device_is_not_in_use() {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/test/.a.swp"),
+                PathBuf::from("/mnt/usb/report.docx"),
+            ]);
+            let metadata = |dev| FileMetadata {
+                size: 0,
+                modified: SystemTime::UNIX_EPOCH,
+                inode: 12,
+                dev,
+            };
+            fs.set_metadata(PathBuf::from("/test/.a.swp"), metadata(1));
+            fs.set_metadata(PathBuf::from("/mnt/usb/report.docx"), metadata(2));
+            fs.set_open(PathBuf::from("/mnt/usb/report.docx"));
+            let fs_clone = fs.clone();
+
+            let result = Cleaner::new(fs, &["*.swp".to_string()], vec![])
+                .unwrap()
+                .with_in_use_check(true)
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_deleted, 1);
+            assert!(fs_clone.was_deleted(Path::new("/test/.a.swp")));
+        }
+
+        #[tokio::test]
+        async fn test_open_files_reported_in_dry_run() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/.a.swp")]);
+            fs.set_open(PathBuf::from("/test/.a.swp"));
+
+            let result = Cleaner::new(fs, &["*.swp".to_string(), ".nfs*".to_string()], vec![])
+                .unwrap()
+                .with_in_use_check(true)
+                .clean(Path::new("/test"), true)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_deleted, 0);
+            assert_eq!(result.files_skipped[0].1, SkipReason::InUse);
+        }
+
+        #[tokio::test]
+        async fn test_open_files_deleted_when_check_disabled() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/.a.swp")]);
+            fs.set_open(PathBuf::from("/test/.a.swp"));
+            let cleaner = Cleaner::new(fs, &["*.swp".to_string()], vec![]).unwrap();
+
+            let result = cleaner.clean(Path::new("/test"), false).await.unwrap();
+
+            assert_eq!(result.files_deleted, 1);
+        }
+    }
+
+    mod editor_artifacts {
+        use super::*;
+        use crate::core::editor::tests::vim_swap;
+
+        /// Files on host build01, where pid 100 is running
+        fn build01_fs(files: Vec<PathBuf>) -> MockFileSystem {
+            let fs = MockFileSystem::with_files(files);
+            fs.set_hostname("build01");
+            fs.set_alive(100);
+            fs
+        }
+
+        fn swap_fs(host: &str, pid: u32) -> MockFileSystem {
+            let fs = build01_fs(vec![PathBuf::from("/test/.notes.txt.swp")]);
+            fs.set_contents(PathBuf::from("/test/.notes.txt.swp"), vim_swap(host, pid));
+            fs
+        }
+
+        #[tokio::test]
+        async fn test_vim_swap_of_dead_process_is_deleted() {
+            let fs = swap_fs("build01", 200);
+            let fs_clone = fs.clone();
+
+            let result = cleaner(fs)
+                .with_editor_artifacts(true)
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_deleted, 1);
+            assert!(fs_clone.was_deleted(Path::new("/test/.notes.txt.swp")));
+        }
+
+        #[tokio::test]
+        async fn test_vim_swap_of_live_process_is_reported() {
+            let result = cleaner(swap_fs("build01", 100))
+                .with_editor_artifacts(true)
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_deleted, 0);
+            assert_eq!(
+                result.files_skipped[0].1.to_string(),
+                "live editor session (pid 100 on build01)"
+            );
+        }
+
+        #[tokio::test]
+        async fn test_vim_swap_of_live_process_is_kept_from_rules() {
+            let rules = [crate::models::RuleConfig {
+                when: r#"name("*.swp")"#.to_string(),
+                action: Action::Quarantine,
+                rename_to: None,
+            }];
+
+            let result = cleaner(swap_fs("build01", 100))
+                .with_editor_artifacts(true)
+                .with_quarantine_dir(Some(PathBuf::from("/quarantine")))
+                .with_rules(&rules)
+                .unwrap()
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_quarantined, 0);
+            assert_eq!(
+                result.files_skipped[0].1,
+                SkipReason::LiveEditorSession("pid 100 on build01".to_string())
+            );
+        }
+
+        #[tokio::test]
+        async fn test_vim_swap_from_other_host_is_kept() {
+            let result = cleaner(swap_fs("laptop", 200))
+                .with_editor_artifacts(true)
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_deleted, 0);
+            assert_eq!(
+                result.files_skipped[0].1,
+                SkipReason::LiveEditorSession("pid 200 on laptop".to_string())
+            );
+        }
+
+        #[tokio::test]
+        async fn test_unrecognised_swap_is_left_alone() {
+            let fs = build01_fs(vec![PathBuf::from("/test/.data.swp")]);
+            fs.set_contents(PathBuf::from("/test/.data.swp"), b"not vim".to_vec());
+
+            let result = cleaner(fs)
+                .with_editor_artifacts(true)
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_found, 0);
+        }
+
+        #[tokio::test]
+        async fn test_emacs_lock_and_auto_save() {
+            let fs = build01_fs(vec![
+                PathBuf::from("/test/stale/#notes.txt#"),
+                PathBuf::from("/test/live/#draft.txt#"),
+                PathBuf::from("/test/orphan/#todo.txt#"),
+            ]);
+            fs.add_symlink(
+                PathBuf::from("/test/stale/.#notes.txt"),
+                PathBuf::from("alice@build01.200:1700000000"),
+            );
+            fs.add_symlink(
+                PathBuf::from("/test/live/.#draft.txt"),
+                PathBuf::from("alice@build01.100:1700000000"),
+            );
+            let fs_clone = fs.clone();
+
+            let result = cleaner(fs)
+                .with_editor_artifacts(true)
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_deleted, 2);
+            assert!(fs_clone.was_deleted(Path::new("/test/stale/#notes.txt#")));
+            assert!(fs_clone.was_deleted(Path::new("/test/stale/.#notes.txt")));
+            assert!(!fs_clone.was_deleted(Path::new("/test/orphan/#todo.txt#")));
+            assert_eq!(
+                result.files_skipped,
+                vec![
+                    (
+                        PathBuf::from("/test/live/#draft.txt#"),
+                        SkipReason::LiveEditorSession("pid 100 on build01".to_string())
+                    ),
+                    (
+                        PathBuf::from("/test/orphan/#todo.txt#"),
+                        SkipReason::UnownedAutoSave
+                    ),
+                    (
+                        PathBuf::from("/test/live/.#draft.txt"),
+                        SkipReason::LiveEditorSession("pid 100 on build01".to_string())
+                    ),
+                ]
+            );
+        }
+
+        #[tokio::test]
+        async fn test_emacs_lock_as_plain_file() {
+            let fs = build01_fs(vec![PathBuf::from("/test/.#notes.txt")]);
+            fs.set_contents(
+                PathBuf::from("/test/.#notes.txt"),
+                b"alice@build01.200".to_vec(),
+            );
+
+            let result = cleaner(fs)
+                .with_editor_artifacts(true)
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_deleted, 1);
+        }
+
+        #[tokio::test]
+        async fn test_disabled_by_default() {
+            let fs = swap_fs("build01", 200);
+            let found = cleaner(fs).scan(Path::new("/test")).await.unwrap();
+
+            assert!(found.is_empty());
+        }
+    }
+
+    mod conditions {
+        use super::*;
+        use crate::models::Conditions;
+        use std::time::Duration;
+
+        const DAY: Duration = Duration::from_secs(86400);
+
+        fn conditional(pattern: &str, conditions: Conditions) -> ConditionalPattern {
+            ConditionalPattern {
+                pattern: pattern.to_string(),
+                conditions,
+                ..Default::default()
+            }
+        }
+
+        fn set_file(fs: &MockFileSystem, path: &str, size: u64, age: Duration) {
+            fs.set_metadata(
+                PathBuf::from(path),
+                FileMetadata {
+                    size,
+                    modified: SystemTime::now() - age,
+                    inode: size + 1,
+                    dev: 0,
+                },
+            );
+        }
+
+        #[tokio::test]
+        async fn test_fresh_temp_files_are_kept() {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/test/.DS_Store"),
+                PathBuf::from("/test/old.tmp"),
+                PathBuf::from("/test/fresh.tmp"),
+            ]);
+            set_file(&fs, "/test/.DS_Store", 10, Duration::ZERO);
+            set_file(&fs, "/test/old.tmp", 20, DAY * 2);
+            set_file(&fs, "/test/fresh.tmp", 30, DAY / 24);
+            let older_than_day = Conditions {
+                older_than: Some(DAY),
+                ..Default::default()
+            };
+            let cleaner = cleaner(fs)
+                .with_conditional_patterns(&[conditional("*.tmp", older_than_day)])
+                .unwrap();
+
+            let found = cleaner.scan(Path::new("/test")).await.unwrap();
+
+            assert_eq!(
+                found,
+                vec![
+                    PathBuf::from("/test/.DS_Store"),
+                    PathBuf::from("/test/old.tmp")
+                ]
+            );
+        }
+
+        #[tokio::test]
+        async fn test_size_limits() {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/test/empty.log"),
+                PathBuf::from("/test/small.log"),
+                PathBuf::from("/test/huge.log"),
+            ]);
+            set_file(&fs, "/test/empty.log", 0, DAY);
+            set_file(&fs, "/test/small.log", 100, DAY);
+            set_file(&fs, "/test/huge.log", 10_000, DAY);
+            let between = Conditions {
+                min_size: Some(1),
+                max_size: Some(1000),
+                ..Default::default()
+            };
+            let cleaner = Cleaner::new(fs, &[], vec![])
+                .unwrap()
+                .with_conditional_patterns(&[conditional("*.log", between)])
+                .unwrap();
+
+            let found = cleaner.scan(Path::new("/test")).await.unwrap();
+
+            assert_eq!(found, vec![PathBuf::from("/test/small.log")]);
+        }
+
+        #[tokio::test]
+        async fn test_any_matching_rule_is_enough() {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/test/empty.tmp"),
+                PathBuf::from("/test/old.tmp"),
+                PathBuf::from("/test/busy.tmp"),
+            ]);
+            set_file(&fs, "/test/empty.tmp", 0, Duration::ZERO);
+            set_file(&fs, "/test/old.tmp", 50, DAY * 2);
+            set_file(&fs, "/test/busy.tmp", 50, Duration::ZERO);
+            let cleaner = Cleaner::new(fs, &[], vec![])
+                .unwrap()
+                .with_conditional_patterns(&[
+                    conditional(
+                        "*.tmp",
+                        Conditions {
+                            older_than: Some(DAY),
+                            ..Default::default()
+                        },
+                    ),
+                    conditional(
+                        "*.tmp",
+                        Conditions {
+                            max_size: Some(0),
+                            ..Default::default()
+                        },
+                    ),
+                ])
+                .unwrap();
+
+            let found = cleaner.scan(Path::new("/test")).await.unwrap();
+
+            assert_eq!(
+                found,
+                vec![
+                    PathBuf::from("/test/empty.tmp"),
+                    PathBuf::from("/test/old.tmp")
+                ]
+            );
+        }
+    }
+
+    mod rules {
+        use super::*;
+        use crate::models::RuleConfig;
+
+        fn rule(when: &str, action: Action) -> RuleConfig {
+            RuleConfig {
+                when: when.to_string(),
+                action,
+                rename_to: None,
+            }
+        }
+
+        #[tokio::test]
+        async fn test_rules_combine_name_and_path() {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/test/build/out.o"),
+                PathBuf::from("/test/src/main.o"),
+                PathBuf::from("/test/.DS_Store"),
+            ]);
+            let rules = [rule(r#"name("*.o") and path("build/**")"#, Action::Delete)];
+
+            let found = cleaner(fs)
+                .with_quarantine_dir(Some(PathBuf::from("/quarantine")))
+                .with_rules(&rules)
+                .unwrap()
+                .scan(Path::new("/test"))
+                .await
+                .unwrap();
+
+            assert_eq!(
+                found,
+                vec![
+                    PathBuf::from("/test/build/out.o"),
+                    PathBuf::from("/test/.DS_Store")
+                ]
+            );
+        }
+
+        #[tokio::test]
+        async fn test_first_matching_rule_decides_action() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/.DS_Store")]);
+            let rules = [
+                rule(r#"name(".DS_Store")"#, Action::ReportOnly),
+                rule(r#"name("*")"#, Action::Delete),
+            ];
+
+            let matches = cleaner(fs)
+                .with_quarantine_dir(Some(PathBuf::from("/quarantine")))
+                .with_rules(&rules)
+                .unwrap()
+                .scan_matches(Path::new("/test"))
+                .await
+                .unwrap();
+
+            assert_eq!(matches.len(), 1);
+            assert_eq!(matches[0].action, Action::ReportOnly);
+        }
+
+        #[tokio::test]
+        async fn test_quarantine_moves_file_under_mirrored_path() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/a/core.1234")]);
+            fs.set_contents(PathBuf::from("/test/a/core.1234"), b"\x7fELF core");
+            let fs_clone = fs.clone();
+            let rules = [rule(
+                r#"name("core.*") and content("ELF")"#,
+                Action::Quarantine,
+            )];
+
+            let result = cleaner(fs)
+                .with_quarantine_dir(Some(PathBuf::from("/quarantine")))
+                .with_rules(&rules)
+                .unwrap()
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_quarantined, 1);
+            assert_eq!(result.files_deleted, 0);
+            assert!(fs_clone
+                .get_files()
+                .contains(&PathBuf::from("/quarantine/test/a/core.1234")));
+            assert!(fs_clone.was_deleted(Path::new("/test/a/core.1234")));
+        }
+
+        #[tokio::test]
+        async fn test_quarantine_never_overwrites() {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/test/core.1"),
+                PathBuf::from("/quarantine/test/core.1"),
+            ]);
+            let rules = [rule(r#"name("core.*")"#, Action::Quarantine)];
+
+            let result = cleaner(fs)
+                .with_quarantine_dir(Some(PathBuf::from("/quarantine")))
+                .with_rules(&rules)
+                .unwrap()
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_quarantined, 0);
+            assert_eq!(result.files_failed.len(), 1);
+            assert!(result.files_failed[0].1.contains("already exists"));
+        }
+
+        #[tokio::test]
+        async fn test_report_only_keeps_file() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/big.iso")]);
+            fs.set_metadata(
+                PathBuf::from("/test/big.iso"),
+                FileMetadata {
+                    size: 5 * 1024 * 1024 * 1024,
+                    modified: SystemTime::now(),
+                    inode: 7,
+                    dev: 0,
+                },
+            );
+            let fs_clone = fs.clone();
+            let rules = [rule("size > 1G", Action::ReportOnly)];
+
+            let result = cleaner(fs)
+                .with_quarantine_dir(Some(PathBuf::from("/quarantine")))
+                .with_rules(&rules)
+                .unwrap()
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_reported, 1);
+            assert_eq!(result.files_deleted, 0);
+            assert!(!fs_clone.was_deleted(Path::new("/test/big.iso")));
+        }
+
+        #[tokio::test]
+        async fn test_owner_predicate() {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/test/mine.tmp"),
+                PathBuf::from("/test/theirs.tmp"),
+            ]);
+            fs.set_owner(PathBuf::from("/test/mine.tmp"), 1000, "alice");
+            fs.set_owner(PathBuf::from("/test/theirs.tmp"), 1001, "bob");
+            let rules = [rule(r#"name("*.tmp") and owner("alice")"#, Action::Delete)];
+
+            let found = cleaner(fs)
+                .with_quarantine_dir(Some(PathBuf::from("/quarantine")))
+                .with_rules(&rules)
+                .unwrap()
+                .scan(Path::new("/test"))
+                .await
+                .unwrap();
+
+            assert_eq!(found, vec![PathBuf::from("/test/mine.tmp")]);
+        }
+
+        #[tokio::test]
+        async fn test_unreadable_file_does_not_match() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/core.1")]);
+            fs.set_fail_on(PathBuf::from("/test/core.1"));
+            let rules = [rule(r#"content("ELF")"#, Action::Delete)];
+
+            let found = cleaner(fs)
+                .with_quarantine_dir(Some(PathBuf::from("/quarantine")))
+                .with_rules(&rules)
+                .unwrap()
+                .scan(Path::new("/test"))
+                .await
+                .unwrap();
+
+            assert!(found.is_empty());
+        }
+
+        #[test]
+        fn test_invalid_rule_is_rejected() {
+            let err = Cleaner::new(MockFileSystem::new(), &[], vec![])
+                .unwrap()
+                .with_rules(&[rule("name(", Action::Delete)])
+                .err()
+                .unwrap();
+            assert!(err.to_string().starts_with("Invalid rule 'name('"));
+        }
+    }
+
+    mod actions {
+        use super::*;
+        use crate::models::RenameTo;
+
+        fn pattern(pattern: &str, action: Action, rename_to: Option<&str>) -> ConditionalPattern {
+            ConditionalPattern {
+                pattern: pattern.to_string(),
+                action,
+                rename_to: rename_to.map(|r| RenameTo::new(r).unwrap()),
+                ..Default::default()
+            }
+        }
+
+        fn patterns() -> Vec<ConditionalPattern> {
+            vec![
+                pattern("*.bak", Action::Quarantine, None),
+                pattern("*.orig", Action::ReportOnly, None),
+                pattern("Thumbs.db", Action::Rename, Some(".{name}.old")),
+            ]
+        }
+
+        #[tokio::test]
+        async fn test_each_pattern_applies_its_action() {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/test/.DS_Store"),
+                PathBuf::from("/test/notes.bak"),
+                PathBuf::from("/test/main.rs.orig"),
+                PathBuf::from("/test/photos/Thumbs.db"),
+            ]);
+            let fs_clone = fs.clone();
+
+            let result = cleaner(fs)
+                .with_quarantine_dir(Some(PathBuf::from("/quarantine")))
+                .with_conditional_patterns(&patterns())
+                .unwrap()
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_found, 4);
+            assert_eq!(result.files_deleted, 1);
+            assert_eq!(result.files_quarantined, 1);
+            assert_eq!(result.files_reported, 1);
+            assert_eq!(result.files_renamed, 1);
+            assert!(result.files_failed.is_empty());
+
+            let files = fs_clone.get_files();
+            assert!(files.contains(&PathBuf::from("/quarantine/test/notes.bak")));
+            assert!(files.contains(&PathBuf::from("/test/photos/.Thumbs.db.old")));
+            assert!(fs_clone.was_deleted(Path::new("/test/.DS_Store")));
+            assert!(fs_clone.was_deleted(Path::new("/test/photos/Thumbs.db")));
+            assert!(!fs_clone.was_deleted(Path::new("/test/main.rs.orig")));
+        }
+
+        #[tokio::test]
+        async fn test_rename_target_recorded_at_scan() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/Thumbs.db")]);
+
+            let matches = cleaner(fs)
+                .with_quarantine_dir(Some(PathBuf::from("/quarantine")))
+                .with_conditional_patterns(&patterns())
+                .unwrap()
+                .scan_matches(Path::new("/test"))
+                .await
+                .unwrap();
+
+            assert_eq!(matches[0].action, Action::Rename);
+            assert_eq!(
+                matches[0].rename_to,
+                Some(PathBuf::from("/test/.Thumbs.db.old"))
+            );
+        }
+
+        #[tokio::test]
+        async fn test_rename_never_overwrites() {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/test/Thumbs.db"),
+                PathBuf::from("/test/.Thumbs.db.old"),
+            ]);
+            let fs_clone = fs.clone();
+
+            let result = cleaner(fs)
+                .with_quarantine_dir(Some(PathBuf::from("/quarantine")))
+                .with_conditional_patterns(&patterns())
+                .unwrap()
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_renamed, 0);
+            assert_eq!(result.files_failed.len(), 1);
+            assert!(result.files_failed[0].1.contains("already exists"));
+            assert!(!fs_clone.was_deleted(Path::new("/test/Thumbs.db")));
+        }
+
+        #[tokio::test]
+        async fn test_dry_run_counts_each_action() {
+            let fs = MockFileSystem::with_files(vec![
+                PathBuf::from("/test/notes.bak"),
+                PathBuf::from("/test/Thumbs.db"),
+            ]);
+            let fs_clone = fs.clone();
+
+            let result = cleaner(fs)
+                .with_quarantine_dir(Some(PathBuf::from("/quarantine")))
+                .with_conditional_patterns(&patterns())
+                .unwrap()
+                .clean(Path::new("/test"), true)
+                .await
+                .unwrap();
+
+            assert_eq!(result.files_quarantined, 1);
+            assert_eq!(result.files_renamed, 1);
+            assert!(fs_clone.get_deleted().is_empty());
+        }
+
+        #[test]
+        fn test_rule_rename_needs_new_name() {
+            let err = Cleaner::new(MockFileSystem::new(), &[], vec![])
+                .unwrap()
+                .with_rules(&[RuleConfig {
+                    when: r#"name("Thumbs.db")"#.to_string(),
+                    action: Action::Rename,
+                    rename_to: None,
+                }])
+                .err()
+                .unwrap();
+            assert_eq!(
+                err.to_string(),
+                r#"Invalid rule 'name("Thumbs.db")': action 'rename' needs rename_to"#
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    mod non_utf8 {
+        use super::*;
+        use crate::core::appledouble::tests::build;
+        use crate::models::RenameTo;
+        use std::os::unix::ffi::OsStrExt;
+
+        /// A path from raw bytes, like the Latin-1 names old volumes carry
+        fn raw(bytes: &[u8]) -> PathBuf {
+            PathBuf::from(OsStr::from_bytes(bytes))
+        }
+
+        #[tokio::test]
+        async fn test_names_are_matched_not_skipped() {
+            let fs = MockFileSystem::with_files(vec![
+                raw(b"/test/caf\xe9.tmp"),
+                raw(b"/test/caf\xe9.txt"),
+                raw(b"/test/\xe9t\xe9/.DS_Store"),
+                raw(b"/test/node_modules/caf\xe9.tmp"),
+            ]);
+            let fs_clone = fs.clone();
+            let cleaner = Cleaner::new(
+                fs,
+                &[".DS_Store".to_string(), "*.tmp".to_string()],
+                vec!["node_modules".to_string()],
+            )
+            .unwrap();
+
+            let result = cleaner.clean(Path::new("/test"), false).await.unwrap();
+
+            assert_eq!(result.files_deleted, 2);
+            assert!(fs_clone.was_deleted(&raw(b"/test/caf\xe9.tmp")));
+            assert!(fs_clone.was_deleted(&raw(b"/test/\xe9t\xe9/.DS_Store")));
+            assert!(!fs_clone.was_deleted(&raw(b"/test/caf\xe9.txt")));
+        }
+
+        #[tokio::test]
+        async fn test_apple_double_pairs_by_exact_bytes() {
+            let fs = MockFileSystem::with_files(vec![
+                raw(b"/test/caf\xe9.jpg"),
+                raw(b"/test/._caf\xe9.jpg"),
+                raw(b"/test/._caf\xe8.jpg"),
+            ]);
+            let fork = build(&[(9, vec![0; 32]), (2, vec![7; 1024])]);
+            fs.set_contents(raw(b"/test/._caf\xe9.jpg"), fork.clone());
+            fs.set_contents(raw(b"/test/._caf\xe8.jpg"), fork);
+            let cleaner = Cleaner::new(fs, &[], vec![])
+                .unwrap()
+                .with_apple_double(true);
+
+            let result = cleaner.clean(Path::new("/test"), false).await.unwrap();
+
+            // Both map to the same text; only the one without a base file goes
+            assert_eq!(result.files_deleted, 1);
+            assert_eq!(
+                result.files_skipped,
+                vec![(raw(b"/test/._caf\xe9.jpg"), SkipReason::PairedAppleDouble)]
+            );
+        }
+
+        #[tokio::test]
+        async fn test_rename_keeps_name_bytes() {
+            let fs = MockFileSystem::with_files(vec![raw(b"/test/caf\xe9.db")]);
+            let cleaner = Cleaner::new(fs, &[], vec![])
+                .unwrap()
+                .with_conditional_patterns(&[ConditionalPattern {
+                    pattern: "*.db".to_string(),
+                    action: Action::Rename,
+                    rename_to: Some(RenameTo::new(".{name}.old").unwrap()),
+                    ..Default::default()
+                }])
+                .unwrap();
+
+            let matches = cleaner.scan_matches(Path::new("/test")).await.unwrap();
+
+            assert_eq!(matches[0].rename_to, Some(raw(b"/test/.caf\xe9.db.old")));
+        }
+
+        #[tokio::test]
+        async fn test_plan_keeps_exact_paths() {
+            let fs = MockFileSystem::with_files(vec![raw(b"/test/caf\xe9.tmp")]);
+            let cleaner = Cleaner::new(fs, &["*.tmp".to_string()], vec![]).unwrap();
+            let matches = cleaner.scan_matches(Path::new("/test")).await.unwrap();
+
+            let json = serde_json::to_string(&matches[0]).unwrap();
+            assert!(json.contains(r#""path":{"escaped":"/test/caf\\xE9.tmp"}"#));
+            let parsed: FileMatch = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed.path, raw(b"/test/caf\xe9.tmp"));
+        }
+
+        #[tokio::test]
+        async fn test_xattr_names_keep_their_bytes() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/photo.jpg")]);
+            let name = OsStr::from_bytes(b"user.com.apple.caf\xe9");
+            fs.set_xattrs(PathBuf::from("/test/photo.jpg"), &[name]);
+            let fs_clone = fs.clone();
+
+            let result = cleaner(fs)
+                .with_xattr_patterns(&["user.com.apple.*".to_string()])
+                .unwrap()
+                .clean(Path::new("/test"), false)
+                .await
+                .unwrap();
+
+            assert_eq!(result.xattrs_removed, 1);
+            assert!(fs_clone.get_xattrs(Path::new("/test/photo.jpg")).is_empty());
+        }
+    }
+
+    mod explain {
+        use super::*;
+        use crate::models::{Conditions, RuleConfig};
+        use std::time::Duration;
+
+        #[tokio::test]
+        async fn test_ignored_ancestor_is_named() {
+            let fs =
+                MockFileSystem::with_files(vec![PathBuf::from("/test/a/node_modules/b/.DS_Store")]);
+            let cleaner = Cleaner::new(
+                fs,
+                &[".DS_Store".to_string()],
+                vec!["node_modules".to_string()],
+            )
+            .unwrap();
+
+            let explanation = cleaner
+                .explain(
+                    Path::new("/test"),
+                    Path::new("/test/a/node_modules/b/.DS_Store"),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(
+                explanation.ignored_by,
+                Some((
+                    PathBuf::from("/test/a/node_modules"),
+                    "node_modules".to_string()
+                ))
+            );
+            assert_eq!(explanation.patterns.len(), 1);
+            assert!(matches!(explanation.verdict, Verdict::Keep(_)));
+        }
+
+        #[tokio::test]
+        async fn test_failed_condition_is_shown() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/fresh.tmp")]);
+            fs.set_metadata(
+                PathBuf::from("/test/fresh.tmp"),
+                FileMetadata {
+                    size: 10,
+                    modified: SystemTime::now(),
+                    inode: 1,
+                    dev: 0,
+                },
+            );
+            let pattern = ConditionalPattern {
+                pattern: "*.tmp".to_string(),
+                conditions: Conditions {
+                    older_than: Some(Duration::from_secs(86400)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let cleaner = cleaner(fs).with_conditional_patterns(&[pattern]).unwrap();
+
+            let explanation = cleaner
+                .explain(Path::new("/test"), Path::new("/test/fresh.tmp"))
+                .await
+                .unwrap();
+
+            assert_eq!(explanation.kind, EntryKind::File);
+            assert_eq!(explanation.patterns[0].pattern, "*.tmp");
+            assert_eq!(
+                explanation.patterns[0].conditions,
+                vec![("older than 1d".to_string(), false)]
+            );
+            assert_eq!(
+                explanation.verdict,
+                Verdict::Keep("no matching pattern's conditions are met".to_string())
+            );
+        }
+
+        #[tokio::test]
+        async fn test_rule_decides_action_and_target() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/a/core.1")]);
+            let rules = [
+                RuleConfig {
+                    when: r#"path("b/**")"#.to_string(),
+                    action: Action::Delete,
+                    rename_to: None,
+                },
+                RuleConfig {
+                    when: r#"name("core.*")"#.to_string(),
+                    action: Action::Quarantine,
+                    rename_to: None,
+                },
+            ];
+            let cleaner = cleaner(fs)
+                .with_quarantine_dir(Some(PathBuf::from("/quarantine")))
+                .with_rules(&rules)
+                .unwrap();
+
+            let explanation = cleaner
+                .explain(Path::new("/test"), Path::new("/test/a/core.1"))
+                .await
+                .unwrap();
+
+            let matched: Vec<_> = explanation.rules.iter().map(|r| r.matched).collect();
+            assert_eq!(matched, vec![Some(false), Some(true)]);
+            assert_eq!(
+                explanation.verdict,
+                Verdict::Act {
+                    action: Action::Quarantine,
+                    target: Some(PathBuf::from("/quarantine/test/a/core.1")),
+                }
+            );
+        }
+
+        #[tokio::test]
+        async fn test_files_in_matched_directory_go_with_it() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from(
+                "/test/zip/__MACOSX/inner/photo.jpg",
+            )]);
+            let cleaner = cleaner(fs)
+                .with_dir_patterns(&["__MACOSX".to_string()])
+                .unwrap();
+
+            for path in ["/test/zip/__MACOSX/inner/photo.jpg", "/test/zip/__MACOSX"] {
+                let explanation = cleaner
+                    .explain(Path::new("/test"), Path::new(path))
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    explanation.removed_with,
+                    Some((PathBuf::from("/test/zip/__MACOSX"), "__MACOSX".to_string()))
+                );
+                assert_eq!(
+                    explanation.verdict,
+                    Verdict::Act {
+                        action: Action::Delete,
+                        target: None,
+                    }
+                );
+            }
+
+            let explanation = cleaner
+                .explain(Path::new("/test"), Path::new("/test/zip"))
+                .await
+                .unwrap();
+            assert_eq!(explanation.kind, EntryKind::Directory);
+            assert!(matches!(explanation.verdict, Verdict::Keep(_)));
+        }
+
+        #[tokio::test]
+        async fn test_open_file_is_skipped() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/.DS_Store")]);
+            fs.set_open(PathBuf::from("/test/.DS_Store"));
+            let cleaner = cleaner(fs).with_in_use_check(true);
+
+            let explanation = cleaner
+                .explain(Path::new("/test"), Path::new("/test/.DS_Store"))
+                .await
+                .unwrap();
+
+            assert_eq!(explanation.verdict, Verdict::Skip(SkipReason::InUse));
+        }
+
+        #[tokio::test]
+        async fn test_path_outside_root_is_rejected() {
+            let fs = MockFileSystem::with_files(vec![PathBuf::from("/other/.DS_Store")]);
+            let cleaner = cleaner(fs);
+
+            let result = cleaner
+                .explain(Path::new("/test"), Path::new("/other/.DS_Store"))
+                .await;
+
+            assert!(result.is_err());
+        }
+    }
+
+    mod name_verdict {
+        use super::*;
+        use crate::models::{Conditions, RuleConfig};
+        use std::time::Duration;
+
+        fn verdict(cleaner: &Cleaner<MockFileSystem>, path: &str) -> (Outcome, String) {
+            let verdict = cleaner.name_verdict(Path::new(path));
+            (verdict.outcome, verdict.reason)
+        }
+
+        #[test]
+        fn test_directories_decide_first() {
+            let cleaner = Cleaner::new(
+                MockFileSystem::new(),
+                &[".DS_Store".to_string()],
+                vec!["node_modules".to_string()],
+            )
+            .unwrap()
+            .with_dir_patterns(&["__MACOSX".to_string()])
+            .unwrap();
+
+            assert_eq!(
+                verdict(&cleaner, "app/node_modules/pkg/.DS_Store"),
+                (Outcome::Keep, "ignored: node_modules".to_string())
+            );
+            assert_eq!(
+                verdict(&cleaner, "zip/__MACOSX/a/photo.jpg"),
+                (
+                    Outcome::Act(Action::Delete),
+                    "dir pattern '__MACOSX' on zip/__MACOSX".to_string()
+                )
+            );
+            // Only directories are ignored or removed whole
+            assert_eq!(verdict(&cleaner, "./node_modules").0, Outcome::Keep);
+            assert_eq!(
+                verdict(&cleaner, "./src/.DS_Store"),
+                (Outcome::Act(Action::Delete), "glob '.DS_Store'".to_string())
+            );
+        }
+
+        #[test]
+        fn test_rules_and_conditions() {
+            let rules = [
+                RuleConfig {
+                    when: r#"path("logs/**")"#.to_string(),
+                    action: Action::ReportOnly,
+                    rename_to: None,
+                },
+                RuleConfig {
+                    when: r#"name("core.*") and content("ELF")"#.to_string(),
+                    action: Action::Quarantine,
+                    rename_to: None,
+                },
+            ];
+            let old_tmp = ConditionalPattern {
+                pattern: "*.tmp".to_string(),
+                conditions: Conditions {
+                    older_than: Some(Duration::from_secs(86400)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let cleaner = cleaner(MockFileSystem::new())
+                .with_rules(&rules)
+                .unwrap()
+                .with_conditional_patterns(&[old_tmp])
+                .unwrap();
+
+            assert_eq!(
+                verdict(&cleaner, "logs/app.tmp").0,
+                Outcome::Act(Action::ReportOnly)
+            );
+            assert_eq!(
+                verdict(&cleaner, "core.42").0,
+                Outcome::Depends(Action::Quarantine)
+            );
+            assert_eq!(
+                verdict(&cleaner, "build/x.tmp"),
+                (
+                    Outcome::Depends(Action::Delete),
+                    "glob '*.tmp' if older than 1d".to_string()
+                )
+            );
+            assert_eq!(
+                verdict(&cleaner, "notes.txt"),
+                (Outcome::Keep, "no match".to_string())
+            );
+        }
+
+        #[test]
+        fn test_rules_on_apple_double_files_depend_on_contents() {
+            let rules = [RuleConfig {
+                when: r#"name("._*")"#.to_string(),
+                action: Action::Delete,
+                rename_to: None,
+            }];
+            let cleaner = cleaner(MockFileSystem::new())
+                .with_apple_double(true)
+                .with_rules(&rules)
+                .unwrap();
+
+            assert_eq!(
+                verdict(&cleaner, "._photo.jpg"),
+                (
+                    Outcome::Depends(Action::Delete),
+                    r#"rule 'name("._*")', depends on its contents"#.to_string()
+                )
+            );
+        }
+
+        #[test]
+        fn test_signature_checks_qualify_matches() {
+            let cleaner = cleaner(MockFileSystem::new()).with_signature_checks(true);
+
+            assert_eq!(
+                verdict(&cleaner, ".DS_Store").0,
+                Outcome::Depends(Action::Delete)
+            );
+        }
     }
 }
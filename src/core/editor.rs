@@ -0,0 +1,180 @@
+//! Ownership of editor swap, auto-save and lock files.
+//!
+//! Vim records the PID and hostname of the editing session in the first block
+//! of its swap file; Emacs encodes them in the target of its `.#name` lock
+//! symlink. An artifact is stale once that process is gone.
+
+use anyhow::{anyhow, bail, Result};
+use std::fmt;
+
+/// Bytes of the vim swap header needed to read the owner
+pub const VIM_HEADER_LEN: usize = 108;
+const VIM_PID: std::ops::Range<usize> = 24..28;
+const VIM_HOSTNAME: std::ops::Range<usize> = 68..108;
+/// Longest lock target we bother to read when locks are plain files
+pub const EMACS_LOCK_MAX_LEN: usize = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditorArtifact {
+    /// `.name.swp` (and `.swo`, `.swn`, ... for further sessions)
+    VimSwap,
+    /// `#name#`, owned by whoever holds the `.#name` lock
+    EmacsAutoSave,
+    /// `.#name`, a symlink (or plain file) naming the owner
+    EmacsLock,
+}
+
+impl EditorArtifact {
+    pub fn description(&self) -> &'static str {
+        match self {
+            EditorArtifact::VimSwap => "vim swap file",
+            EditorArtifact::EmacsAutoSave => "Emacs auto-save file",
+            EditorArtifact::EmacsLock => "Emacs lock",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name.len() > 2 && name.starts_with(".#") {
+            return Some(EditorArtifact::EmacsLock);
+        }
+        if name.len() > 2 && name.starts_with('#') && name.ends_with('#') {
+            return Some(EditorArtifact::EmacsAutoSave);
+        }
+        let is_swap_extension = name
+            .rsplit_once('.')
+            .map(|(_, ext)| {
+                ext.len() == 3 && ext.starts_with("sw") && ext.as_bytes()[2].is_ascii_lowercase()
+            })
+            .unwrap_or(false);
+        if name.starts_with('.') && name.len() > 5 && is_swap_extension {
+            return Some(EditorArtifact::VimSwap);
+        }
+        None
+    }
+}
+
+/// The editing session that created an artifact
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Owner {
+    pub host: String,
+    pub pid: u32,
+}
+
+impl Owner {
+    /// Whether the owner ran on `hostname`. Vim truncates long hostnames, so a
+    /// recorded name that fills its field only has to be a prefix.
+    pub fn is_on(&self, hostname: &str) -> bool {
+        let field_len = VIM_HOSTNAME.len() - 1;
+        self.host == hostname || (self.host.len() == field_len && hostname.starts_with(&self.host))
+    }
+}
+
+impl fmt::Display for Owner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "pid {} on {}", self.pid, self.host)
+    }
+}
+
+/// Read the owner from the first block of a vim swap file
+pub fn parse_vim_swap(head: &[u8]) -> Result<Owner> {
+    if head.len() < VIM_HEADER_LEN || !head.starts_with(b"b0") {
+        bail!("Not a vim swap file");
+    }
+    let pid = u32::from_le_bytes(head[VIM_PID].try_into().expect("4 bytes"));
+    let host = c_string(&head[VIM_HOSTNAME]);
+    owner(host, pid)
+}
+
+/// Read the owner from an Emacs lock target: `user@host.pid` optionally
+/// followed by `:boot-time`
+pub fn parse_emacs_lock(target: &str) -> Result<Owner> {
+    let invalid = || anyhow!("Not an Emacs lock: {}", target);
+    let (_user, rest) = target.split_once('@').ok_or_else(invalid)?;
+    let rest = rest.split_once(':').map_or(rest, |(owner, _boot)| owner);
+    let (host, pid) = rest.rsplit_once('.').ok_or_else(invalid)?;
+    let pid = pid.parse().map_err(|_| invalid())?;
+    owner(host.to_string(), pid)
+}
+
+fn owner(host: String, pid: u32) -> Result<Owner> {
+    if host.is_empty() || pid == 0 {
+        bail!("Editor artifact has no owner recorded");
+    }
+    Ok(Owner { host, pid })
+}
+
+fn c_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// First block of a vim swap file owned by `pid` on `host`
+    pub(crate) fn vim_swap(host: &str, pid: u32) -> Vec<u8> {
+        let mut block = vec![0u8; 1024];
+        block[0..2].copy_from_slice(b"b0");
+        block[2..9].copy_from_slice(b"VIM 9.0");
+        block[VIM_PID].copy_from_slice(&pid.to_le_bytes());
+        block[28..32].copy_from_slice(b"user");
+        block[VIM_HOSTNAME.start..VIM_HOSTNAME.start + host.len()].copy_from_slice(host.as_bytes());
+        block
+    }
+
+    #[test]
+    fn test_artifact_names() {
+        assert_eq!(
+            EditorArtifact::from_name(".notes.txt.swp"),
+            Some(EditorArtifact::VimSwap)
+        );
+        assert_eq!(
+            EditorArtifact::from_name(".notes.txt.swo"),
+            Some(EditorArtifact::VimSwap)
+        );
+        assert_eq!(
+            EditorArtifact::from_name("#notes.txt#"),
+            Some(EditorArtifact::EmacsAutoSave)
+        );
+        assert_eq!(
+            EditorArtifact::from_name(".#notes.txt"),
+            Some(EditorArtifact::EmacsLock)
+        );
+        assert_eq!(EditorArtifact::from_name("notes.swp"), None);
+        assert_eq!(EditorArtifact::from_name("#"), None);
+        assert_eq!(EditorArtifact::from_name(".DS_Store"), None);
+    }
+
+    #[test]
+    fn test_parse_vim_swap() {
+        let owner = parse_vim_swap(&vim_swap("build01", 4242)).unwrap();
+        assert_eq!(
+            owner,
+            Owner {
+                host: "build01".to_string(),
+                pid: 4242
+            }
+        );
+        assert!(parse_vim_swap(b"b0 too short").is_err());
+        assert!(parse_vim_swap(&[0u8; 200]).is_err());
+    }
+
+    #[test]
+    fn test_parse_emacs_lock() {
+        let owner = parse_emacs_lock("alice@build01.example.com.4242:1700000000").unwrap();
+        assert_eq!(owner.host, "build01.example.com");
+        assert_eq!(owner.pid, 4242);
+        assert_eq!(parse_emacs_lock("alice@build01.77").unwrap().pid, 77);
+        assert!(parse_emacs_lock("not a lock").is_err());
+        assert!(parse_emacs_lock("alice@build01.x").is_err());
+    }
+
+    #[test]
+    fn test_truncated_hostname_matches_prefix() {
+        let long_host = "a".repeat(60);
+        let owner = parse_vim_swap(&vim_swap(&long_host[..39], 1)).unwrap();
+        assert!(owner.is_on(&long_host));
+        assert!(!owner.is_on("other"));
+    }
+}
@@ -0,0 +1,224 @@
+//! Glob syntax, translated to regular expressions so that globs and regexes can
+//! be compiled together into one automaton.
+//!
+//! `*` matches any run of characters and `?` any one character, `[abc]`, `[a-z]`
+//! and `[!abc]` (or `[^abc]`) match one character from or outside a class, and
+//! `{a,b}` matches either alternative; alternatives may nest and hold wildcards.
+//! A backslash makes the next character literal. In path globs `*`, `?` and
+//! classes stay within one component, while `**` crosses them.
+//!
+//! The regexes match names as bytes. A byte that isn't part of valid UTF-8
+//! counts as one character to wildcards and negated classes.
+
+use anyhow::{anyhow, bail, Result};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// One byte of a name that isn't UTF-8
+const INVALID_BYTE: &str = r"(?-u:[\x80-\xFF])";
+
+/// The anchored regex equivalent to `glob`. `paths` keeps wildcards from
+/// matching `/` except through `**`.
+pub fn to_regex(glob: &str, paths: bool) -> Result<String> {
+    let mut translator = Translator {
+        chars: glob.chars().peekable(),
+        paths,
+        regex: String::from("(?s)^"),
+    };
+    translator
+        .translate(false)
+        .map_err(|e| anyhow!("Invalid glob pattern '{}': {}", glob, e))?;
+    translator.regex.push('$');
+    Ok(translator.regex)
+}
+
+struct Translator<'a> {
+    chars: Peekable<Chars<'a>>,
+    paths: bool,
+    regex: String,
+}
+
+impl Translator<'_> {
+    /// Translate to the end of the glob or, inside braces, past the closing `}`
+    fn translate(&mut self, in_braces: bool) -> Result<()> {
+        let any = if self.paths {
+            format!("(?:[^/]|{})", INVALID_BYTE)
+        } else {
+            format!("(?:.|{})", INVALID_BYTE)
+        };
+        while let Some(c) = self.chars.next() {
+            match c {
+                '*' if self.chars.peek() == Some(&'*') => {
+                    self.chars.next();
+                    if self.paths && self.chars.peek() == Some(&'/') {
+                        // `**/` also matches no directories at all
+                        self.chars.next();
+                        self.regex
+                            .push_str(&format!("(?:(?:.|{})*/)?", INVALID_BYTE));
+                    } else {
+                        self.regex.push_str(&format!("(?:.|{})*", INVALID_BYTE));
+                    }
+                }
+                '*' => {
+                    self.regex.push_str(&any);
+                    self.regex.push('*');
+                }
+                '?' => self.regex.push_str(&any),
+                '[' => self.class()?,
+                '{' => {
+                    self.regex.push_str("(?:");
+                    self.translate(true)?;
+                    self.regex.push(')');
+                }
+                ',' if in_braces => self.regex.push('|'),
+                '}' if in_braces => return Ok(()),
+                '\\' => {
+                    let escaped = self
+                        .chars
+                        .next()
+                        .ok_or_else(|| anyhow!("trailing backslash"))?;
+                    self.literal(escaped);
+                }
+                c => self.literal(c),
+            }
+        }
+        if in_braces {
+            bail!("unclosed '{{'");
+        }
+        Ok(())
+    }
+
+    /// Translate a class after its opening `[`. A `]` right after the opening
+    /// (and any negation) is a member, as is a `-` at either end.
+    fn class(&mut self) -> Result<()> {
+        let negated = matches!(self.chars.peek(), Some('!' | '^'));
+        if negated {
+            self.chars.next();
+        }
+        let mut members = String::new();
+        let mut first = true;
+        loop {
+            let c = self.chars.next().ok_or_else(|| anyhow!("unclosed '['"))?;
+            match c {
+                ']' if !first => break,
+                '-' if !first && self.chars.peek().is_some_and(|&next| next != ']') => {
+                    members.push('-')
+                }
+                c => {
+                    if matches!(c, '\\' | '[' | ']' | '^' | '-' | '&' | '~') {
+                        members.push('\\');
+                    }
+                    members.push(c);
+                }
+            }
+            first = false;
+        }
+
+        if negated {
+            let slash = if self.paths { "/" } else { "" };
+            self.regex
+                .push_str(&format!("(?:[^{}{}]|{})", slash, members, INVALID_BYTE));
+        } else {
+            self.regex.push_str(&format!("[{}]", members));
+        }
+        Ok(())
+    }
+
+    fn literal(&mut self, c: char) {
+        self.regex
+            .push_str(&regex::escape(c.encode_utf8(&mut [0; 4])));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::bytes::Regex;
+
+    fn matches(glob: &str, name: impl AsRef<[u8]>) -> bool {
+        Regex::new(&to_regex(glob, false).unwrap())
+            .unwrap()
+            .is_match(name.as_ref())
+    }
+
+    fn matches_path(glob: &str, path: impl AsRef<[u8]>) -> bool {
+        Regex::new(&to_regex(glob, true).unwrap())
+            .unwrap()
+            .is_match(path.as_ref())
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(matches("*.tmp", "build.tmp"));
+        assert!(matches("*.tmp", ".tmp"));
+        assert!(!matches("*.tmp", "build.tmp.txt"));
+        assert!(matches("caf?.txt", "caf\u{e9}.txt"));
+        assert!(!matches("caf?.txt", "cafe\u{301}.txt"));
+        assert!(matches("a.b", "a.b"));
+        assert!(!matches("a.b", "axb"));
+    }
+
+    #[test]
+    fn test_classes() {
+        assert!(matches("*.sw[a-p]", ".notes.swp"));
+        assert!(!matches("*.sw[a-p]", ".notes.swx"));
+        assert!(matches("[!.]*", "notes"));
+        assert!(!matches("[^.]*", ".notes"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("[a-]", "-"));
+        assert!(matches("[\u{e9}e]", "\u{e9}"));
+        assert!(matches("[[]x]", "[x]"));
+    }
+
+    #[test]
+    fn test_invalid_bytes_are_characters() {
+        assert!(matches("*.tmp", b"caf\xe9.tmp"));
+        assert!(matches("caf?.tmp", b"caf\xe9.tmp"));
+        assert!(matches("caf[!e].tmp", b"caf\xe9.tmp"));
+        assert!(!matches("caf[e\u{e9}].tmp", b"caf\xe9.tmp"));
+        assert!(matches_path("**/*.tmp", b"d\xe9j\xe0/caf\xe9.tmp"));
+        assert!(!matches_path("*.tmp", b"d\xe9j\xe0/caf\xe9.tmp"));
+    }
+
+    #[test]
+    fn test_braces() {
+        assert!(matches("~$*.{docx,xlsx,pptx}", "~$budget.xlsx"));
+        assert!(!matches("~$*.{docx,xlsx,pptx}", "~$budget.pdf"));
+        assert!(matches("{Thumbs,ehthumbs}.db", "ehthumbs.db"));
+        assert!(matches("*.{sw[a-p],{bak,old}}", "x.old"));
+        assert!(matches("*.{tmp,}", "x."));
+        assert!(matches("a,b", "a,b"));
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert!(matches(r"\*.txt", "*.txt"));
+        assert!(!matches(r"\*.txt", "a.txt"));
+        assert!(matches(r"\{a,b\}", "{a,b}"));
+    }
+
+    #[test]
+    fn test_path_globs() {
+        assert!(matches_path("build/*.o", "build/main.o"));
+        assert!(!matches_path("build/*.o", "build/sub/main.o"));
+        assert!(matches_path("build/**/*.o", "build/main.o"));
+        assert!(matches_path("build/**/*.o", "build/sub/deep/main.o"));
+        assert!(matches_path("**/.DS_Store", ".DS_Store"));
+        assert!(matches_path("cache/**", "cache/a/b"));
+        assert!(!matches_path("[!x]", "/"));
+    }
+
+    #[test]
+    fn test_invalid_globs() {
+        for glob in ["[abc", "{a,b", "a\\", "[z-a]"] {
+            let valid = to_regex(glob, false)
+                .ok()
+                .and_then(|regex| Regex::new(&regex).ok());
+            assert!(valid.is_none(), "{}", glob);
+        }
+        assert_eq!(
+            to_regex("{a,b", false).unwrap_err().to_string(),
+            "Invalid glob pattern '{a,b': unclosed '{'"
+        );
+    }
+}
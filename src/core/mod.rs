@@ -1,5 +1,15 @@
+mod appledouble;
+mod audit;
 mod cleaner;
+mod editor;
+mod globs;
 mod patterns;
+mod rules;
+mod server_names;
+mod signatures;
 
+pub use audit::LeakAuditor;
 pub use cleaner::Cleaner;
-pub use patterns::PatternMatcher;
+pub use patterns::{NameGlob, PatternMatcher, REGEX_PREFIX};
+pub use rules::CompiledRule;
+pub use signatures::Signature;
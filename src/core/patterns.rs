@@ -1,20 +1,309 @@
-use anyhow::Result;
-use glob::Pattern;
+use anyhow::{anyhow, Result};
+use regex::bytes::{Regex, RegexSet};
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::fmt;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
 
+use super::{globs, Signature};
+use crate::models::{Action, ConditionalPattern, Conditions, RenameTo};
+
+/// A glob over names that compares them in Unicode NFC, so the NFD names macOS
+/// writes match patterns typed elsewhere, and optionally ignores case
+#[derive(Debug)]
+pub struct NameGlob {
+    regex: Regex,
+}
+
+impl NameGlob {
+    pub fn new(text: &str, ignore_case: bool) -> Result<Self> {
+        Self::compile(text, ignore_case, false)
+    }
+
+    /// A glob over relative paths: `*` stays within one component, `**` crosses them
+    pub fn for_paths(text: &str, ignore_case: bool) -> Result<Self> {
+        Self::compile(text, ignore_case, true)
+    }
+
+    fn compile(text: &str, ignore_case: bool, paths: bool) -> Result<Self> {
+        let source = glob_source(text, ignore_case, paths)?;
+        let regex = Regex::new(&source).map_err(|e| invalid_glob(text, e))?;
+        Ok(Self { regex })
+    }
+
+    pub fn matches(&self, name: &(impl AsRef<OsStr> + ?Sized)) -> bool {
+        self.regex.is_match(&match_bytes(name.as_ref()))
+    }
+}
+
+fn glob_source(text: &str, ignore_case: bool, paths: bool) -> Result<String> {
+    let source = globs::to_regex(&fold(text), paths)?;
+    Ok(with_case(source, ignore_case))
+}
+
+fn with_case(source: String, ignore_case: bool) -> String {
+    if ignore_case {
+        format!("(?i){}", source)
+    } else {
+        source
+    }
+}
+
+/// Translated globs can still hold a bad class range such as `[z-a]`
+fn invalid_glob(text: &str, e: regex::Error) -> anyhow::Error {
+    anyhow!("Invalid glob pattern '{}': {}", text, e)
+}
+
+/// The bytes patterns see for a file name: the name as stored, in NFC where it
+/// is UTF-8. Names that aren't, like the Latin-1 names of files copied off old
+/// volumes, keep their invalid bytes: wildcards count each as one character,
+/// and no typed literal matches them.
+fn match_bytes(name: &OsStr) -> Cow<'_, [u8]> {
+    let bytes = os_bytes(name);
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return match fold(text) {
+            Cow::Borrowed(text) => Cow::Borrowed(text.as_bytes()),
+            Cow::Owned(text) => Cow::Owned(text.into_bytes()),
+        };
+    }
+    let mut folded = Vec::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        folded.extend_from_slice(fold(chunk.valid()).as_bytes());
+        folded.extend_from_slice(chunk.invalid());
+    }
+    Cow::Owned(folded)
+}
+
+#[cfg(unix)]
+fn os_bytes(name: &OsStr) -> &[u8] {
+    name.as_bytes()
+}
+
+#[cfg(not(unix))]
+fn os_bytes(name: &OsStr) -> &[u8] {
+    name.as_encoded_bytes()
+}
+
+/// A file name as text, for display and for checks on its ASCII parts. Bytes
+/// that aren't UTF-8 become U+FFFD; patterns match the bytes themselves.
+pub fn lossy_text(name: &OsStr) -> Cow<'_, str> {
+    if let Some(text) = name.to_str() {
+        return Cow::Borrowed(text);
+    }
+    let mut text = String::new();
+    for chunk in name.as_encoded_bytes().utf8_chunks() {
+        text.push_str(chunk.valid());
+        text.extend(chunk.invalid().iter().map(|_| char::REPLACEMENT_CHARACTER));
+    }
+    Cow::Owned(text)
+}
+
+/// `text` in NFC. Case is left to the compiled patterns.
+fn fold(text: &str) -> Cow<'_, str> {
+    match is_nfc_quick(text.chars()) {
+        IsNormalized::Yes => Cow::Borrowed(text),
+        _ => Cow::Owned(text.nfc().collect()),
+    }
+}
+
+/// Prefix marking a pattern as a regular expression rather than a glob
+pub const REGEX_PREFIX: &str = "re:";
+
+/// How a rule's pattern compares names, for explaining matches
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PatternKind {
+    Glob,
+    Regex,
+}
+
+impl fmt::Display for PatternKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternKind::Glob => write!(f, "glob"),
+            PatternKind::Regex => write!(f, "regex"),
+        }
+    }
+}
+
+/// A pattern as a regex source, checked on its own so a bad one is reported
+/// by itself before joining the matcher's set
+struct NamePattern {
+    kind: PatternKind,
+    source: String,
+}
+
+impl NamePattern {
+    fn new(text: &str, ignore_case: bool) -> Result<Self> {
+        let Some(source) = text.strip_prefix(REGEX_PREFIX) else {
+            let source = glob_source(text, ignore_case, false)?;
+            Regex::new(&source).map_err(|e| invalid_glob(text, e))?;
+            return Ok(Self {
+                kind: PatternKind::Glob,
+                source,
+            });
+        };
+        let source = with_case(fold(source).into_owned(), ignore_case);
+        Regex::new(&source).map_err(|e| anyhow!("Invalid regex pattern '{}': {}", text, e))?;
+        Ok(Self {
+            kind: PatternKind::Regex,
+            source,
+        })
+    }
+}
+
+/// A name pattern, optionally requiring the file's contents to carry a signature
+/// and its metadata to meet age and size conditions, and what to do with matches
+pub struct Rule {
+    text: String,
+    pattern: NamePattern,
+    signature: Option<Signature>,
+    conditions: Conditions,
+    action: Action,
+    rename_to: Option<RenameTo>,
+}
+
+impl Rule {
+    fn new(pattern: &str, ignore_case: bool) -> Result<Self> {
+        Ok(Self {
+            text: pattern.to_string(),
+            pattern: NamePattern::new(pattern, ignore_case)?,
+            signature: None,
+            conditions: Conditions::default(),
+            action: Action::Delete,
+            rename_to: None,
+        })
+    }
+
+    /// The pattern as written, including any `re:` prefix
+    pub fn pattern(&self) -> &str {
+        &self.text
+    }
+
+    pub fn kind(&self) -> PatternKind {
+        self.pattern.kind
+    }
+
+    pub fn signature(&self) -> Option<Signature> {
+        self.signature
+    }
+
+    pub fn conditions(&self) -> &Conditions {
+        &self.conditions
+    }
+
+    pub fn action(&self) -> Action {
+        self.action
+    }
+
+    pub fn rename_to(&self) -> Option<&RenameTo> {
+        self.rename_to.as_ref()
+    }
+}
+
+/// Glob and regex rules, tried in the order they were added. Every pattern is
+/// compiled into one regex set, so each name is scanned once however many
+/// patterns there are, and the scan reports all the patterns that matched.
 pub struct PatternMatcher {
-    patterns: Vec<Pattern>,
+    rules: Vec<Rule>,
+    set: RegexSet,
+    ignore_case: bool,
 }
 
 impl PatternMatcher {
     pub fn new(patterns: &[String]) -> Result<Self> {
-        let compiled: Result<Vec<_>, _> = patterns.iter().map(|p| Pattern::new(p)).collect();
-        Ok(Self {
-            patterns: compiled?,
-        })
+        let rules: Result<Vec<_>> = patterns.iter().map(|p| Rule::new(p, false)).collect();
+        Self {
+            rules: rules?,
+            set: RegexSet::empty(),
+            ignore_case: false,
+        }
+        .compile()
     }
 
-    pub fn matches(&self, filename: &str) -> bool {
-        self.patterns.iter().any(|p| p.matches(filename))
+    /// Match the patterns added so far, and validated patterns added later,
+    /// regardless of case. Conditional patterns choose for themselves.
+    pub fn with_ignore_case(mut self, ignore_case: bool) -> Result<Self> {
+        if ignore_case && !self.ignore_case {
+            for rule in &mut self.rules {
+                rule.pattern = NamePattern::new(&rule.text, true)?;
+            }
+        }
+        self.ignore_case = ignore_case;
+        self.compile()
+    }
+
+    /// Add content-validated rules: a file matches only if its name matches and its
+    /// header carries the signature known for the pattern (see `Signature::for_pattern`)
+    pub fn with_validated(mut self, patterns: &[String]) -> Result<Self> {
+        for p in patterns {
+            let signature = Signature::for_pattern(p)
+                .ok_or_else(|| anyhow!("No content check is known for pattern '{}'", p))?;
+            self.rules.push(Rule {
+                signature: Some(signature),
+                ..Rule::new(p, self.ignore_case)?
+            });
+        }
+        self.compile()
+    }
+
+    /// Add rules that match only files whose metadata meets the pattern's conditions,
+    /// each with the pattern's action
+    pub fn with_conditions(mut self, patterns: &[ConditionalPattern]) -> Result<Self> {
+        for p in patterns {
+            self.rules.push(Rule {
+                conditions: p.conditions.clone(),
+                action: p.action,
+                rename_to: p.rename_to.clone(),
+                ..Rule::new(&p.pattern, p.ignore_case)?
+            });
+        }
+        self.compile()
+    }
+
+    /// Rebuild the set from the rules, keeping their order
+    fn compile(mut self) -> Result<Self> {
+        self.set = RegexSet::new(self.rules.iter().map(|r| &r.pattern.source))?;
+        Ok(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn matches(&self, filename: &(impl AsRef<OsStr> + ?Sized)) -> bool {
+        self.matching_pattern(filename).is_some()
+    }
+
+    /// Return the first pattern (as written) that matches `filename`
+    pub fn matching_pattern(&self, filename: &(impl AsRef<OsStr> + ?Sized)) -> Option<&str> {
+        self.matching_rule(filename).map(|(pattern, _)| pattern)
+    }
+
+    /// Return the first pattern matching `filename` by name, along with the
+    /// signature the file's contents must carry for it to count as a match
+    pub fn matching_rule(
+        &self,
+        filename: &(impl AsRef<OsStr> + ?Sized),
+    ) -> Option<(&str, Option<Signature>)> {
+        self.matching_rules(filename)
+            .next()
+            .map(|r| (r.pattern(), r.signature))
+    }
+
+    /// Every rule matching `filename` by name, in the order they were added
+    pub fn matching_rules(
+        &self,
+        filename: &(impl AsRef<OsStr> + ?Sized),
+    ) -> impl Iterator<Item = &Rule> {
+        let matched = if self.rules.is_empty() {
+            Vec::new()
+        } else {
+            let name = match_bytes(filename.as_ref());
+            self.set.matches(&name).into_iter().collect()
+        };
+        matched.into_iter().map(|index| &self.rules[index])
     }
 }
 
@@ -52,4 +341,233 @@ mod tests {
         assert!(!matcher.matches("readme.md"));
         assert!(!matcher.matches("DS_Store")); // Missing dot
     }
+
+    #[test]
+    fn test_validated_rule() {
+        let matcher = PatternMatcher::new(&["Thumbs.db".to_string()])
+            .unwrap()
+            .with_validated(&["*:Zone.Identifier".to_string()])
+            .unwrap();
+
+        assert_eq!(
+            matcher.matching_rule("Thumbs.db"),
+            Some(("Thumbs.db", None))
+        );
+        assert_eq!(
+            matcher.matching_rule("setup.exe:Zone.Identifier"),
+            Some(("*:Zone.Identifier", Some(Signature::ZoneIdentifier)))
+        );
+        assert_eq!(matcher.matching_rule("setup.exe"), None);
+    }
+
+    #[test]
+    fn test_validated_rule_needs_known_signature() {
+        let err = PatternMatcher::new(&[])
+            .unwrap()
+            .with_validated(&["*.bak".to_string()])
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("No content check is known"));
+    }
+
+    #[test]
+    fn test_conditional_rules() {
+        let older_than_day = ConditionalPattern {
+            pattern: "*.tmp".to_string(),
+            conditions: Conditions {
+                older_than: Some(std::time::Duration::from_secs(86400)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let matcher = PatternMatcher::new(&[".DS_Store".to_string()])
+            .unwrap()
+            .with_conditions(std::slice::from_ref(&older_than_day))
+            .unwrap();
+
+        let rules: Vec<_> = matcher.matching_rules("build.tmp").collect();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].pattern(), "*.tmp");
+        assert_eq!(rules[0].conditions(), &older_than_day.conditions);
+        assert!(matcher
+            .matching_rules(".DS_Store")
+            .all(|r| r.conditions().is_empty()));
+        assert_eq!(matcher.matching_rules("notes.txt").count(), 0);
+    }
+
+    #[test]
+    fn test_names_compared_in_nfc() {
+        let nfc = "Caf\u{e9}.txt";
+        let nfd = "Cafe\u{301}.txt";
+        let matcher = PatternMatcher::new(&[nfc.to_string()]).unwrap();
+        assert!(matcher.matches(nfd));
+        assert!(matcher.matches(nfc));
+
+        let matcher = PatternMatcher::new(&["Cafe\u{301}*".to_string()]).unwrap();
+        assert!(matcher.matches(nfc));
+        assert_eq!(matcher.matching_pattern(nfc), Some("Cafe\u{301}*"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_names() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let latin1 = OsStr::from_bytes(b"caf\xe9.tmp");
+        assert_eq!(lossy_text(latin1), "caf\u{fffd}.tmp");
+        assert_eq!(lossy_text(OsStr::new("caf\u{e9}.tmp")), "caf\u{e9}.tmp");
+
+        let matcher = PatternMatcher::new(&["*.tmp".to_string(), "caf?.db".to_string()]).unwrap();
+        assert!(matcher.matches(latin1));
+        // Each invalid byte is one character to wildcards
+        assert!(matcher.matches(OsStr::from_bytes(b"caf\xe9.db")));
+        assert!(!matcher.matches(OsStr::from_bytes(b"caf\xc3\x28.db")));
+        // Only the bytes themselves match, not the text shown for them
+        for pattern in ["caf\u{e9}.tmp", "caf\u{fffd}.tmp"] {
+            let matcher = PatternMatcher::new(&[pattern.to_string()]).unwrap();
+            assert!(!matcher.matches(latin1), "{}", pattern);
+        }
+        let matcher = PatternMatcher::new(&[r"re:^caf(?-u:\xE9)\.tmp$".to_string()]).unwrap();
+        assert!(matcher.matches(latin1));
+        assert!(!matcher.matches("caf\u{e9}.tmp"));
+    }
+
+    #[test]
+    fn test_braces_and_every_match_reported() {
+        let matcher = PatternMatcher::new(&[
+            "~$*.{docx,xlsx}".to_string(),
+            "*.sw[a-p]".to_string(),
+            "~$*".to_string(),
+        ])
+        .unwrap();
+
+        let patterns: Vec<_> = matcher
+            .matching_rules("~$budget.xlsx")
+            .map(|r| r.pattern())
+            .collect();
+        assert_eq!(patterns, vec!["~$*.{docx,xlsx}", "~$*"]);
+        assert_eq!(matcher.matching_pattern(".x.swp"), Some("*.sw[a-p]"));
+        assert!(!matcher.matches(".x.swz"));
+
+        let err = PatternMatcher::new(&["*.{tmp".to_string()]).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Invalid glob pattern '*.{tmp': unclosed '{'"
+        );
+    }
+
+    #[test]
+    fn test_regex_patterns() {
+        let matcher = PatternMatcher::new(&[
+            r"re:^\.~lock\..*#$".to_string(),
+            "*.tmp".to_string(),
+            r"re:^~\$.*\.(docx|xlsx)$".to_string(),
+            r"re:^.+ \(conflicted copy \d{4}-\d{2}-\d{2}\)(\.[^.]+)?$".to_string(),
+        ])
+        .unwrap();
+
+        assert!(matcher.matches(".~lock.report.odt#"));
+        assert!(matcher.matches("~$budget.xlsx"));
+        assert!(matcher.matches("notes (conflicted copy 2024-01-02).txt"));
+        assert!(!matcher.matches("~$budget.pdf"));
+        assert!(!matcher.matches("notes (conflicted copy).txt"));
+        // Regexes search the name, so only anchored ones must match all of it
+        assert!(!matcher.matches("x.~lock.report.odt#"));
+
+        let rule = matcher.matching_rules("~$budget.docx").next().unwrap();
+        assert_eq!(rule.pattern(), r"re:^~\$.*\.(docx|xlsx)$");
+        assert_eq!(rule.kind(), PatternKind::Regex);
+        let rule = matcher.matching_rules("a.tmp").next().unwrap();
+        assert_eq!(rule.kind(), PatternKind::Glob);
+    }
+
+    #[test]
+    fn test_regex_and_glob_rules_keep_their_order() {
+        let matcher = PatternMatcher::new(&["re:^a".to_string(), "*.tmp".to_string()])
+            .unwrap()
+            .with_conditions(&[ConditionalPattern {
+                pattern: r"re:\.tmp$".to_string(),
+                ignore_case: true,
+                ..Default::default()
+            }])
+            .unwrap();
+
+        let patterns: Vec<_> = matcher
+            .matching_rules("a.tmp")
+            .map(|r| r.pattern())
+            .collect();
+        assert_eq!(patterns, vec!["re:^a", "*.tmp", r"re:\.tmp$"]);
+        let patterns: Vec<_> = matcher
+            .matching_rules("B.TMP")
+            .map(|r| r.pattern())
+            .collect();
+        assert_eq!(patterns, vec![r"re:\.tmp$"]);
+    }
+
+    #[test]
+    fn test_regex_ignore_case_and_nfc() {
+        let matcher = PatternMatcher::new(&["re:^Cafe\u{301}$".to_string()]).unwrap();
+        assert!(matcher.matches("Caf\u{e9}"));
+        assert!(!matcher.matches("CAF\u{c9}"));
+
+        let matcher = matcher.with_ignore_case(true).unwrap();
+        assert!(matcher.matches("CAF\u{c9}"));
+    }
+
+    #[test]
+    fn test_invalid_regex_is_rejected() {
+        let err = PatternMatcher::new(&["re:(unclosed".to_string()])
+            .err()
+            .unwrap();
+        assert!(err
+            .to_string()
+            .starts_with("Invalid regex pattern 're:(unclosed'"));
+    }
+
+    #[test]
+    fn test_ignore_case() {
+        let matcher =
+            PatternMatcher::new(&[".DS_Store".to_string(), "Caf\u{e9}".to_string()]).unwrap();
+        assert!(!matcher.matches(".ds_store"));
+
+        let matcher = matcher
+            .with_ignore_case(true)
+            .unwrap()
+            .with_validated(&["Thumbs.db".to_string()])
+            .unwrap();
+        assert!(matcher.matches(".ds_store"));
+        assert!(matcher.matches("CAF\u{c9}"));
+        assert!(matcher.matches("CAFE\u{301}"));
+        assert_eq!(
+            matcher.matching_rule("THUMBS.DB"),
+            Some(("Thumbs.db", Some(Signature::OleCompound)))
+        );
+    }
+
+    #[test]
+    fn test_conditional_pattern_chooses_case() {
+        let matcher = PatternMatcher::new(&[])
+            .unwrap()
+            .with_conditions(&[
+                ConditionalPattern {
+                    pattern: "*.bak".to_string(),
+                    ignore_case: true,
+                    ..Default::default()
+                },
+                ConditionalPattern {
+                    pattern: "*.tmp".to_string(),
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+        assert!(matcher.matches("NOTES.BAK"));
+        assert!(!matcher.matches("NOTES.TMP"));
+    }
+
+    #[test]
+    fn test_matching_pattern_returns_first_match() {
+        let matcher = PatternMatcher::new(&["*.db".to_string(), "Thumbs.db".to_string()]).unwrap();
+        assert_eq!(matcher.matching_pattern("Thumbs.db"), Some("*.db"));
+        assert_eq!(matcher.matching_pattern("readme.md"), None);
+    }
 }
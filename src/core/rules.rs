@@ -0,0 +1,710 @@
+//! The rule language used in config files, e.g.
+//! `name("*.orig") and age > 3d and not path("vendor/**")`.
+//!
+//! A rule is parsed once into an expression tree. Evaluation is three-valued:
+//! predicates on facts that haven't been loaded yet (metadata, owner, content)
+//! are unknown, and `and`/`or` short-circuit around them, so a file is only
+//! stat'ed or read when the name and path alone can't decide the rule.
+
+use anyhow::{anyhow, bail, Result};
+use std::ffi::OsStr;
+use std::time::{Duration, SystemTime};
+
+use super::patterns::NameGlob;
+use crate::models::units::{parse_duration, parse_size};
+use crate::models::{Action, FileMetadata, FileOwner, RenameTo, RuleConfig};
+
+/// Bytes read from the start of a file for `content(...)`
+pub const CONTENT_SCAN_LEN: usize = 4096;
+
+const PREDICATES: &str = "name, iname, path, ipath, age, size, owner, content";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Cmp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+}
+
+impl Cmp {
+    fn holds<T: PartialOrd>(self, lhs: T, rhs: T) -> bool {
+        match self {
+            Cmp::Lt => lhs < rhs,
+            Cmp::Le => lhs <= rhs,
+            Cmp::Gt => lhs > rhs,
+            Cmp::Ge => lhs >= rhs,
+            Cmp::Eq => lhs == rhs,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Predicate {
+    /// File name glob, with the glob as written
+    Name(NameGlob, String),
+    /// Glob over the path relative to the cleaned root
+    Path(NameGlob),
+    /// Time since last modification
+    Age(Cmp, Duration),
+    Size(Cmp, u64),
+    /// User name or numeric uid
+    Owner(String),
+    /// Text somewhere in the first `CONTENT_SCAN_LEN` bytes
+    Content(Vec<u8>),
+}
+
+impl Predicate {
+    fn evaluate(&self, facts: &Facts) -> Option<bool> {
+        match self {
+            Predicate::Name(pattern, _) => Some(pattern.matches(facts.name)),
+            Predicate::Path(pattern) => Some(pattern.matches(facts.path)),
+            Predicate::Age(cmp, limit) => {
+                let metadata = facts.metadata.as_ref()?.as_ref()?;
+                let age = facts
+                    .now
+                    .duration_since(metadata.modified)
+                    .unwrap_or_default();
+                Some(cmp.holds(age, *limit))
+            }
+            Predicate::Size(cmp, limit) => {
+                let metadata = facts.metadata.as_ref()?.as_ref()?;
+                Some(cmp.holds(metadata.size, *limit))
+            }
+            Predicate::Owner(user) => {
+                let owner = facts.owner.as_ref()?.as_ref()?;
+                Some(owner.name.as_deref() == Some(user) || user.parse() == Ok(owner.uid))
+            }
+            Predicate::Content(text) => {
+                let head = facts.head.as_ref()?.as_ref()?;
+                Some(text.is_empty() || head.windows(text.len()).any(|w| w == text))
+            }
+        }
+    }
+
+    fn needs(&self) -> Needs {
+        match self {
+            Predicate::Name(..) | Predicate::Path(_) => Needs::default(),
+            Predicate::Age(..) | Predicate::Size(..) => Needs {
+                metadata: true,
+                ..Default::default()
+            },
+            Predicate::Owner(_) => Needs {
+                owner: true,
+                ..Default::default()
+            },
+            Predicate::Content(_) => Needs {
+                content: true,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Predicate(Predicate),
+}
+
+impl Expr {
+    fn evaluate(&self, facts: &Facts) -> Option<bool> {
+        match self {
+            Expr::And(lhs, rhs) => match lhs.evaluate(facts) {
+                Some(false) => Some(false),
+                left => match (left, rhs.evaluate(facts)) {
+                    (_, Some(false)) => Some(false),
+                    (Some(true), Some(true)) => Some(true),
+                    _ => None,
+                },
+            },
+            Expr::Or(lhs, rhs) => match lhs.evaluate(facts) {
+                Some(true) => Some(true),
+                left => match (left, rhs.evaluate(facts)) {
+                    (_, Some(true)) => Some(true),
+                    (Some(false), Some(false)) => Some(false),
+                    _ => None,
+                },
+            },
+            Expr::Not(inner) => inner.evaluate(facts).map(|v| !v),
+            Expr::Predicate(predicate) => predicate.evaluate(facts),
+        }
+    }
+
+    fn needs(&self) -> Needs {
+        match self {
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => lhs.needs().union(rhs.needs()),
+            Expr::Not(inner) => inner.needs(),
+            Expr::Predicate(predicate) => predicate.needs(),
+        }
+    }
+
+    /// Name globs that count towards a match, leaving out negated ones
+    fn name_patterns<'e>(&'e self, negated: bool, patterns: &mut Vec<&'e str>) {
+        match self {
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+                lhs.name_patterns(negated, patterns);
+                rhs.name_patterns(negated, patterns);
+            }
+            Expr::Not(inner) => inner.name_patterns(!negated, patterns),
+            Expr::Predicate(Predicate::Name(_, text)) if !negated => patterns.push(text),
+            Expr::Predicate(_) => {}
+        }
+    }
+
+    /// Whether the expression can only hold for a file matching some
+    /// non-negated name glob
+    fn requires_name(&self, negated: bool) -> bool {
+        match (self, negated) {
+            (Expr::And(lhs, rhs), false) | (Expr::Or(lhs, rhs), true) => {
+                lhs.requires_name(negated) || rhs.requires_name(negated)
+            }
+            (Expr::Or(lhs, rhs), false) | (Expr::And(lhs, rhs), true) => {
+                lhs.requires_name(negated) && rhs.requires_name(negated)
+            }
+            (Expr::Not(inner), _) => inner.requires_name(!negated),
+            (Expr::Predicate(Predicate::Name(..)), false) => true,
+            (Expr::Predicate(_), _) => false,
+        }
+    }
+}
+
+/// Facts about a file beyond its name and path that a rule may depend on
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Needs {
+    pub metadata: bool,
+    pub owner: bool,
+    pub content: bool,
+}
+
+impl Needs {
+    fn union(self, other: Needs) -> Needs {
+        Needs {
+            metadata: self.metadata || other.metadata,
+            owner: self.owner || other.owner,
+            content: self.content || other.content,
+        }
+    }
+}
+
+/// What is known about one file while rules are evaluated. Each loadable fact
+/// is `None` until loaded and `Some(None)` if it couldn't be read.
+pub struct Facts<'a> {
+    pub name: &'a OsStr,
+    /// Path relative to the cleaned root, `/`-separated
+    pub path: &'a OsStr,
+    pub now: SystemTime,
+    pub metadata: Option<Option<FileMetadata>>,
+    pub owner: Option<Option<FileOwner>>,
+    /// The first `CONTENT_SCAN_LEN` bytes
+    pub head: Option<Option<Vec<u8>>>,
+}
+
+impl<'a> Facts<'a> {
+    pub fn new<N, P>(name: &'a N, path: &'a P, now: SystemTime) -> Self
+    where
+        N: AsRef<OsStr> + ?Sized,
+        P: AsRef<OsStr> + ?Sized,
+    {
+        Self {
+            name: name.as_ref(),
+            path: path.as_ref(),
+            now,
+            metadata: None,
+            owner: None,
+            head: None,
+        }
+    }
+}
+
+/// A config rule ready to evaluate
+#[derive(Debug)]
+pub struct CompiledRule {
+    source: String,
+    action: Action,
+    rename_to: Option<RenameTo>,
+    expr: Expr,
+    needs: Needs,
+}
+
+impl CompiledRule {
+    pub fn compile(rule: &RuleConfig) -> Result<Self> {
+        let expr = Parser::new(&rule.when)
+            .and_then(|p| p.parse())
+            .map_err(|e| anyhow!("Invalid rule '{}': {}", rule.when, e))?;
+        rule.action
+            .check_rename_to(rule.rename_to.as_ref())
+            .map_err(|e| anyhow!("Invalid rule '{}': {}", rule.when, e))?;
+        Ok(Self {
+            source: rule.when.clone(),
+            action: rule.action,
+            rename_to: rule.rename_to.clone(),
+            needs: expr.needs(),
+            expr,
+        })
+    }
+
+    /// The rule as written
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn action(&self) -> Action {
+        self.action
+    }
+
+    pub fn rename_to(&self) -> Option<&RenameTo> {
+        self.rename_to.as_ref()
+    }
+
+    /// The `name` and `iname` globs a file must match for the rule to hold,
+    /// for the same breadth checks as plain patterns
+    pub fn name_patterns(&self) -> Vec<&str> {
+        let mut patterns = Vec::new();
+        self.expr.name_patterns(false, &mut patterns);
+        patterns
+    }
+
+    /// Whether every file the rule matches must also match one of its name
+    /// globs, so the name checks above bound what it can select
+    pub fn requires_name(&self) -> bool {
+        self.expr.requires_name(false)
+    }
+
+    /// Facts the rule may have to load to reach a verdict
+    pub fn needs(&self) -> Needs {
+        self.needs
+    }
+
+    /// `Some(verdict)` once the facts decide the rule, `None` while it still
+    /// depends on facts that haven't been loaded
+    pub fn evaluate(&self, facts: &Facts) -> Option<bool> {
+        self.expr.evaluate(facts)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    /// A number with an optional unit, such as `3d` or `10M`
+    Value(String),
+    Cmp(Cmp),
+    Open,
+    Close,
+}
+
+fn describe(token: Option<&Token>) -> String {
+    match token {
+        None => "end of rule".to_string(),
+        Some(Token::Ident(name)) => format!("'{}'", name),
+        Some(Token::Str(text)) => format!("\"{}\"", text),
+        Some(Token::Value(value)) => format!("'{}'", value),
+        Some(Token::Cmp(_)) => "comparison".to_string(),
+        Some(Token::Open) => "'('".to_string(),
+        Some(Token::Close) => "')'".to_string(),
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        let column = start + 1;
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let token = match c {
+            '(' => {
+                chars.next();
+                Token::Open
+            }
+            ')' => {
+                chars.next();
+                Token::Close
+            }
+            '<' | '>' | '=' => {
+                chars.next();
+                let or_equal = chars.next_if(|&(_, c)| c == '=').is_some();
+                Token::Cmp(match (c, or_equal) {
+                    ('<', false) => Cmp::Lt,
+                    ('<', true) => Cmp::Le,
+                    ('>', false) => Cmp::Gt,
+                    ('>', true) => Cmp::Ge,
+                    _ => Cmp::Eq,
+                })
+            }
+            '"' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, c @ ('"' | '\\'))) => text.push(c),
+                            _ => bail!("unsupported escape in string at column {}", column),
+                        },
+                        Some((_, c)) => text.push(c),
+                        None => bail!("unterminated string at column {}", column),
+                    }
+                }
+                Token::Str(text)
+            }
+            c if c.is_ascii_digit() => {
+                let mut value = String::new();
+                while let Some((_, c)) = chars.next_if(|&(_, c)| c.is_alphanumeric() || c == '.') {
+                    value.push(c);
+                }
+                Token::Value(value)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut ident = String::new();
+                while let Some((_, c)) = chars.next_if(|&(_, c)| c.is_alphanumeric() || c == '_') {
+                    ident.push(c);
+                }
+                Token::Ident(ident)
+            }
+            other => bail!("unexpected '{}' at column {}", other, column),
+        };
+        tokens.push((column, token));
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over `or` < `and` < `not` < predicate
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    len: usize,
+}
+
+impl Parser {
+    fn new(input: &str) -> Result<Self> {
+        Ok(Self {
+            tokens: tokenize(input)?,
+            pos: 0,
+            len: input.len(),
+        })
+    }
+
+    fn parse(mut self) -> Result<Expr> {
+        let expr = self.parse_or()?;
+        if self.pos < self.tokens.len() {
+            bail!("{}", self.unexpected("'and', 'or' or end of rule"));
+        }
+        Ok(expr)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(_, t)| t.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(k)) if k == keyword) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn unexpected(&self, expected: &str) -> String {
+        let column = self
+            .tokens
+            .get(self.pos)
+            .map_or(self.len + 1, |(column, _)| *column);
+        format!(
+            "expected {} but found {} at column {}",
+            expected,
+            describe(self.peek()),
+            column
+        )
+    }
+
+    fn expect(&mut self, token: Token) -> Result<()> {
+        if self.peek() != Some(&token) {
+            bail!("{}", self.unexpected(&describe(Some(&token))));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.eat_keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_not()?;
+        while self.eat_keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr> {
+        if self.eat_keyword("not") {
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        if self.peek() == Some(&Token::Open) {
+            self.pos += 1;
+            let expr = self.parse_or()?;
+            self.expect(Token::Close)?;
+            return Ok(expr);
+        }
+        self.parse_predicate().map(Expr::Predicate)
+    }
+
+    fn parse_predicate(&mut self) -> Result<Predicate> {
+        let Some(Token::Ident(name)) = self.peek().cloned() else {
+            bail!("{}", self.unexpected("a predicate"));
+        };
+        let column = self.tokens[self.pos].0;
+        self.pos += 1;
+
+        match name.as_str() {
+            "name" | "iname" => {
+                let text = self.parse_argument()?;
+                let glob = NameGlob::new(&text, name == "iname")?;
+                Ok(Predicate::Name(glob, text))
+            }
+            "path" | "ipath" => {
+                let glob = NameGlob::for_paths(&self.parse_argument()?, name == "ipath")?;
+                Ok(Predicate::Path(glob))
+            }
+            "owner" => Ok(Predicate::Owner(self.parse_argument()?)),
+            "content" => Ok(Predicate::Content(self.parse_argument()?.into_bytes())),
+            "age" => {
+                let (cmp, value) = self.parse_comparison()?;
+                Ok(Predicate::Age(cmp, parse_duration(&value)?))
+            }
+            "size" => {
+                let (cmp, value) = self.parse_comparison()?;
+                Ok(Predicate::Size(cmp, parse_size(&value)?))
+            }
+            other => bail!(
+                "unknown predicate '{}' at column {} (expected one of: {})",
+                other,
+                column,
+                PREDICATES
+            ),
+        }
+    }
+
+    /// `("text")`
+    fn parse_argument(&mut self) -> Result<String> {
+        self.expect(Token::Open)?;
+        let Some(Token::Str(text)) = self.peek().cloned() else {
+            bail!("{}", self.unexpected("a quoted string"));
+        };
+        self.pos += 1;
+        self.expect(Token::Close)?;
+        Ok(text)
+    }
+
+    /// `> 3d`, `<= "10M"`
+    fn parse_comparison(&mut self) -> Result<(Cmp, String)> {
+        let Some(Token::Cmp(cmp)) = self.peek().cloned() else {
+            bail!("{}", self.unexpected("a comparison (<, <=, >, >=, ==)"));
+        };
+        self.pos += 1;
+        match self.next() {
+            Some(Token::Value(value) | Token::Str(value)) => Ok((cmp, value)),
+            _ => {
+                self.pos -= 1;
+                bail!("{}", self.unexpected("a value such as 3d or 10M"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(86400);
+
+    fn rule(when: &str) -> CompiledRule {
+        CompiledRule::compile(&RuleConfig {
+            when: when.to_string(),
+            action: Action::Delete,
+            rename_to: None,
+        })
+        .unwrap()
+    }
+
+    fn compile_error(when: &str) -> String {
+        CompiledRule::compile(&RuleConfig {
+            when: when.to_string(),
+            action: Action::Delete,
+            rename_to: None,
+        })
+        .unwrap_err()
+        .to_string()
+    }
+
+    /// Facts with everything loaded
+    fn facts<'a>(name: &'a str, path: &'a str, size: u64, age: Duration) -> Facts<'a> {
+        let now = SystemTime::now();
+        Facts {
+            metadata: Some(Some(FileMetadata {
+                size,
+                modified: now - age,
+                inode: 1,
+                dev: 0,
+            })),
+            owner: Some(Some(FileOwner {
+                uid: 1000,
+                name: Some("alice".to_string()),
+            })),
+            head: Some(Some(b"<<<<<<< HEAD\nours\n".to_vec())),
+            ..Facts::new(name, path, now)
+        }
+    }
+
+    #[test]
+    fn test_example_rule() {
+        let rule = rule(r#"name("*.orig") and age > 3d and not path("vendor/**")"#);
+
+        assert_eq!(
+            rule.evaluate(&facts("a.orig", "src/a.orig", 1, DAY * 4)),
+            Some(true)
+        );
+        assert_eq!(
+            rule.evaluate(&facts("a.orig", "src/a.orig", 1, DAY)),
+            Some(false)
+        );
+        assert_eq!(
+            rule.evaluate(&facts("a.orig", "vendor/lib/a.orig", 1, DAY * 4)),
+            Some(false)
+        );
+        assert_eq!(
+            rule.evaluate(&facts("a.rs", "src/a.rs", 1, DAY * 4)),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn test_unloaded_facts_are_unknown_unless_short_circuited() {
+        let sized = rule(r#"name("*.orig") and size > 1K"#);
+        let now = SystemTime::now();
+
+        assert_eq!(
+            sized.evaluate(&Facts::new("a.rs", "a.rs", now)),
+            Some(false)
+        );
+        assert_eq!(sized.evaluate(&Facts::new("a.orig", "a.orig", now)), None);
+        assert_eq!(
+            sized.needs(),
+            Needs {
+                metadata: true,
+                ..Default::default()
+            }
+        );
+
+        let either = rule(r#"owner("alice") or name("*.tmp")"#);
+        assert_eq!(
+            either.evaluate(&Facts::new("x.tmp", "x.tmp", now)),
+            Some(true)
+        );
+        assert_eq!(either.evaluate(&Facts::new("x.rs", "x.rs", now)), None);
+    }
+
+    #[test]
+    fn test_unreadable_facts_stay_unknown() {
+        let rule = rule(r#"content("HEAD")"#);
+        let mut facts = Facts::new("a", "a", SystemTime::now());
+        facts.head = Some(None);
+        assert_eq!(rule.evaluate(&facts), None);
+    }
+
+    #[test]
+    fn test_precedence_and_parentheses() {
+        // not > and > or
+        let loose = rule(r#"name("a") or name("b") and size > 10"#);
+        let grouped = rule(r#"(name("a") or name("b")) and size > 10"#);
+
+        assert_eq!(loose.evaluate(&facts("a", "a", 1, DAY)), Some(true));
+        assert_eq!(grouped.evaluate(&facts("a", "a", 1, DAY)), Some(false));
+        assert_eq!(
+            rule(r#"not not name("a")"#).evaluate(&facts("a", "a", 1, DAY)),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn test_owner_and_content() {
+        let f = facts("a.orig", "a.orig", 1, DAY);
+        assert_eq!(rule(r#"owner("alice")"#).evaluate(&f), Some(true));
+        assert_eq!(rule(r#"owner("1000")"#).evaluate(&f), Some(true));
+        assert_eq!(rule(r#"owner("bob")"#).evaluate(&f), Some(false));
+        assert_eq!(rule(r#"content("<<<<<<<")"#).evaluate(&f), Some(true));
+        assert_eq!(rule(r#"content("\"quoted\"")"#).evaluate(&f), Some(false));
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let f = facts("a", "a", 2048, DAY);
+        assert_eq!(rule("size >= 2K").evaluate(&f), Some(true));
+        assert_eq!(rule("size > 2K").evaluate(&f), Some(false));
+        assert_eq!(rule("size == 2048").evaluate(&f), Some(true));
+        assert_eq!(rule(r#"size < "1.5 KiB""#).evaluate(&f), Some(false));
+        assert_eq!(rule("age <= 25h").evaluate(&f), Some(true));
+    }
+
+    #[test]
+    fn test_path_star_stays_in_one_directory() {
+        let f = facts("x.orig", "src/deep/x.orig", 1, DAY);
+        assert_eq!(rule(r#"path("src/*")"#).evaluate(&f), Some(false));
+        assert_eq!(rule(r#"path("src/**")"#).evaluate(&f), Some(true));
+        assert_eq!(rule(r#"path("**/deep/*")"#).evaluate(&f), Some(true));
+    }
+
+    #[test]
+    fn test_case_insensitive_and_normalized_names() {
+        let f = facts("THUMBS.DB", "Photos/THUMBS.DB", 1, DAY);
+        assert_eq!(rule(r#"name("Thumbs.db")"#).evaluate(&f), Some(false));
+        assert_eq!(rule(r#"iname("Thumbs.db")"#).evaluate(&f), Some(true));
+        assert_eq!(rule(r#"ipath("photos/*")"#).evaluate(&f), Some(true));
+
+        // "Café" as macOS writes it (NFD) against a pattern typed in NFC
+        let f = facts("Cafe\u{301}.orig", "Cafe\u{301}.orig", 1, DAY);
+        assert_eq!(rule("name(\"Caf\u{e9}.*\")").evaluate(&f), Some(true));
+    }
+
+    #[test]
+    fn test_requires_name() {
+        assert!(rule(r#"name("*.bak") and size > 1M"#).requires_name());
+        assert!(rule(r#"name("*.bak") or iname("*.orig")"#).requires_name());
+        assert!(rule(r#"not (not name("*.bak") or size > 1M)"#).requires_name());
+        assert!(!rule(r#"name("*.bak") or size > 1M"#).requires_name());
+        assert!(!rule(r#"not name(".keep")"#).requires_name());
+        assert!(!rule(r#"path("**")"#).requires_name());
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        assert!(compile_error(r#"name("*.orig") and"#)
+            .contains("expected a predicate but found end of rule at column 19"));
+        assert!(compile_error(r#"colour("red")"#).contains("unknown predicate 'colour'"));
+        assert!(compile_error("age > soon").contains("expected a value such as 3d"));
+        assert!(compile_error("age > 3x").contains("Invalid duration '3x'"));
+        assert!(compile_error(r#"name("a""#).contains("expected ')'"));
+        assert!(compile_error(r#"name("a) or"#).contains("unterminated string"));
+        assert!(compile_error(r#"name("a") name("b")"#).contains("expected 'and', 'or'"));
+        assert!(compile_error("size ! 3").contains("unexpected '!'"));
+        assert!(compile_error("name(*.tmp)").contains("unexpected '*' at column 6"));
+    }
+}
@@ -0,0 +1,113 @@
+use std::borrow::Cow;
+
+/// Characters macOS allows in names but SMB doesn't, as Samba's catia/fruit
+/// modules and Services for Macintosh remap them into the private use area.
+const PRIVATE_USE_MAP: &[(char, char)] = &[
+    ('\u{F020}', '"'),
+    ('\u{F021}', '*'),
+    ('\u{F022}', ':'),
+    ('\u{F023}', '<'),
+    ('\u{F024}', '>'),
+    ('\u{F025}', '?'),
+    ('\u{F026}', '\\'),
+    ('\u{F027}', '|'),
+    ('\u{F028}', ' '),
+    ('\u{F029}', '.'),
+];
+
+/// Decode a name as a Mac client sees it when a Linux file server stores it mangled:
+/// netatalk's `:xx` hex escapes (`:2eDS_Store`) and Samba's private-use character
+/// mapping. Names without either encoding are returned unchanged.
+pub fn decode_server_name(name: &str) -> Cow<'_, str> {
+    let decoded = match decode_hex_escapes(name) {
+        Some(decoded) => Cow::Owned(decoded),
+        None => Cow::Borrowed(name),
+    };
+
+    if !decoded.chars().any(is_mapped_private_use) {
+        return decoded;
+    }
+    Cow::Owned(decoded.chars().map(unmap_private_use).collect())
+}
+
+/// Replace every `:` followed by two hex digits with that byte.
+/// Returns `None` when there is nothing to decode or the result isn't UTF-8.
+fn decode_hex_escapes(name: &str) -> Option<String> {
+    if !name.contains(':') {
+        return None;
+    }
+
+    let bytes = name.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut changed = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes
+            .get(i + 1..i + 3)
+            .filter(|h| h.iter().all(u8::is_ascii_hexdigit));
+        if let (b':', Some(hex)) = (bytes[i], escape) {
+            let hex = std::str::from_utf8(hex).expect("hex digits are ASCII");
+            decoded.push(u8::from_str_radix(hex, 16).expect("validated hex digits"));
+            changed = true;
+            i += 3;
+            continue;
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    if !changed {
+        return None;
+    }
+    String::from_utf8(decoded).ok()
+}
+
+fn is_mapped_private_use(c: char) -> bool {
+    ('\u{F001}'..='\u{F01F}').contains(&c) || PRIVATE_USE_MAP.iter().any(|&(from, _)| from == c)
+}
+
+fn unmap_private_use(c: char) -> char {
+    if ('\u{F001}'..='\u{F01F}').contains(&c) {
+        return char::from_u32(c as u32 - 0xF000).unwrap_or(c);
+    }
+    PRIVATE_USE_MAP
+        .iter()
+        .find(|&&(from, _)| from == c)
+        .map(|&(_, to)| to)
+        .unwrap_or(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_names_are_borrowed() {
+        assert!(matches!(
+            decode_server_name(".DS_Store"),
+            Cow::Borrowed(".DS_Store")
+        ));
+        assert_eq!(decode_server_name("notes.txt"), "notes.txt");
+        assert_eq!(decode_server_name("ratio 1:x"), "ratio 1:x");
+    }
+
+    #[test]
+    fn test_netatalk_hex_escapes() {
+        assert_eq!(decode_server_name(":2eDS_Store"), ".DS_Store");
+        assert_eq!(decode_server_name(":2eAppleDouble"), ".AppleDouble");
+        assert_eq!(decode_server_name("caf:c3:a9.txt"), "café.txt");
+        assert_eq!(decode_server_name("trailing:2"), "trailing:2");
+    }
+
+    #[test]
+    fn test_invalid_utf8_escape_is_left_alone() {
+        assert_eq!(decode_server_name("bad:ff"), "bad:ff");
+    }
+
+    #[test]
+    fn test_samba_private_use_mapping() {
+        assert_eq!(decode_server_name("Icon\u{F00D}"), "Icon\r");
+        assert_eq!(decode_server_name("what\u{F025}.txt"), "what?.txt");
+        assert_eq!(decode_server_name("a\u{F022}b"), "a:b");
+    }
+}
@@ -0,0 +1,129 @@
+/// Magic bytes at the start of a Finder `.DS_Store` file: a big-endian 1 followed by `Bud1`
+const DS_STORE_MAGIC: &[u8] = b"\x00\x00\x00\x01Bud1";
+/// AppleDouble header magic (`0x00051607`)
+const APPLE_DOUBLE_MAGIC: &[u8] = b"\x00\x05\x16\x07";
+/// OLE compound document header used by `Thumbs.db`
+const OLE_MAGIC: &[u8] = b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1";
+/// First line of the `Zone.Identifier` stream Windows attaches to downloads
+const ZONE_TRANSFER_MAGIC: &[u8] = b"[ZoneTransfer]";
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Known file header for a well-known junk artifact.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Signature {
+    DsStore,
+    AppleDouble,
+    OleCompound,
+    /// A `Zone.Identifier` alternate stream spilled into a `name:Zone.Identifier` file
+    ZoneIdentifier,
+}
+
+impl Signature {
+    /// Signature expected for files matched by `pattern`, if it is a well-known artifact
+    pub fn for_pattern(pattern: &str) -> Option<Self> {
+        match pattern {
+            ".DS_Store" => Some(Signature::DsStore),
+            "._*" => Some(Signature::AppleDouble),
+            p if p.eq_ignore_ascii_case("Thumbs.db") => Some(Signature::OleCompound),
+            "*:Zone.Identifier" => Some(Signature::ZoneIdentifier),
+            _ => None,
+        }
+    }
+
+    fn magic(&self) -> &'static [u8] {
+        match self {
+            Signature::DsStore => DS_STORE_MAGIC,
+            Signature::AppleDouble => APPLE_DOUBLE_MAGIC,
+            Signature::OleCompound => OLE_MAGIC,
+            Signature::ZoneIdentifier => ZONE_TRANSFER_MAGIC,
+        }
+    }
+
+    /// Number of leading bytes needed to verify the signature
+    pub fn header_len(&self) -> usize {
+        match self {
+            Signature::ZoneIdentifier => UTF8_BOM.len() + self.magic().len(),
+            _ => self.magic().len(),
+        }
+    }
+
+    pub fn verify(&self, head: &[u8]) -> bool {
+        match self {
+            // Some tools write the stream with a UTF-8 byte order mark
+            Signature::ZoneIdentifier => head
+                .strip_prefix(UTF8_BOM)
+                .unwrap_or(head)
+                .starts_with(self.magic()),
+            _ => head.starts_with(self.magic()),
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Signature::DsStore => "Finder .DS_Store (Bud1) header",
+            Signature::AppleDouble => "AppleDouble header",
+            Signature::OleCompound => "OLE compound file header",
+            Signature::ZoneIdentifier => "Zone.Identifier [ZoneTransfer] header",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_pattern() {
+        assert_eq!(
+            Signature::for_pattern(".DS_Store"),
+            Some(Signature::DsStore)
+        );
+        assert_eq!(Signature::for_pattern("._*"), Some(Signature::AppleDouble));
+        assert_eq!(
+            Signature::for_pattern("Thumbs.db"),
+            Some(Signature::OleCompound)
+        );
+        assert_eq!(
+            Signature::for_pattern("thumbs.DB"),
+            Some(Signature::OleCompound)
+        );
+        assert_eq!(
+            Signature::for_pattern("*:Zone.Identifier"),
+            Some(Signature::ZoneIdentifier)
+        );
+        assert_eq!(Signature::for_pattern("*.bak"), None);
+    }
+
+    #[test]
+    fn test_verify_ds_store() {
+        let mut header = b"\x00\x00\x00\x01Bud1".to_vec();
+        header.extend_from_slice(&[0; 24]);
+        assert!(Signature::DsStore.verify(&header));
+        assert!(!Signature::DsStore.verify(b"my notes"));
+        assert!(!Signature::DsStore.verify(b""));
+    }
+
+    #[test]
+    fn test_verify_apple_double() {
+        assert!(Signature::AppleDouble.verify(b"\x00\x05\x16\x07\x00\x02\x00\x00"));
+        assert!(!Signature::AppleDouble.verify(b"\x00\x05\x16\x00"));
+    }
+
+    #[test]
+    fn test_verify_ole() {
+        assert!(Signature::OleCompound.verify(b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1\x00"));
+        assert!(!Signature::OleCompound.verify(b"PK\x03\x04"));
+    }
+
+    #[test]
+    fn test_verify_zone_identifier() {
+        let stream = b"[ZoneTransfer]\r\nZoneId=3\r\nHostUrl=https://example.com/\r\n";
+        assert!(Signature::ZoneIdentifier.verify(stream));
+        let mut with_bom = UTF8_BOM.to_vec();
+        with_bom.extend_from_slice(stream);
+        assert!(
+            Signature::ZoneIdentifier.verify(&with_bom[..Signature::ZoneIdentifier.header_len()])
+        );
+        assert!(!Signature::ZoneIdentifier.verify(b"meeting notes 10:30"));
+    }
+}
@@ -0,0 +1,260 @@
+//! Minimal binary property list (`bplist00`) decoder.
+//!
+//! Finder stores view settings such as `bwsp`, `lsvp` and `icvp` as binary
+//! plists inside `.DS_Store` blobs. This decodes them to JSON for display.
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::{json, Map, Value};
+use std::cell::Cell;
+
+const MAGIC: &[u8] = b"bplist00";
+const TRAILER_LEN: usize = 32;
+/// Guards against reference cycles in malformed plists
+const MAX_DEPTH: usize = 64;
+/// Guards against shared references fanning out in malformed plists; Finder's
+/// view settings decode to a few dozen objects
+const MAX_OBJECTS: usize = 1 << 16;
+
+pub fn is_bplist(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+pub fn decode(data: &[u8]) -> Result<Value> {
+    if !is_bplist(data) || data.len() < MAGIC.len() + TRAILER_LEN {
+        bail!("Not a binary plist");
+    }
+    let trailer = &data[data.len() - TRAILER_LEN..];
+    let offset_size = trailer[6] as usize;
+    let ref_size = trailer[7] as usize;
+    let num_objects = be_uint(&trailer[8..16]) as usize;
+    let top_object = be_uint(&trailer[16..24]) as usize;
+    let table_offset = be_uint(&trailer[24..32]) as usize;
+
+    let table_len = num_objects
+        .checked_mul(offset_size)
+        .ok_or_else(|| anyhow!("Offset table overflow"))?;
+    let table = slice(data, table_offset, table_len)?;
+    let offsets = table
+        .chunks(offset_size.max(1))
+        .map(|c| be_uint(c) as usize)
+        .collect();
+
+    let plist = Plist {
+        data,
+        offsets,
+        ref_size,
+        decoded: Cell::new(0),
+    };
+    plist.object(top_object, 0)
+}
+
+struct Plist<'a> {
+    data: &'a [u8],
+    offsets: Vec<usize>,
+    ref_size: usize,
+    /// Objects decoded so far, counting shared ones each time
+    decoded: Cell<usize>,
+}
+
+impl Plist<'_> {
+    fn object(&self, index: usize, depth: usize) -> Result<Value> {
+        if depth > MAX_DEPTH {
+            bail!("Plist nested too deeply");
+        }
+        self.decoded.set(self.decoded.get() + 1);
+        if self.decoded.get() > MAX_OBJECTS {
+            bail!("Plist has too many objects");
+        }
+        let offset = *self
+            .offsets
+            .get(index)
+            .ok_or_else(|| anyhow!("Object {} out of range", index))?;
+        let marker = *self
+            .data
+            .get(offset)
+            .ok_or_else(|| anyhow!("Object offset {} out of range", offset))?;
+        let (kind, info) = (marker >> 4, (marker & 0x0f) as usize);
+
+        match kind {
+            0x0 => match info {
+                0x0 => Ok(Value::Null),
+                0x8 => Ok(Value::Bool(false)),
+                0x9 => Ok(Value::Bool(true)),
+                _ => bail!("Unknown plist marker {:#04x}", marker),
+            },
+            0x1 => Ok(json!(be_uint(slice(self.data, offset + 1, 1 << info)?))),
+            0x2 => {
+                let bytes = slice(self.data, offset + 1, 1 << info)?;
+                let value = match bytes.len() {
+                    4 => f32::from_be_bytes(bytes.try_into()?) as f64,
+                    8 => f64::from_be_bytes(bytes.try_into()?),
+                    n => bail!("Unsupported real size {}", n),
+                };
+                Ok(json!(value))
+            }
+            0x3 => {
+                // Seconds since 2001-01-01, reported as-is
+                let bytes = slice(self.data, offset + 1, 8)?;
+                Ok(json!({ "date": f64::from_be_bytes(bytes.try_into()?) }))
+            }
+            0x4 => {
+                let (len, start) = self.length(offset, info)?;
+                Ok(json!({ "data": hex(slice(self.data, start, len)?) }))
+            }
+            0x5 => {
+                let (len, start) = self.length(offset, info)?;
+                Ok(json!(String::from_utf8_lossy(slice(
+                    self.data, start, len
+                )?)))
+            }
+            0x6 => {
+                let (len, start) = self.length(offset, info)?;
+                Ok(json!(utf16_be(slice(self.data, start, len * 2)?)))
+            }
+            0x8 => Ok(json!({ "uid": be_uint(slice(self.data, offset + 1, info + 1)?) })),
+            0xA => {
+                let (len, start) = self.length(offset, info)?;
+                let items = (0..len)
+                    .map(|i| self.object(self.reference(start, i)?, depth + 1))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Value::Array(items))
+            }
+            0xD => {
+                let (len, start) = self.length(offset, info)?;
+                let mut map = Map::new();
+                for i in 0..len {
+                    let key = match self.object(self.reference(start, i)?, depth + 1)? {
+                        Value::String(s) => s,
+                        other => other.to_string(),
+                    };
+                    let value = self.object(self.reference(start, len + i)?, depth + 1)?;
+                    map.insert(key, value);
+                }
+                Ok(Value::Object(map))
+            }
+            _ => bail!("Unsupported plist marker {:#04x}", marker),
+        }
+    }
+
+    /// Decode an object's length, which overflows into a following integer when the nibble is 0xF.
+    /// Returns the length and the offset where the payload starts.
+    fn length(&self, offset: usize, info: usize) -> Result<(usize, usize)> {
+        if info != 0x0f {
+            return Ok((info, offset + 1));
+        }
+        let int_marker = *self
+            .data
+            .get(offset + 1)
+            .ok_or_else(|| anyhow!("Truncated length"))?;
+        if int_marker >> 4 != 0x1 {
+            bail!("Expected integer length, found marker {:#04x}", int_marker);
+        }
+        let size = 1 << (int_marker & 0x0f);
+        let len = be_uint(slice(self.data, offset + 2, size)?) as usize;
+        Ok((len, offset + 2 + size))
+    }
+
+    fn reference(&self, start: usize, index: usize) -> Result<usize> {
+        let bytes = slice(self.data, start + index * self.ref_size, self.ref_size)?;
+        Ok(be_uint(bytes) as usize)
+    }
+}
+
+fn slice(data: &[u8], start: usize, len: usize) -> Result<&[u8]> {
+    start
+        .checked_add(len)
+        .and_then(|end| data.get(start..end))
+        .ok_or_else(|| anyhow!("Read of {} bytes at {} is out of bounds", len, start))
+}
+
+fn be_uint(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |acc, b| (acc << 8) | *b as u64)
+}
+
+fn utf16_be(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|c| u16::from_be_bytes([c[0], c[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+pub(super) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `{"ShowSidebar": true, "SidebarWidth": 180}`
+    fn sample() -> Vec<u8> {
+        let mut data = b"bplist00".to_vec();
+        let mut offsets = Vec::new();
+        // 0: dict with 2 pairs: keys 1, 2; values 3, 4
+        offsets.push(data.len());
+        data.extend_from_slice(&[0xD2, 1, 2, 3, 4]);
+        offsets.push(data.len());
+        data.push(0x5B);
+        data.extend_from_slice(b"ShowSidebar");
+        offsets.push(data.len());
+        data.push(0x5C);
+        data.extend_from_slice(b"SidebarWidth");
+        offsets.push(data.len());
+        data.push(0x09);
+        offsets.push(data.len());
+        data.extend_from_slice(&[0x10, 180]);
+        let table_offset = data.len();
+        data.extend(offsets.iter().map(|o| *o as u8));
+        data.extend_from_slice(&[0; 6]);
+        data.extend_from_slice(&[1, 1]);
+        data.extend_from_slice(&(offsets.len() as u64).to_be_bytes());
+        data.extend_from_slice(&0u64.to_be_bytes());
+        data.extend_from_slice(&(table_offset as u64).to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn test_decode_dict() {
+        let value = decode(&sample()).unwrap();
+        assert_eq!(value, json!({"ShowSidebar": true, "SidebarWidth": 180}));
+    }
+
+    #[test]
+    fn test_rejects_non_plist() {
+        assert!(!is_bplist(b"Bud1"));
+        assert!(decode(b"not a plist at all, really not one").is_err());
+    }
+
+    #[test]
+    fn test_shared_references_are_capped() {
+        // Each array holds the next one twice: 2^40 objects if fully expanded
+        let levels = 40;
+        let mut data = b"bplist00".to_vec();
+        let mut offsets = Vec::new();
+        for i in 0..levels {
+            offsets.push(data.len());
+            data.extend_from_slice(&[0xA2, i + 1, i + 1]);
+        }
+        offsets.push(data.len());
+        data.push(0x00);
+        let table_offset = data.len();
+        data.extend(offsets.iter().map(|o| *o as u8));
+        data.extend_from_slice(&[0; 6]);
+        data.extend_from_slice(&[1, 1]);
+        data.extend_from_slice(&(offsets.len() as u64).to_be_bytes());
+        data.extend_from_slice(&0u64.to_be_bytes());
+        data.extend_from_slice(&(table_offset as u64).to_be_bytes());
+
+        let err = decode(&data).unwrap_err();
+        assert!(err.to_string().contains("too many objects"));
+    }
+
+    #[test]
+    fn test_truncated_plist_is_error() {
+        let data = sample();
+        let mut truncated = data[..12].to_vec();
+        truncated.extend_from_slice(&data[data.len() - TRAILER_LEN..]);
+        assert!(decode(&truncated).is_err());
+    }
+}
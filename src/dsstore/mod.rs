@@ -0,0 +1,569 @@
+//! Parser for the Finder `.DS_Store` format.
+//!
+//! A `.DS_Store` file is a buddy-allocated block store. One block, named
+//! `DSDB` in the allocator's table of contents, holds the header of a B-tree
+//! whose leaves contain records keyed by file name and a four-character code
+//! such as `Iloc` (icon position) or `bwsp` (browser window settings).
+
+mod bplist;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{json, Value};
+
+/// Real `.DS_Store` files are a few KiB; anything far larger isn't worth loading
+pub const MAX_FILE_SIZE: usize = 64 * 1024 * 1024;
+
+const MAGIC: &[u8] = b"\x00\x00\x00\x01Bud1";
+/// Block addresses are relative to this file offset
+const BLOCK_BASE: usize = 4;
+/// The allocator pads its address list to a multiple of this many entries
+const ADDRESS_PAGE: usize = 256;
+const FREE_LIST_COUNT: usize = 32;
+/// Guards against cycles in corrupt trees
+const MAX_TREE_DEPTH: usize = 32;
+/// Size in bytes of one allocator address entry
+const ADDRESS_LEN: usize = 4;
+
+/// Typed payload of a record
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecordValue {
+    Long(u32),
+    Short(u16),
+    Bool(bool),
+    Blob(Vec<u8>),
+    Type(String),
+    Ustr(String),
+    Comp(u64),
+    Dutc(u64),
+}
+
+impl RecordValue {
+    /// Four-character type tag as stored in the file
+    pub fn type_code(&self) -> &'static str {
+        match self {
+            RecordValue::Long(_) => "long",
+            RecordValue::Short(_) => "shor",
+            RecordValue::Bool(_) => "bool",
+            RecordValue::Blob(_) => "blob",
+            RecordValue::Type(_) => "type",
+            RecordValue::Ustr(_) => "ustr",
+            RecordValue::Comp(_) => "comp",
+            RecordValue::Dutc(_) => "dutc",
+        }
+    }
+}
+
+/// One entry in a `.DS_Store` file
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    /// Name of the file in the same folder the record describes (`.` for the folder itself)
+    pub filename: String,
+    /// Record type, e.g. `Iloc`, `bwsp` or `lsvp`
+    pub code: String,
+    pub value: RecordValue,
+}
+
+impl Record {
+    /// JSON form with the value decoded where the record type is understood
+    pub fn to_json(&self) -> Value {
+        json!({
+            "filename": self.filename,
+            "code": self.code,
+            "type": self.value.type_code(),
+            "value": self.decoded_value(),
+        })
+    }
+
+    fn decoded_value(&self) -> Value {
+        match &self.value {
+            RecordValue::Long(v) => json!(v),
+            RecordValue::Short(v) => json!(v),
+            RecordValue::Bool(v) => json!(v),
+            RecordValue::Type(v) | RecordValue::Ustr(v) => json!(v),
+            RecordValue::Comp(v) | RecordValue::Dutc(v) => json!(v),
+            RecordValue::Blob(data) => decode_blob(&self.code, data),
+        }
+    }
+}
+
+fn decode_blob(code: &str, data: &[u8]) -> Value {
+    match code {
+        // Icon location: x, y followed by padding
+        "Iloc" if data.len() >= 8 => json!({
+            "x": be_u32(&data[0..4]),
+            "y": be_u32(&data[4..8]),
+        }),
+        // Window frame (top, left, bottom, right) and view style
+        "fwi0" if data.len() >= 12 => json!({
+            "top": be_u16(&data[0..2]),
+            "left": be_u16(&data[2..4]),
+            "bottom": be_u16(&data[4..6]),
+            "right": be_u16(&data[6..8]),
+            "view": String::from_utf8_lossy(&data[8..12]),
+        }),
+        _ if bplist::is_bplist(data) => {
+            bplist::decode(data).unwrap_or_else(|_| json!({ "hex": bplist::hex(data) }))
+        }
+        _ => json!({ "hex": bplist::hex(data) }),
+    }
+}
+
+/// Parse every record in a `.DS_Store` file, in B-tree order
+pub fn parse(data: &[u8]) -> Result<Vec<Record>> {
+    if !data.starts_with(MAGIC) {
+        bail!("Not a .DS_Store file: missing Bud1 header");
+    }
+    let mut header = Reader::at(data, MAGIC.len())?;
+    let info_offset = header.u32()? as usize;
+    let info_size = header.u32()? as usize;
+    if header.u32()? as usize != info_offset {
+        bail!("Corrupt .DS_Store header: allocator offsets disagree");
+    }
+
+    let store =
+        Store::read_allocator(data, info_offset, info_size).context("Failed to read allocator")?;
+    let dsdb = store
+        .directory
+        .iter()
+        .find(|(name, _)| name == "DSDB")
+        .map(|(_, id)| *id)
+        .ok_or_else(|| anyhow!("No DSDB entry in .DS_Store"))?;
+
+    let mut tree = store.block(dsdb)?;
+    let root = tree.u32()?;
+
+    let mut records = Vec::new();
+    let mut visited = 0;
+    store.walk(root, 0, &mut visited, &mut records)?;
+    Ok(records)
+}
+
+struct Store<'a> {
+    data: &'a [u8],
+    addresses: Vec<u32>,
+    directory: Vec<(String, u32)>,
+}
+
+impl<'a> Store<'a> {
+    fn read_allocator(data: &'a [u8], offset: usize, size: usize) -> Result<Self> {
+        let mut reader = Reader::at(data, BLOCK_BASE + offset)?;
+        reader.limit(size)?;
+
+        let count = reader.u32()? as usize;
+        // Checked before allocating, as the count comes straight from the file
+        if count > size / ADDRESS_LEN {
+            bail!(
+                "Allocator claims {} blocks but holds at most {}",
+                count,
+                size / ADDRESS_LEN
+            );
+        }
+        reader.skip(4)?;
+        let padded = count.div_ceil(ADDRESS_PAGE) * ADDRESS_PAGE;
+        let mut addresses = Vec::with_capacity(count);
+        for i in 0..padded {
+            let address = reader.u32()?;
+            if i < count {
+                addresses.push(address);
+            }
+        }
+
+        let entries = reader.u32()?;
+        let mut directory = Vec::new();
+        for _ in 0..entries {
+            let len = reader.u8()? as usize;
+            let name = String::from_utf8_lossy(reader.bytes(len)?).into_owned();
+            directory.push((name, reader.u32()?));
+        }
+        // Free lists follow; they aren't needed to read records
+        for _ in 0..FREE_LIST_COUNT {
+            let n = reader.u32()? as usize;
+            reader.skip(n * 4)?;
+        }
+
+        Ok(Self {
+            data,
+            addresses,
+            directory,
+        })
+    }
+
+    /// Reader over a block's contents. Addresses pack a 32-byte aligned offset
+    /// with log2 of the block size in the low five bits.
+    fn block(&self, id: u32) -> Result<Reader<'a>> {
+        let address = *self
+            .addresses
+            .get(id as usize)
+            .ok_or_else(|| anyhow!("Block {} not in allocator", id))?;
+        let offset = (address & !0x1f) as usize;
+        let size = 1usize
+            .checked_shl(address & 0x1f)
+            .ok_or_else(|| anyhow!("Block {} has invalid size", id))?;
+        let mut reader = Reader::at(self.data, BLOCK_BASE + offset)?;
+        reader.limit(size)?;
+        Ok(reader)
+    }
+
+    /// Read the records below `node` in order. A sound tree visits each block
+    /// at most once, so corrupt trees sharing children stop at that count.
+    fn walk(
+        &self,
+        node: u32,
+        depth: usize,
+        visited: &mut usize,
+        records: &mut Vec<Record>,
+    ) -> Result<()> {
+        if depth > MAX_TREE_DEPTH {
+            bail!("B-tree nested too deeply");
+        }
+        *visited += 1;
+        if *visited > self.addresses.len() {
+            bail!("B-tree visits more nodes than the file has blocks");
+        }
+        let mut reader = self.block(node)?;
+        let rightmost = reader.u32()?;
+        let count = reader.u32()?;
+        for _ in 0..count {
+            if rightmost != 0 {
+                let child = reader.u32()?;
+                self.walk(child, depth + 1, visited, records)?;
+            }
+            records.push(read_record(&mut reader)?);
+        }
+        if rightmost != 0 {
+            self.walk(rightmost, depth + 1, visited, records)?;
+        }
+        Ok(())
+    }
+}
+
+fn read_record(reader: &mut Reader<'_>) -> Result<Record> {
+    let name_len = reader.u32()? as usize;
+    let filename = reader.utf16(name_len)?;
+    let code = reader.four_cc()?;
+    let value = match reader.four_cc()?.as_str() {
+        "long" => RecordValue::Long(reader.u32()?),
+        "shor" => RecordValue::Short(reader.u32()? as u16),
+        "bool" => RecordValue::Bool(reader.u8()? != 0),
+        "blob" => {
+            let len = reader.u32()? as usize;
+            RecordValue::Blob(reader.bytes(len)?.to_vec())
+        }
+        "type" => RecordValue::Type(reader.four_cc()?),
+        "ustr" => {
+            let len = reader.u32()? as usize;
+            RecordValue::Ustr(reader.utf16(len)?)
+        }
+        "comp" => RecordValue::Comp(reader.u64()?),
+        "dutc" => RecordValue::Dutc(reader.u64()?),
+        other => bail!("Unknown record type '{}' for {}", other, filename),
+    };
+    Ok(Record {
+        filename,
+        code,
+        value,
+    })
+}
+
+/// Bounds-checked big-endian reader
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    end: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn at(data: &'a [u8], pos: usize) -> Result<Self> {
+        if pos > data.len() {
+            bail!("Offset {} is beyond end of file", pos);
+        }
+        Ok(Self {
+            data,
+            pos,
+            end: data.len(),
+        })
+    }
+
+    fn limit(&mut self, len: usize) -> Result<()> {
+        self.end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| anyhow!("Block at {} extends beyond end of file", self.pos))?;
+        Ok(())
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.end)
+            .ok_or_else(|| anyhow!("Truncated data at offset {}", self.pos))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<()> {
+        self.bytes(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(be_u32(self.bytes(4)?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        let bytes = self.bytes(8)?;
+        Ok(u64::from_be_bytes(bytes.try_into()?))
+    }
+
+    fn four_cc(&mut self) -> Result<String> {
+        Ok(String::from_utf8_lossy(self.bytes(4)?).into_owned())
+    }
+
+    fn utf16(&mut self, units: usize) -> Result<String> {
+        let len = units
+            .checked_mul(2)
+            .ok_or_else(|| anyhow!("String length overflow"))?;
+        let units: Vec<u16> = self
+            .bytes(len)?
+            .chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .collect();
+        Ok(String::from_utf16_lossy(&units))
+    }
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn be_u16(bytes: &[u8]) -> u16 {
+    u16::from_be_bytes([bytes[0], bytes[1]])
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Encode a record as it appears in a B-tree node
+    pub(crate) fn encode_record(filename: &str, code: &str, value: &RecordValue) -> Vec<u8> {
+        let mut out = Vec::new();
+        let name: Vec<u16> = filename.encode_utf16().collect();
+        out.extend_from_slice(&(name.len() as u32).to_be_bytes());
+        for unit in name {
+            out.extend_from_slice(&unit.to_be_bytes());
+        }
+        out.extend_from_slice(code.as_bytes());
+        out.extend_from_slice(value.type_code().as_bytes());
+        match value {
+            RecordValue::Long(v) => out.extend_from_slice(&v.to_be_bytes()),
+            RecordValue::Short(v) => out.extend_from_slice(&(*v as u32).to_be_bytes()),
+            RecordValue::Bool(v) => out.push(*v as u8),
+            RecordValue::Blob(data) => {
+                out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+                out.extend_from_slice(data);
+            }
+            RecordValue::Type(v) => out.extend_from_slice(v.as_bytes()),
+            RecordValue::Ustr(v) => {
+                let units: Vec<u16> = v.encode_utf16().collect();
+                out.extend_from_slice(&(units.len() as u32).to_be_bytes());
+                for unit in units {
+                    out.extend_from_slice(&unit.to_be_bytes());
+                }
+            }
+            RecordValue::Comp(v) | RecordValue::Dutc(v) => out.extend_from_slice(&v.to_be_bytes()),
+        }
+        out
+    }
+
+    /// Build a `.DS_Store` whose B-tree is a single leaf holding `records`.
+    /// Blocks: 0 = allocator, 1 = DSDB header, 2 = leaf node.
+    pub(crate) fn build(records: &[(&str, &str, RecordValue)]) -> Vec<u8> {
+        let mut leaf = Vec::new();
+        leaf.extend_from_slice(&0u32.to_be_bytes());
+        leaf.extend_from_slice(&(records.len() as u32).to_be_bytes());
+        for (name, code, value) in records {
+            leaf.extend(encode_record(name, code, value));
+        }
+        build_with_nodes(vec![leaf], 2, records.len())
+    }
+
+    /// Build a `.DS_Store` from raw node blocks. `nodes[i]` becomes block `i + 2`.
+    pub(crate) fn build_with_nodes(nodes: Vec<Vec<u8>>, root: u32, record_count: usize) -> Vec<u8> {
+        const BLOCK_SHIFT: u32 = 12;
+        const BLOCK_SIZE: usize = 1 << BLOCK_SHIFT;
+        let block_count = nodes.len() + 2;
+
+        // Every block gets its own 4 KiB slot after a first slot holding the header
+        let address = |slot: usize| ((slot * BLOCK_SIZE) as u32) | BLOCK_SHIFT;
+
+        let mut allocator = Vec::new();
+        allocator.extend_from_slice(&(block_count as u32).to_be_bytes());
+        allocator.extend_from_slice(&0u32.to_be_bytes());
+        for i in 0..ADDRESS_PAGE {
+            let value = if i < block_count { address(i + 1) } else { 0 };
+            allocator.extend_from_slice(&value.to_be_bytes());
+        }
+        allocator.extend_from_slice(&1u32.to_be_bytes());
+        allocator.push(4);
+        allocator.extend_from_slice(b"DSDB");
+        allocator.extend_from_slice(&1u32.to_be_bytes());
+        for _ in 0..FREE_LIST_COUNT {
+            allocator.extend_from_slice(&0u32.to_be_bytes());
+        }
+
+        let mut dsdb = Vec::new();
+        for value in [root, 0, record_count as u32, nodes.len() as u32, 0x1000] {
+            dsdb.extend_from_slice(&value.to_be_bytes());
+        }
+
+        let mut blocks = vec![allocator, dsdb];
+        blocks.extend(nodes);
+
+        let mut data = vec![0u8; BLOCK_BASE + (block_count + 1) * BLOCK_SIZE];
+        data[..MAGIC.len()].copy_from_slice(MAGIC);
+        let info_offset = (BLOCK_SIZE as u32).to_be_bytes();
+        data[8..12].copy_from_slice(&info_offset);
+        data[12..16].copy_from_slice(&(BLOCK_SIZE as u32).to_be_bytes());
+        data[16..20].copy_from_slice(&info_offset);
+        for (i, block) in blocks.iter().enumerate() {
+            let start = BLOCK_BASE + (i + 1) * BLOCK_SIZE;
+            data[start..start + block.len()].copy_from_slice(block);
+        }
+        data
+    }
+
+    #[test]
+    fn test_parse_leaf_records() {
+        let data = build(&[
+            (
+                "photo.jpg",
+                "Iloc",
+                RecordValue::Blob(vec![
+                    0, 0, 0, 64, 0, 0, 0, 96, 255, 255, 255, 255, 255, 255, 0, 0,
+                ]),
+            ),
+            (".", "vSrn", RecordValue::Long(1)),
+            ("Résumé.pdf", "cmmt", RecordValue::Ustr("draft".to_string())),
+        ]);
+
+        let records = parse(&data).unwrap();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].filename, "photo.jpg");
+        assert_eq!(records[0].code, "Iloc");
+        assert_eq!(records[0].to_json()["value"], json!({"x": 64, "y": 96}));
+        assert_eq!(records[1].value, RecordValue::Long(1));
+        assert_eq!(records[2].filename, "Résumé.pdf");
+        assert_eq!(records[2].to_json()["value"], "draft");
+        assert_eq!(records[2].to_json()["type"], "ustr");
+    }
+
+    #[test]
+    fn test_parse_internal_node() {
+        let leaf = |name: &str| {
+            let mut node = Vec::new();
+            node.extend_from_slice(&0u32.to_be_bytes());
+            node.extend_from_slice(&1u32.to_be_bytes());
+            node.extend(encode_record(name, "vSrn", &RecordValue::Long(1)));
+            node
+        };
+        // Block 2: internal node with child 3, separator "b", rightmost child 4
+        let mut internal = Vec::new();
+        internal.extend_from_slice(&4u32.to_be_bytes());
+        internal.extend_from_slice(&1u32.to_be_bytes());
+        internal.extend_from_slice(&3u32.to_be_bytes());
+        internal.extend(encode_record("b", "vSrn", &RecordValue::Long(1)));
+
+        let data = build_with_nodes(vec![internal, leaf("a"), leaf("c")], 2, 3);
+
+        let names: Vec<String> = parse(&data)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.filename)
+            .collect();
+        assert_eq!(names, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_parse_value_types() {
+        let data = build(&[
+            ("a", "dscl", RecordValue::Bool(true)),
+            ("a", "icvo", RecordValue::Type("icv4".to_string())),
+            ("a", "lg1S", RecordValue::Comp(123456)),
+            ("a", "modD", RecordValue::Dutc(42)),
+            ("a", "fwsw", RecordValue::Short(170)),
+        ]);
+
+        let records = parse(&data).unwrap();
+
+        assert_eq!(records[0].value, RecordValue::Bool(true));
+        assert_eq!(records[1].value, RecordValue::Type("icv4".to_string()));
+        assert_eq!(records[2].value, RecordValue::Comp(123456));
+        assert_eq!(records[3].value, RecordValue::Dutc(42));
+        assert_eq!(records[4].value, RecordValue::Short(170));
+    }
+
+    #[test]
+    fn test_unknown_blob_is_hex() {
+        let data = build(&[("a", "zzzz", RecordValue::Blob(vec![0xde, 0xad]))]);
+
+        let records = parse(&data).unwrap();
+
+        assert_eq!(records[0].to_json()["value"], json!({"hex": "dead"}));
+    }
+
+    #[test]
+    fn test_rejects_non_ds_store() {
+        let err = parse(b"hello world").unwrap_err();
+        assert!(err.to_string().contains("Bud1"));
+    }
+
+    #[test]
+    fn test_oversized_allocator_count_is_error() {
+        let mut data = vec![0u8; 100];
+        data[..MAGIC.len()].copy_from_slice(MAGIC);
+        for (at, value) in [(8, 32u32), (12, 64), (16, 32)] {
+            data[at..at + 4].copy_from_slice(&value.to_be_bytes());
+        }
+        data[BLOCK_BASE + 32..BLOCK_BASE + 36].copy_from_slice(&u32::MAX.to_be_bytes());
+
+        let err = parse(&data).unwrap_err();
+        assert!(format!("{:#}", err).contains("Allocator claims"));
+    }
+
+    #[test]
+    fn test_shared_children_are_error() {
+        // Each node lists the next one both as a child and as its rightmost child,
+        // so following every reference would visit the last leaf 2^30 times
+        let levels = 30;
+        let mut nodes = Vec::new();
+        for i in 0..levels {
+            let next = (i + 3) as u32;
+            let mut node = Vec::new();
+            node.extend_from_slice(&next.to_be_bytes());
+            node.extend_from_slice(&1u32.to_be_bytes());
+            node.extend_from_slice(&next.to_be_bytes());
+            node.extend(encode_record("a", "vSrn", &RecordValue::Long(1)));
+            nodes.push(node);
+        }
+        let mut leaf = Vec::new();
+        leaf.extend_from_slice(&0u32.to_be_bytes());
+        leaf.extend_from_slice(&0u32.to_be_bytes());
+        nodes.push(leaf);
+
+        let err = parse(&build_with_nodes(nodes, 2, levels)).unwrap_err();
+        assert!(err.to_string().contains("more nodes"));
+    }
+
+    #[test]
+    fn test_truncated_file_is_error() {
+        let data = build(&[("a", "vSrn", RecordValue::Long(1))]);
+        assert!(parse(&data[..data.len() / 2]).is_err());
+    }
+}
@@ -1,10 +1,13 @@
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
 use super::FileSystem;
+use crate::models::{EscapePath, FileMetadata, FileOwner};
 
 #[derive(Clone, Default)]
 pub struct MockFileSystem {
@@ -14,6 +17,26 @@ pub struct MockFileSystem {
     deleted: Arc<Mutex<Vec<PathBuf>>>,
     /// Optional path that should trigger a permission error
     fail_on: Arc<Mutex<Option<PathBuf>>>,
+    /// Metadata overrides; files without an entry get defaults
+    metadata: Arc<Mutex<HashMap<PathBuf, FileMetadata>>>,
+    /// File contents; files without an entry are empty
+    contents: Arc<Mutex<HashMap<PathBuf, Vec<u8>>>>,
+    /// Directories that exist even without files in them
+    dirs: Arc<Mutex<Vec<PathBuf>>>,
+    /// Directories that have been "removed" recursively
+    removed_dirs: Arc<Mutex<Vec<PathBuf>>>,
+    /// Extended attribute names per file
+    xattrs: Arc<Mutex<HashMap<PathBuf, Vec<OsString>>>>,
+    /// Files "held open" by some process
+    open_files: Arc<Mutex<Vec<PathBuf>>>,
+    /// Symlink targets; symlinks are also listed in `files` but not walked as files
+    symlinks: Arc<Mutex<HashMap<PathBuf, PathBuf>>>,
+    /// Name reported for this machine
+    hostname: Arc<Mutex<String>>,
+    /// PIDs of "running" processes
+    alive_pids: Arc<Mutex<HashSet<u32>>>,
+    /// File owners; files without an entry belong to root
+    owners: Arc<Mutex<HashMap<PathBuf, FileOwner>>>,
 }
 
 impl MockFileSystem {
@@ -27,6 +50,16 @@ impl MockFileSystem {
             files: Arc::new(Mutex::new(files)),
             deleted: Arc::new(Mutex::new(Vec::new())),
             fail_on: Arc::new(Mutex::new(None)),
+            metadata: Arc::new(Mutex::new(HashMap::new())),
+            contents: Arc::new(Mutex::new(HashMap::new())),
+            dirs: Arc::new(Mutex::new(Vec::new())),
+            removed_dirs: Arc::new(Mutex::new(Vec::new())),
+            xattrs: Arc::new(Mutex::new(HashMap::new())),
+            open_files: Arc::new(Mutex::new(Vec::new())),
+            symlinks: Arc::new(Mutex::new(HashMap::new())),
+            hostname: Arc::new(Mutex::new("mockhost".to_string())),
+            alive_pids: Arc::new(Mutex::new(HashSet::new())),
+            owners: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -61,20 +94,140 @@ impl MockFileSystem {
         self.files.lock().unwrap().push(path);
     }
 
+    /// Override the metadata reported for a file
+    #[allow(dead_code)]
+    pub fn set_metadata(&self, path: PathBuf, metadata: FileMetadata) {
+        self.metadata.lock().unwrap().insert(path, metadata);
+    }
+
+    /// Set the contents of a file
+    #[allow(dead_code)]
+    pub fn set_contents(&self, path: PathBuf, contents: impl Into<Vec<u8>>) {
+        self.contents.lock().unwrap().insert(path, contents.into());
+    }
+
+    /// Set the extended attribute names of a file
+    #[allow(dead_code)]
+    pub fn set_xattrs<N: AsRef<OsStr>>(&self, path: PathBuf, names: &[N]) {
+        let names = names.iter().map(|n| n.as_ref().to_os_string()).collect();
+        self.xattrs.lock().unwrap().insert(path, names);
+    }
+
+    /// Get the extended attribute names a file still carries
+    #[allow(dead_code)]
+    pub fn get_xattrs(&self, path: &Path) -> Vec<OsString> {
+        self.xattrs
+            .lock()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Add a symlink pointing at `target` (which need not exist)
+    #[allow(dead_code)]
+    pub fn add_symlink(&self, path: PathBuf, target: PathBuf) {
+        self.files.lock().unwrap().push(path.clone());
+        self.symlinks.lock().unwrap().insert(path, target);
+    }
+
+    /// Set the name reported for this machine (default `mockhost`)
+    #[allow(dead_code)]
+    pub fn set_hostname(&self, hostname: &str) {
+        *self.hostname.lock().unwrap() = hostname.to_string();
+    }
+
+    /// Mark a PID as belonging to a running process
+    #[allow(dead_code)]
+    pub fn set_alive(&self, pid: u32) {
+        self.alive_pids.lock().unwrap().insert(pid);
+    }
+
+    /// Set the user owning a file
+    #[allow(dead_code)]
+    pub fn set_owner(&self, path: PathBuf, uid: u32, name: &str) {
+        let owner = FileOwner {
+            uid,
+            name: Some(name.to_string()),
+        };
+        self.owners.lock().unwrap().insert(path, owner);
+    }
+
+    fn is_symlink(&self, path: &Path) -> bool {
+        self.symlinks.lock().unwrap().contains_key(path)
+    }
+
+    /// Mark a file as held open by some process
+    #[allow(dead_code)]
+    pub fn set_open(&self, path: PathBuf) {
+        self.open_files.lock().unwrap().push(path);
+    }
+
+    /// Add a directory, which exists even if no file lives in it
+    #[allow(dead_code)]
+    pub fn add_dir(&self, path: PathBuf) {
+        self.dirs.lock().unwrap().push(path);
+    }
+
     /// Check if a specific file was deleted
     pub fn was_deleted(&self, path: &Path) -> bool {
         self.deleted.lock().unwrap().iter().any(|p| p == path)
     }
+
+    /// Check if a specific directory was removed
+    #[allow(dead_code)]
+    pub fn was_dir_removed(&self, path: &Path) -> bool {
+        self.removed_dirs.lock().unwrap().iter().any(|p| p == path)
+    }
+
+    /// Directories that currently exist: explicit ones plus parents of every file
+    /// ever added. Like a real directory, a parent outlives the files deleted from it.
+    fn live_dirs(&self) -> BTreeSet<PathBuf> {
+        let removed = self.removed_dirs.lock().unwrap().clone();
+        let mut dirs: BTreeSet<PathBuf> = self.dirs.lock().unwrap().iter().cloned().collect();
+        for file in self.files.lock().unwrap().iter() {
+            dirs.extend(file.ancestors().skip(1).map(Path::to_path_buf));
+        }
+        dirs.retain(|d| !removed.iter().any(|r| d.starts_with(r)));
+        dirs
+    }
+
+    /// Whether a live file or directory exists directly or deeper below `dir`
+    fn has_entries(&self, dir: &Path) -> bool {
+        let has_file = {
+            let files = self.files.lock().unwrap();
+            let deleted = self.deleted.lock().unwrap();
+            files
+                .iter()
+                .any(|f| f.starts_with(dir) && !deleted.contains(f))
+        };
+        has_file
+            || self
+                .live_dirs()
+                .iter()
+                .any(|d| d.starts_with(dir) && d != dir)
+    }
+
+    fn check_fail_on(&self, path: &Path) -> Result<()> {
+        let fail_on = self.fail_on.lock().unwrap();
+        if let Some(ref fail_path) = *fail_on {
+            if path == fail_path {
+                return Err(anyhow!("Permission denied: {}", path.display()));
+            }
+        }
+        Ok(())
+    }
 }
 
 /// Check if a path contains any directory component that matches an ignore pattern.
 fn path_contains_ignored_dir(path: &Path, ignore_patterns: &[String]) -> bool {
     for component in path.components() {
         if let std::path::Component::Normal(name) = component {
-            if let Some(name_str) = name.to_str() {
-                if ignore_patterns.iter().any(|p| name_str == p) {
-                    return true;
-                }
+            if ignore_patterns
+                .iter()
+                .any(|p| name == std::ffi::OsStr::new(p))
+            {
+                return true;
             }
         }
     }
@@ -83,34 +236,238 @@ fn path_contains_ignored_dir(path: &Path, ignore_patterns: &[String]) -> bool {
 
 #[async_trait]
 impl FileSystem for MockFileSystem {
-    async fn walk_dir(&self, _root: &Path, ignore_patterns: &[String]) -> Result<Vec<PathBuf>> {
-        // Return all files that haven't been deleted and aren't in ignored directories
+    async fn walk_dir(&self, root: &Path, ignore_patterns: &[String]) -> Result<Vec<PathBuf>> {
+        // Return all files under root that haven't been deleted and aren't in ignored directories
         let files = self.files.lock().unwrap();
         let deleted = self.deleted.lock().unwrap();
         let deleted_set: HashSet<_> = deleted.iter().collect();
 
         Ok(files
             .iter()
+            .filter(|f| f.starts_with(root))
             .filter(|f| !deleted_set.contains(f))
+            .filter(|f| !self.is_symlink(f))
             .filter(|f| !path_contains_ignored_dir(f, ignore_patterns))
             .cloned()
             .collect())
     }
 
+    async fn walk_symlinks(&self, root: &Path, ignore_patterns: &[String]) -> Result<Vec<PathBuf>> {
+        let mut links: Vec<PathBuf> = self
+            .symlinks
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|l| l.starts_with(root))
+            .filter(|l| !self.was_deleted(l))
+            .filter(|l| !path_contains_ignored_dir(l, ignore_patterns))
+            .cloned()
+            .collect();
+        links.sort();
+        Ok(links)
+    }
+
+    async fn read_link(&self, path: &Path) -> Result<PathBuf> {
+        if self.was_deleted(path) {
+            return Err(anyhow!("No such file: {}", path.display()));
+        }
+        self.symlinks
+            .lock()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| anyhow!("Not a symlink: {}", path.display()))
+    }
+
+    async fn walk_dirs(&self, root: &Path, ignore_patterns: &[String]) -> Result<Vec<PathBuf>> {
+        Ok(self
+            .live_dirs()
+            .into_iter()
+            .filter(|d| d.starts_with(root) && d != root)
+            .filter(|d| !path_contains_ignored_dir(d, ignore_patterns))
+            .collect())
+    }
+
+    async fn metadata(&self, path: &Path) -> Result<FileMetadata> {
+        if self.was_deleted(path) {
+            return Err(anyhow!("No such file: {}", path.display()));
+        }
+        if let Some(metadata) = self.metadata.lock().unwrap().get(path) {
+            return Ok(metadata.clone());
+        }
+        // Default metadata: size of contents, mtime at the epoch, inode derived from position
+        let files = self.files.lock().unwrap();
+        let index = files
+            .iter()
+            .position(|f| f == path)
+            .ok_or_else(|| anyhow!("No such file: {}", path.display()))?;
+        let size = self
+            .contents
+            .lock()
+            .unwrap()
+            .get(path)
+            .map_or(0, |c| c.len() as u64);
+        Ok(FileMetadata {
+            size,
+            modified: SystemTime::UNIX_EPOCH,
+            inode: index as u64 + 1,
+            dev: 0,
+        })
+    }
+
+    async fn owner(&self, path: &Path) -> Result<FileOwner> {
+        if !self.exists(path).await? {
+            return Err(anyhow!("No such file: {}", path.display()));
+        }
+        Ok(self
+            .owners
+            .lock()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .unwrap_or(FileOwner {
+                uid: 0,
+                name: Some("root".to_string()),
+            }))
+    }
+
+    async fn exists(&self, path: &Path) -> Result<bool> {
+        let is_live_file = {
+            let files = self.files.lock().unwrap();
+            let deleted = self.deleted.lock().unwrap();
+            files.iter().any(|f| f == path && !deleted.contains(f))
+        };
+        Ok(is_live_file || self.live_dirs().contains(path))
+    }
+
+    async fn is_dir(&self, path: &Path) -> Result<bool> {
+        if self.live_dirs().contains(path) {
+            return Ok(true);
+        }
+        if self.exists(path).await? {
+            return Ok(false);
+        }
+        Err(anyhow!("No such file: {}", path.display()))
+    }
+
+    async fn read_head(&self, path: &Path, len: usize) -> Result<Vec<u8>> {
+        if self.was_deleted(path) || !self.files.lock().unwrap().iter().any(|f| f == path) {
+            return Err(anyhow!("No such file: {}", path.display()));
+        }
+        let contents = self.contents.lock().unwrap();
+        let data = contents.get(path).map(Vec::as_slice).unwrap_or_default();
+        Ok(data[..data.len().min(len)].to_vec())
+    }
+
     async fn remove_file(&self, path: &Path) -> Result<()> {
         // Check if this path should fail
-        let fail_on = self.fail_on.lock().unwrap();
-        if let Some(ref fail_path) = *fail_on {
-            if path == fail_path {
-                return Err(anyhow!("Permission denied: {}", path.display()));
-            }
-        }
-        drop(fail_on);
+        self.check_fail_on(path)?;
 
         // "Remove" the file by adding to deleted list
         self.deleted.lock().unwrap().push(path.to_path_buf());
         Ok(())
     }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        self.check_fail_on(from)?;
+        // Keep the file's identity, as a real rename would
+        let metadata = self.metadata(from).await?;
+        if self.exists(to).await? {
+            return Err(anyhow!("Destination exists: {}", to.display()));
+        }
+        {
+            let mut contents = self.contents.lock().unwrap();
+            if let Some(data) = contents.remove(from) {
+                contents.insert(to.to_path_buf(), data);
+            }
+        }
+        self.metadata
+            .lock()
+            .unwrap()
+            .insert(to.to_path_buf(), metadata);
+        self.deleted.lock().unwrap().push(from.to_path_buf());
+        self.files.lock().unwrap().push(to.to_path_buf());
+        Ok(())
+    }
+
+    async fn create_dir_all(&self, path: &Path) -> Result<()> {
+        self.dirs.lock().unwrap().push(path.to_path_buf());
+        Ok(())
+    }
+
+    async fn remove_dir_all(&self, path: &Path) -> Result<()> {
+        self.check_fail_on(path)?;
+        if !self.live_dirs().contains(path) {
+            return Err(anyhow!("No such directory: {}", path.display()));
+        }
+
+        // Every file below the directory goes with it
+        let inside: Vec<PathBuf> = self
+            .files
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|f| f.starts_with(path))
+            .cloned()
+            .collect();
+        self.deleted.lock().unwrap().extend(inside);
+        self.removed_dirs.lock().unwrap().push(path.to_path_buf());
+        Ok(())
+    }
+
+    async fn is_empty_dir(&self, path: &Path) -> Result<bool> {
+        if !self.live_dirs().contains(path) {
+            return Err(anyhow!("No such directory: {}", path.display()));
+        }
+        Ok(!self.has_entries(path))
+    }
+
+    async fn remove_dir(&self, path: &Path) -> Result<()> {
+        self.check_fail_on(path)?;
+        if !self.is_empty_dir(path).await? {
+            return Err(anyhow!("Directory not empty: {}", path.display()));
+        }
+        self.removed_dirs.lock().unwrap().push(path.to_path_buf());
+        Ok(())
+    }
+
+    async fn open_file_ids(&self) -> Result<HashSet<(u64, u64)>> {
+        let open_files = self.open_files.lock().unwrap().clone();
+        let mut ids = HashSet::new();
+        for path in open_files {
+            if let Ok(metadata) = self.metadata(&path).await {
+                ids.insert((metadata.dev, metadata.inode));
+            }
+        }
+        Ok(ids)
+    }
+
+    async fn hostname(&self) -> Result<String> {
+        Ok(self.hostname.lock().unwrap().clone())
+    }
+
+    async fn process_alive(&self, pid: u32) -> bool {
+        self.alive_pids.lock().unwrap().contains(&pid)
+    }
+
+    async fn list_xattrs(&self, path: &Path) -> Result<Vec<OsString>> {
+        if !self.exists(path).await? {
+            return Err(anyhow!("No such file: {}", path.display()));
+        }
+        Ok(self.get_xattrs(path))
+    }
+
+    async fn remove_xattr(&self, path: &Path, name: &OsStr) -> Result<()> {
+        self.check_fail_on(path)?;
+        let mut xattrs = self.xattrs.lock().unwrap();
+        let names = xattrs.entry(path.to_path_buf()).or_default();
+        let before = names.len();
+        names.retain(|n| n != name);
+        if names.len() == before {
+            return Err(anyhow!("No such attribute: {}", name.escaped()));
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -164,6 +521,237 @@ mod tests {
         assert_eq!(files[0], PathBuf::from("/test/file.txt"));
     }
 
+    #[tokio::test]
+    async fn test_mock_metadata_defaults_and_overrides() {
+        let fs = MockFileSystem::with_files(vec![
+            PathBuf::from("/test/.DS_Store"),
+            PathBuf::from("/test/file.txt"),
+        ]);
+
+        let first = fs.metadata(Path::new("/test/.DS_Store")).await.unwrap();
+        let second = fs.metadata(Path::new("/test/file.txt")).await.unwrap();
+        assert_eq!(first.size, 0);
+        assert_ne!(first.inode, second.inode);
+
+        fs.set_metadata(
+            PathBuf::from("/test/.DS_Store"),
+            FileMetadata { size: 42, ..first },
+        );
+        let updated = fs.metadata(Path::new("/test/.DS_Store")).await.unwrap();
+        assert_eq!(updated.size, 42);
+
+        assert!(fs.metadata(Path::new("/test/missing")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_read_head() {
+        let fs = MockFileSystem::with_files(vec![
+            PathBuf::from("/test/.DS_Store"),
+            PathBuf::from("/test/empty"),
+        ]);
+        fs.set_contents(
+            PathBuf::from("/test/.DS_Store"),
+            b"\x00\x00\x00\x01Bud1".to_vec(),
+        );
+
+        let head = fs.read_head(Path::new("/test/.DS_Store"), 4).await.unwrap();
+        assert_eq!(head, b"\x00\x00\x00\x01");
+        let size = fs
+            .metadata(Path::new("/test/.DS_Store"))
+            .await
+            .unwrap()
+            .size;
+        assert_eq!(size, 8);
+        assert!(fs
+            .read_head(Path::new("/test/empty"), 4)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(fs.read_head(Path::new("/test/missing"), 4).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_exists() {
+        let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/photos/a.jpg")]);
+
+        assert!(fs.exists(Path::new("/test/photos/a.jpg")).await.unwrap());
+        assert!(fs.exists(Path::new("/test/photos")).await.unwrap());
+        assert!(!fs.exists(Path::new("/test/photos/b.jpg")).await.unwrap());
+        assert!(!fs.exists(Path::new("/test/phot")).await.unwrap());
+
+        fs.remove_file(Path::new("/test/photos/a.jpg"))
+            .await
+            .unwrap();
+        assert!(!fs.exists(Path::new("/test/photos/a.jpg")).await.unwrap());
+        assert!(fs.exists(Path::new("/test/photos")).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_mock_walk_dirs() {
+        let fs = MockFileSystem::with_files(vec![
+            PathBuf::from("/test/a/b/file.txt"),
+            PathBuf::from("/test/node_modules/x/.DS_Store"),
+        ]);
+        fs.add_dir(PathBuf::from("/test/empty"));
+
+        let dirs = fs
+            .walk_dirs(Path::new("/test"), &["node_modules".to_string()])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            dirs,
+            vec![
+                PathBuf::from("/test/a"),
+                PathBuf::from("/test/a/b"),
+                PathBuf::from("/test/empty"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mock_remove_dir_all() {
+        let fs = MockFileSystem::with_files(vec![
+            PathBuf::from("/test/__MACOSX/._a.jpg"),
+            PathBuf::from("/test/__MACOSX/sub/._b.jpg"),
+            PathBuf::from("/test/keep.txt"),
+        ]);
+
+        fs.remove_dir_all(Path::new("/test/__MACOSX"))
+            .await
+            .unwrap();
+
+        assert!(fs.was_dir_removed(Path::new("/test/__MACOSX")));
+        assert!(fs.was_deleted(Path::new("/test/__MACOSX/sub/._b.jpg")));
+        assert!(!fs.exists(Path::new("/test/__MACOSX/sub")).await.unwrap());
+        let files = fs.walk_dir(Path::new("/test"), &[]).await.unwrap();
+        assert_eq!(files, vec![PathBuf::from("/test/keep.txt")]);
+        assert!(fs.remove_dir_all(Path::new("/test/missing")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_remove_dir_requires_empty() {
+        let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/a/b/.DS_Store")]);
+
+        assert!(!fs.is_empty_dir(Path::new("/test/a/b")).await.unwrap());
+        assert!(fs.remove_dir(Path::new("/test/a/b")).await.is_err());
+
+        fs.remove_file(Path::new("/test/a/b/.DS_Store"))
+            .await
+            .unwrap();
+        // The directory outlives the file deleted from it
+        assert!(fs.exists(Path::new("/test/a/b")).await.unwrap());
+        assert!(fs.is_empty_dir(Path::new("/test/a/b")).await.unwrap());
+        assert!(fs.remove_dir(Path::new("/test/a")).await.is_err());
+
+        fs.remove_dir(Path::new("/test/a/b")).await.unwrap();
+        fs.remove_dir(Path::new("/test/a")).await.unwrap();
+        assert!(fs.was_dir_removed(Path::new("/test/a")));
+        assert!(!fs.exists(Path::new("/test/a")).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_mock_xattrs() {
+        let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/a.jpg")]);
+        fs.set_xattrs(
+            PathBuf::from("/test/a.jpg"),
+            &["user.com.apple.FinderInfo", "user.keep"],
+        );
+
+        fs.remove_xattr(
+            Path::new("/test/a.jpg"),
+            OsStr::new("user.com.apple.FinderInfo"),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            fs.list_xattrs(Path::new("/test/a.jpg")).await.unwrap(),
+            vec![OsString::from("user.keep")]
+        );
+        assert!(fs
+            .remove_xattr(Path::new("/test/a.jpg"), OsStr::new("user.missing"))
+            .await
+            .is_err());
+        assert!(fs.list_xattrs(Path::new("/test/missing")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_symlinks() {
+        let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/notes.txt")]);
+        fs.add_symlink(
+            PathBuf::from("/test/.#notes.txt"),
+            PathBuf::from("alice@mockhost.42:1700000000"),
+        );
+
+        let files = fs.walk_dir(Path::new("/test"), &[]).await.unwrap();
+        let links = fs.walk_symlinks(Path::new("/test"), &[]).await.unwrap();
+
+        assert_eq!(files, vec![PathBuf::from("/test/notes.txt")]);
+        assert_eq!(links, vec![PathBuf::from("/test/.#notes.txt")]);
+        assert_eq!(
+            fs.read_link(Path::new("/test/.#notes.txt")).await.unwrap(),
+            PathBuf::from("alice@mockhost.42:1700000000")
+        );
+        assert!(fs.read_link(Path::new("/test/notes.txt")).await.is_err());
+
+        fs.remove_file(Path::new("/test/.#notes.txt"))
+            .await
+            .unwrap();
+        assert!(fs
+            .walk_symlinks(Path::new("/test"), &[])
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_mock_rename() {
+        let fs = MockFileSystem::with_files(vec![PathBuf::from("/test/a.bak")]);
+        fs.set_contents(PathBuf::from("/test/a.bak"), b"data".to_vec());
+        let before = fs.metadata(Path::new("/test/a.bak")).await.unwrap();
+
+        fs.rename(Path::new("/test/a.bak"), Path::new("/q/a.bak"))
+            .await
+            .unwrap();
+
+        assert!(!fs.exists(Path::new("/test/a.bak")).await.unwrap());
+        assert!(fs.exists(Path::new("/q/a.bak")).await.unwrap());
+        assert_eq!(fs.metadata(Path::new("/q/a.bak")).await.unwrap(), before);
+        assert_eq!(
+            fs.read_head(Path::new("/q/a.bak"), 10).await.unwrap(),
+            b"data"
+        );
+        assert!(fs
+            .rename(Path::new("/test/missing"), Path::new("/q/missing"))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_owner() {
+        let fs =
+            MockFileSystem::with_files(vec![PathBuf::from("/test/a"), PathBuf::from("/test/b")]);
+        fs.set_owner(PathBuf::from("/test/b"), 1000, "alice");
+
+        let a = fs.owner(Path::new("/test/a")).await.unwrap();
+        let b = fs.owner(Path::new("/test/b")).await.unwrap();
+        assert_eq!(a.name.as_deref(), Some("root"));
+        assert_eq!(b.uid, 1000);
+        assert!(fs.owner(Path::new("/test/c")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_processes() {
+        let fs = MockFileSystem::new();
+        fs.set_hostname("build01");
+        fs.set_alive(42);
+
+        assert_eq!(fs.hostname().await.unwrap(), "build01");
+        assert!(fs.process_alive(42).await);
+        assert!(!fs.process_alive(43).await);
+    }
+
     #[tokio::test]
     async fn test_walk_dir_with_empty_ignore_patterns() {
         let fs = MockFileSystem::with_files(vec![
@@ -6,14 +6,75 @@ pub use real::RealFileSystem;
 
 use anyhow::Result;
 use async_trait::async_trait;
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
 use std::path::{Path, PathBuf};
 
+use crate::models::{FileMetadata, FileOwner};
+
 #[async_trait]
 pub trait FileSystem: Send + Sync {
     /// Walk directory recursively, returning all file paths (skips symlinks)
     /// Directories matching any ignore pattern (exact name match) will not be traversed.
     async fn walk_dir(&self, root: &Path, ignore_patterns: &[String]) -> Result<Vec<PathBuf>>;
 
+    /// Walk directory recursively, returning every directory below `root` (skips symlinks).
+    /// Directories matching any ignore pattern are neither returned nor traversed.
+    async fn walk_dirs(&self, root: &Path, ignore_patterns: &[String]) -> Result<Vec<PathBuf>>;
+
+    /// Walk directory recursively, returning every symlink below `root` (never followed)
+    async fn walk_symlinks(&self, root: &Path, ignore_patterns: &[String]) -> Result<Vec<PathBuf>>;
+
+    /// Read the target of a symlink
+    async fn read_link(&self, path: &Path) -> Result<PathBuf>;
+
+    /// Get size, modification time, inode and device of a file (does not follow symlinks)
+    async fn metadata(&self, path: &Path) -> Result<FileMetadata>;
+
+    /// Get the user owning a file (does not follow symlinks)
+    async fn owner(&self, path: &Path) -> Result<FileOwner>;
+
+    /// Check whether a file or directory exists (does not follow symlinks)
+    async fn exists(&self, path: &Path) -> Result<bool>;
+
+    /// Check whether `path` is a directory (does not follow symlinks)
+    async fn is_dir(&self, path: &Path) -> Result<bool>;
+
+    /// Read up to `len` bytes from the start of a file
+    async fn read_head(&self, path: &Path, len: usize) -> Result<Vec<u8>>;
+
     /// Remove a file
     async fn remove_file(&self, path: &Path) -> Result<()>;
+
+    /// Move a file to `to`, which must be on the same filesystem
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()>;
+
+    /// Create a directory and any missing parents
+    async fn create_dir_all(&self, path: &Path) -> Result<()>;
+
+    /// Remove a directory and everything in it (does not follow symlinks)
+    async fn remove_dir_all(&self, path: &Path) -> Result<()>;
+
+    /// Check whether a directory has no entries at all
+    async fn is_empty_dir(&self, path: &Path) -> Result<bool>;
+
+    /// Remove an empty directory; fails if it has any entries
+    async fn remove_dir(&self, path: &Path) -> Result<()>;
+
+    /// List the names of a file's extended attributes (does not follow symlinks)
+    async fn list_xattrs(&self, path: &Path) -> Result<Vec<OsString>>;
+
+    /// Remove one extended attribute from a file (does not follow symlinks)
+    async fn remove_xattr(&self, path: &Path, name: &OsStr) -> Result<()>;
+
+    /// Device and inode of regular files currently held open by any process we
+    /// can inspect
+    async fn open_file_ids(&self) -> Result<HashSet<(u64, u64)>>;
+
+    /// Name of this machine
+    async fn hostname(&self) -> Result<String>;
+
+    /// Whether a process with this PID is running on this machine.
+    /// Answers `true` when it can't tell.
+    async fn process_alive(&self, pid: u32) -> bool;
 }
@@ -1,9 +1,13 @@
 use anyhow::Result;
 use async_trait::async_trait;
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
 use std::path::{Path, PathBuf};
+use tokio::io::AsyncReadExt;
 use walkdir::{DirEntry, WalkDir};
 
 use super::FileSystem;
+use crate::models::{FileMetadata, FileOwner};
 
 pub struct RealFileSystem;
 
@@ -13,11 +17,109 @@ fn is_ignored(entry: &DirEntry, ignore_patterns: &[String]) -> bool {
     if !entry.file_type().is_dir() {
         return false;
     }
-    entry
-        .file_name()
-        .to_str()
-        .map(|name| ignore_patterns.iter().any(|p| name == p))
-        .unwrap_or(false)
+    ignore_patterns
+        .iter()
+        .any(|p| entry.file_name() == std::ffi::OsStr::new(p))
+}
+
+#[cfg(unix)]
+fn inode(metadata: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ino()
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &std::fs::Metadata) -> u64 {
+    0
+}
+
+#[cfg(unix)]
+fn device(metadata: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.dev()
+}
+
+#[cfg(not(unix))]
+fn device(_metadata: &std::fs::Metadata) -> u64 {
+    0
+}
+
+#[cfg(unix)]
+fn owner(path: &Path) -> Result<FileOwner> {
+    use std::os::unix::fs::MetadataExt;
+    let uid = std::fs::symlink_metadata(path)?.uid();
+    Ok(FileOwner {
+        uid,
+        name: user_name(uid),
+    })
+}
+
+#[cfg(not(unix))]
+fn owner(_path: &Path) -> Result<FileOwner> {
+    anyhow::bail!("File owners are not supported on this platform")
+}
+
+#[cfg(unix)]
+fn user_name(uid: u32) -> Option<String> {
+    let mut buf = vec![0u8; 4096];
+    // SAFETY: passwd is plain data that getpwuid_r fills in
+    let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut found = std::ptr::null_mut();
+    // SAFETY: every pointer is valid for the duration of the call and the
+    // buffer length matches its allocation
+    let rc = unsafe {
+        libc::getpwuid_r(
+            uid,
+            &mut pwd,
+            buf.as_mut_ptr().cast(),
+            buf.len(),
+            &mut found,
+        )
+    };
+    if rc != 0 || found.is_null() {
+        return None;
+    }
+    // SAFETY: on success pw_name points at a NUL-terminated string inside `buf`
+    let name = unsafe { std::ffi::CStr::from_ptr(pwd.pw_name) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+#[cfg(unix)]
+fn hostname() -> Result<String> {
+    let mut buf = [0u8; 256];
+    // SAFETY: the buffer is valid for its full length, and gethostname
+    // writes at most that many bytes
+    if unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    let end = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    Ok(String::from_utf8_lossy(&buf[..end]).into_owned())
+}
+
+#[cfg(not(unix))]
+fn hostname() -> Result<String> {
+    Ok(std::env::var("COMPUTERNAME")?)
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return true;
+    };
+    if pid <= 0 {
+        return true;
+    }
+    // SAFETY: signal 0 only checks whether the process exists
+    if unsafe { libc::kill(pid, 0) } == 0 {
+        return true;
+    }
+    // EPERM: the process exists but belongs to someone else
+    std::io::Error::last_os_error().raw_os_error() != Some(libc::ESRCH)
+}
+
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    true
 }
 
 #[async_trait]
@@ -42,8 +144,161 @@ impl FileSystem for RealFileSystem {
         .await?
     }
 
+    async fn walk_dirs(&self, root: &Path, ignore_patterns: &[String]) -> Result<Vec<PathBuf>> {
+        let root = root.to_path_buf();
+        let ignore_patterns = ignore_patterns.to_vec();
+        tokio::task::spawn_blocking(move || {
+            let mut dirs = Vec::new();
+            for entry in WalkDir::new(&root)
+                .min_depth(1)
+                .follow_links(false)
+                .into_iter()
+                .filter_entry(|e| !is_ignored(e, &ignore_patterns))
+                .filter_map(|e| e.ok())
+            {
+                if entry.file_type().is_dir() {
+                    dirs.push(entry.path().to_path_buf());
+                }
+            }
+            Ok(dirs)
+        })
+        .await?
+    }
+
+    async fn walk_symlinks(&self, root: &Path, ignore_patterns: &[String]) -> Result<Vec<PathBuf>> {
+        let root = root.to_path_buf();
+        let ignore_patterns = ignore_patterns.to_vec();
+        tokio::task::spawn_blocking(move || {
+            let mut links = Vec::new();
+            for entry in WalkDir::new(&root)
+                .follow_links(false)
+                .into_iter()
+                .filter_entry(|e| !is_ignored(e, &ignore_patterns))
+                .filter_map(|e| e.ok())
+            {
+                if entry.file_type().is_symlink() {
+                    links.push(entry.path().to_path_buf());
+                }
+            }
+            Ok(links)
+        })
+        .await?
+    }
+
+    async fn read_link(&self, path: &Path) -> Result<PathBuf> {
+        Ok(tokio::fs::read_link(path).await?)
+    }
+
+    async fn metadata(&self, path: &Path) -> Result<FileMetadata> {
+        let metadata = tokio::fs::symlink_metadata(path).await?;
+        Ok(FileMetadata {
+            size: metadata.len(),
+            modified: metadata.modified()?,
+            inode: inode(&metadata),
+            dev: device(&metadata),
+        })
+    }
+
+    async fn owner(&self, path: &Path) -> Result<FileOwner> {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || owner(&path)).await?
+    }
+
+    async fn exists(&self, path: &Path) -> Result<bool> {
+        match tokio::fs::symlink_metadata(path).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn is_dir(&self, path: &Path) -> Result<bool> {
+        Ok(tokio::fs::symlink_metadata(path).await?.is_dir())
+    }
+
+    async fn read_head(&self, path: &Path, len: usize) -> Result<Vec<u8>> {
+        let file = tokio::fs::File::open(path).await?;
+        let mut head = Vec::with_capacity(len.min(64 * 1024));
+        file.take(len as u64).read_to_end(&mut head).await?;
+        Ok(head)
+    }
+
     async fn remove_file(&self, path: &Path) -> Result<()> {
         tokio::fs::remove_file(path).await?;
         Ok(())
     }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        tokio::fs::rename(from, to).await?;
+        Ok(())
+    }
+
+    async fn create_dir_all(&self, path: &Path) -> Result<()> {
+        tokio::fs::create_dir_all(path).await?;
+        Ok(())
+    }
+
+    async fn remove_dir_all(&self, path: &Path) -> Result<()> {
+        tokio::fs::remove_dir_all(path).await?;
+        Ok(())
+    }
+
+    async fn is_empty_dir(&self, path: &Path) -> Result<bool> {
+        let mut entries = tokio::fs::read_dir(path).await?;
+        Ok(entries.next_entry().await?.is_none())
+    }
+
+    async fn remove_dir(&self, path: &Path) -> Result<()> {
+        tokio::fs::remove_dir(path).await?;
+        Ok(())
+    }
+
+    async fn hostname(&self) -> Result<String> {
+        hostname()
+    }
+
+    async fn process_alive(&self, pid: u32) -> bool {
+        process_alive(pid)
+    }
+
+    async fn list_xattrs(&self, path: &Path) -> Result<Vec<OsString>> {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || Ok(xattr::list(&path)?.collect())).await?
+    }
+
+    async fn remove_xattr(&self, path: &Path, name: &OsStr) -> Result<()> {
+        let path = path.to_path_buf();
+        let name = name.to_os_string();
+        tokio::task::spawn_blocking(move || Ok(xattr::remove(&path, &name)?)).await?
+    }
+
+    /// Follows every `/proc/<pid>/fd/<n>` link. Processes of other users are
+    /// only visible when running as root.
+    async fn open_file_ids(&self) -> Result<HashSet<(u64, u64)>> {
+        tokio::task::spawn_blocking(|| {
+            let mut ids = HashSet::new();
+            for entry in std::fs::read_dir("/proc")?.flatten() {
+                let is_pid = entry
+                    .file_name()
+                    .to_str()
+                    .is_some_and(|n| n.bytes().all(|b| b.is_ascii_digit()));
+                if !is_pid {
+                    continue;
+                }
+                // Processes may exit or be off-limits while we look
+                let Ok(fds) = std::fs::read_dir(entry.path().join("fd")) else {
+                    continue;
+                };
+                for fd in fds.flatten() {
+                    if let Ok(metadata) = std::fs::metadata(fd.path()) {
+                        if metadata.is_file() {
+                            ids.insert((device(&metadata), inode(&metadata)));
+                        }
+                    }
+                }
+            }
+            Ok(ids)
+        })
+        .await?
+    }
 }
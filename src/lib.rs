@@ -1,5 +1,6 @@
 pub mod cli;
 pub mod commands;
 pub mod core;
+pub mod dsstore;
 pub mod fs;
 pub mod models;
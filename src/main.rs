@@ -1,76 +1,135 @@
+use std::path::PathBuf;
 use std::time::Duration;
 
 use anyhow::Result;
 use clap::Parser;
-use tracing_subscriber::{fmt, EnvFilter};
+use tracing_subscriber::{fmt, fmt::writer::BoxMakeWriter, EnvFilter};
 
-use ds_store_no_more::cli::{Cli, Commands, LogFormat};
+use ds_store_no_more::cli::{Cli, Commands, LogArgs, LogFormat};
 use ds_store_no_more::commands;
 use ds_store_no_more::fs::RealFileSystem;
-use ds_store_no_more::models::CleanConfig;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    // Extract common options and init logging
-    let (verbose, log_format) = match &cli.command {
-        Commands::Run {
-            verbose,
-            log_format,
-            ..
-        } => (*verbose, log_format.clone()),
-        Commands::Monitor {
-            verbose,
-            log_format,
-            ..
-        } => (*verbose, log_format.clone()),
-    };
-
-    init_logging(verbose, log_format);
+    init_logging(cli.command.logging(), cli.command.prints_results());
 
     let fs = RealFileSystem;
 
     match cli.command {
         Commands::Run {
             root_dir,
-            additional_patterns,
-            ignore_patterns,
+            matching,
             dry_run,
+            audit_leaks,
+            limits,
             ..
         } => {
-            let config = CleanConfig::new(root_dir, additional_patterns, ignore_patterns, dry_run);
+            let config = matching
+                .into_config(root_dir, dry_run)
+                .with_limits(limits.into())
+                .with_audit_report(audit_leaks);
             commands::run::execute(fs, config).await?;
         }
         Commands::Monitor {
             root_dir,
             interval,
             timeout,
-            additional_patterns,
-            ignore_patterns,
+            matching,
             dry_run,
+            limits,
             ..
         } => {
-            let config = CleanConfig::new(root_dir, additional_patterns, ignore_patterns, dry_run);
+            let config = matching
+                .into_config(root_dir, dry_run)
+                .with_limits(limits.into());
             let interval_duration = Duration::from_secs(interval);
             let timeout_duration = timeout.map(Duration::from_secs);
             commands::monitor::execute(fs, config, interval_duration, timeout_duration).await?;
         }
+        Commands::Plan {
+            root_dir,
+            output,
+            matching,
+            ..
+        } => {
+            // Absolute, so `apply` acts on the same files wherever it runs
+            let config = matching.into_config(std::path::absolute(root_dir)?, false);
+            commands::plan::execute(fs, config, &output).await?;
+        }
+        Commands::Apply {
+            plan,
+            dry_run,
+            limits,
+            allow_dangerous,
+            ..
+        } => {
+            commands::apply::execute(fs, &plan, dry_run, limits.into(), allow_dangerous).await?;
+        }
+        Commands::AuditLeaks {
+            root_dir,
+            ignore_patterns,
+            ..
+        } => {
+            commands::audit_leaks::execute(fs, &root_dir, &ignore_patterns).await?;
+        }
+        Commands::List {
+            root_dir,
+            matching,
+            null_separated,
+            relative,
+            ..
+        } => {
+            let config = matching.into_config(root_dir, true);
+            commands::list::execute(fs, config, null_separated, relative).await?;
+        }
+        Commands::Explain {
+            path,
+            root_dir,
+            matching,
+            ..
+        } => {
+            // Both absolute, so a relative path and root line up
+            let config = matching.into_config(std::path::absolute(root_dir)?, true);
+            commands::explain::execute(fs, config, &std::path::absolute(path)?).await?;
+        }
+        Commands::TestPatterns {
+            matching,
+            null_separated,
+            ..
+        } => {
+            let config = matching.into_config(PathBuf::new(), true);
+            commands::test_patterns::execute(fs, config, null_separated).await?;
+        }
+        Commands::Inspect { file, .. } => {
+            commands::inspect::execute(fs, &file).await?;
+        }
     }
 
     Ok(())
 }
 
-fn init_logging(verbose: bool, format: LogFormat) {
-    let filter = if verbose { "debug" } else { "info" };
+/// Log to stdout, or to stderr for commands whose results are on stdout
+fn init_logging(logging: &LogArgs, to_stderr: bool) {
+    let filter = if logging.verbose { "debug" } else { "info" };
     let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(filter));
+    let writer = if to_stderr {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
 
-    match format {
+    match logging.log_format {
         LogFormat::Human => {
-            fmt().with_env_filter(env_filter).init();
+            fmt().with_writer(writer).with_env_filter(env_filter).init();
         }
         LogFormat::Json => {
-            fmt().json().with_env_filter(env_filter).init();
+            fmt()
+                .json()
+                .with_writer(writer)
+                .with_env_filter(env_filter)
+                .init();
         }
     }
 }
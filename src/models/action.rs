@@ -0,0 +1,202 @@
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::str::FromStr;
+
+/// What to do with a matched file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    #[default]
+    Delete,
+    /// Move the file under the quarantine directory, keeping its relative path
+    Quarantine,
+    /// Only log the match; the file stays where it is
+    ReportOnly,
+    /// Give the file a new name in the same directory (see [`RenameTo`])
+    Rename,
+}
+
+impl Action {
+    pub const ALL: &'static [Action] = &[
+        Action::Delete,
+        Action::Quarantine,
+        Action::ReportOnly,
+        Action::Rename,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Delete => "delete",
+            Action::Quarantine => "quarantine",
+            Action::ReportOnly => "report-only",
+            Action::Rename => "rename",
+        }
+    }
+
+    /// Whether the action takes the file away from its current path
+    pub fn removes_file(&self) -> bool {
+        !matches!(self, Action::ReportOnly)
+    }
+
+    /// Check that a new name is given exactly when the action is a rename
+    pub fn check_rename_to(&self, rename_to: Option<&RenameTo>) -> Result<()> {
+        match (self, rename_to) {
+            (Action::Rename, None) => bail!("action 'rename' needs rename_to"),
+            (Action::Rename, Some(_)) | (_, None) => Ok(()),
+            (action, Some(_)) => bail!("rename_to is not used with action '{}'", action),
+        }
+    }
+}
+
+/// The new name of a renamed file, in which `{name}` stands for its current
+/// name: `.{name}.old` renames `Thumbs.db` to `.Thumbs.db.old`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RenameTo(String);
+
+impl RenameTo {
+    const NAME: &'static str = "{name}";
+
+    pub fn new(template: &str) -> Result<Self> {
+        let invalid = |reason: &str| anyhow!("Invalid rename_to '{}': {}", template, reason);
+        if template.is_empty() || template == "." || template == ".." {
+            return Err(invalid("not a file name"));
+        }
+        if template.contains(['/', '\\']) {
+            return Err(invalid("must be a name, not a path"));
+        }
+        if template == Self::NAME {
+            return Err(invalid("would keep the current name"));
+        }
+        Ok(Self(template.to_string()))
+    }
+
+    /// The new name for a file currently called `name`, which needn't be UTF-8
+    pub fn apply(&self, name: &OsStr) -> OsString {
+        let mut renamed = OsString::new();
+        let mut parts = self.0.split(Self::NAME);
+        if let Some(first) = parts.next() {
+            renamed.push(first);
+        }
+        for part in parts {
+            renamed.push(name);
+            renamed.push(part);
+        }
+        renamed
+    }
+}
+
+impl fmt::Display for RenameTo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl TryFrom<String> for RenameTo {
+    type Error = anyhow::Error;
+
+    fn try_from(template: String) -> Result<Self> {
+        Self::new(&template)
+    }
+}
+
+impl From<RenameTo> for String {
+    fn from(rename_to: RenameTo) -> Self {
+        rename_to.0
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match Action::ALL.iter().find(|a| a.name() == value) {
+            Some(action) => Ok(*action),
+            None => {
+                let names: Vec<_> = Action::ALL.iter().map(|a| a.name()).collect();
+                bail!(
+                    "unknown action '{}' (expected one of: {})",
+                    value,
+                    names.join(", ")
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_names_round_trip() {
+        for action in Action::ALL {
+            assert_eq!(action.name().parse::<Action>().unwrap(), *action);
+            let json = serde_json::to_string(action).unwrap();
+            assert_eq!(json, format!("\"{}\"", action.name()));
+        }
+    }
+
+    #[test]
+    fn test_unknown_action() {
+        let err = "shred".parse::<Action>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown action 'shred' (expected one of: delete, quarantine, report-only, rename)"
+        );
+    }
+
+    #[test]
+    fn test_rename_to() {
+        let rename_to = RenameTo::new(".{name}.old").unwrap();
+        assert_eq!(rename_to.apply(OsStr::new("Thumbs.db")), ".Thumbs.db.old");
+        assert_eq!(
+            RenameTo::new("archived").unwrap().apply(OsStr::new("x")),
+            "archived"
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let latin1 = OsStr::from_bytes(b"caf\xe9.db");
+            assert_eq!(
+                rename_to.apply(latin1).as_bytes(),
+                b".caf\xe9.db.old".as_slice()
+            );
+        }
+
+        for bad in ["", "..", "old/{name}", "{name}"] {
+            assert!(RenameTo::new(bad).is_err(), "{:?} should be rejected", bad);
+        }
+        assert!(serde_json::from_str::<RenameTo>(r#""a/b""#).is_err());
+    }
+
+    #[test]
+    fn test_rename_to_only_with_rename() {
+        let rename_to = RenameTo::new("{name}.old").unwrap();
+        assert!(Action::Rename.check_rename_to(Some(&rename_to)).is_ok());
+        assert!(Action::Delete.check_rename_to(None).is_ok());
+        assert_eq!(
+            Action::Rename
+                .check_rename_to(None)
+                .unwrap_err()
+                .to_string(),
+            "action 'rename' needs rename_to"
+        );
+        assert_eq!(
+            Action::Delete
+                .check_rename_to(Some(&rename_to))
+                .unwrap_err()
+                .to_string(),
+            "rename_to is not used with action 'delete'"
+        );
+    }
+}
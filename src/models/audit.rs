@@ -0,0 +1,81 @@
+use serde::Serialize;
+use std::path::PathBuf;
+
+use super::escaped_path;
+
+/// Why a name recorded in a `.DS_Store` is worth reporting
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeakReason {
+    /// The named file no longer exists beside the `.DS_Store`
+    Absent,
+    /// The name, or a directory above it, matches an ignore pattern
+    Ignored,
+    /// The name, or a directory above it, looks like credentials or private data
+    Sensitive,
+}
+
+/// A recorded file name that exposes something
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Leak {
+    pub name: String,
+    pub reasons: Vec<LeakReason>,
+}
+
+/// Audit of a single `.DS_Store` file
+#[derive(Clone, Debug, Serialize)]
+pub struct DsStoreAudit {
+    #[serde(serialize_with = "escaped_path::serialize")]
+    pub path: PathBuf,
+    /// Number of distinct file names recorded (excluding the folder itself)
+    pub recorded_names: usize,
+    pub leaks: Vec<Leak>,
+    /// Set when the file could not be read or parsed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of `audit-leaks` over a tree
+#[derive(Clone, Debug, Serialize)]
+pub struct LeakReport {
+    #[serde(serialize_with = "escaped_path::serialize")]
+    pub root: PathBuf,
+    pub files: Vec<DsStoreAudit>,
+}
+
+impl LeakReport {
+    pub fn total_leaks(&self) -> usize {
+        self.files.iter().map(|f| f.leaks.len()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_serializes_reasons_in_snake_case() {
+        let report = LeakReport {
+            root: PathBuf::from("/site"),
+            files: vec![DsStoreAudit {
+                path: PathBuf::from("/site/.DS_Store"),
+                recorded_names: 2,
+                leaks: vec![Leak {
+                    name: "backup.key".to_string(),
+                    reasons: vec![LeakReason::Absent, LeakReason::Sensitive],
+                }],
+                error: None,
+            }],
+        };
+
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(report.total_leaks(), 1);
+        assert_eq!(json["files"][0]["leaks"][0]["name"], "backup.key");
+        assert_eq!(
+            json["files"][0]["leaks"][0]["reasons"],
+            serde_json::json!(["absent", "sensitive"])
+        );
+        assert!(json["files"][0].get("error").is_none());
+    }
+}
@@ -0,0 +1,381 @@
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+use super::units::{format_duration, format_size, parse_duration, parse_size};
+use super::{Action, FileMetadata, RenameTo};
+
+/// Age and size limits a matched file must meet before it is deleted
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Conditions {
+    /// Only files last modified longer ago than this
+    pub older_than: Option<Duration>,
+    /// Only files last modified more recently than this
+    pub newer_than: Option<Duration>,
+    /// Only files at least this many bytes
+    pub min_size: Option<u64>,
+    /// Only files at most this many bytes
+    pub max_size: Option<u64>,
+}
+
+impl Conditions {
+    pub fn is_empty(&self) -> bool {
+        self == &Conditions::default()
+    }
+
+    /// Whether a file with `metadata` meets every condition at time `now`.
+    /// Files modified in the future count as brand new.
+    pub fn is_met(&self, metadata: &FileMetadata, now: SystemTime) -> bool {
+        let age = now.duration_since(metadata.modified).unwrap_or_default();
+        self.older_than.is_none_or(|limit| age > limit)
+            && self.newer_than.is_none_or(|limit| age < limit)
+            && self.min_size.is_none_or(|limit| metadata.size >= limit)
+            && self.max_size.is_none_or(|limit| metadata.size <= limit)
+    }
+
+    /// Each condition with whether a file with `metadata` meets it at time `now`
+    pub fn checks(&self, metadata: &FileMetadata, now: SystemTime) -> Vec<(String, bool)> {
+        let age = now.duration_since(metadata.modified).unwrap_or_default();
+        let mut checks = Vec::new();
+        if let Some(limit) = self.older_than {
+            checks.push((
+                format!("older than {}", format_duration(limit)),
+                age > limit,
+            ));
+        }
+        if let Some(limit) = self.newer_than {
+            checks.push((
+                format!("newer than {}", format_duration(limit)),
+                age < limit,
+            ));
+        }
+        if let Some(limit) = self.min_size {
+            checks.push((
+                format!("at least {}", format_size(limit)),
+                metadata.size >= limit,
+            ));
+        }
+        if let Some(limit) = self.max_size {
+            checks.push((
+                format!("at most {}", format_size(limit)),
+                metadata.size <= limit,
+            ));
+        }
+        checks
+    }
+
+    /// Reject combinations no file can meet
+    fn validate(&self) -> Result<()> {
+        if let (Some(older), Some(newer)) = (self.older_than, self.newer_than) {
+            if older >= newer {
+                bail!(
+                    "older_than ({}) must be less than newer_than ({})",
+                    format_duration(older),
+                    format_duration(newer)
+                );
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_size, self.max_size) {
+            if min > max {
+                bail!(
+                    "min_size ({}) must not exceed max_size ({})",
+                    format_size(min),
+                    format_size(max)
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Conditions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(limit) = self.older_than {
+            parts.push(format!("older than {}", format_duration(limit)));
+        }
+        if let Some(limit) = self.newer_than {
+            parts.push(format!("newer than {}", format_duration(limit)));
+        }
+        if let Some(limit) = self.min_size {
+            parts.push(format!("at least {}", format_size(limit)));
+        }
+        if let Some(limit) = self.max_size {
+            parts.push(format!("at most {}", format_size(limit)));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// A file pattern whose matches are only acted on when they meet `conditions`,
+/// e.g. `*.tmp` files older than a day
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawConditionalPattern", into = "RawConditionalPattern")]
+pub struct ConditionalPattern {
+    pub pattern: String,
+    /// Match names regardless of case, e.g. `thumbs.db` also matches `THUMBS.DB`
+    pub ignore_case: bool,
+    pub conditions: Conditions,
+    pub action: Action,
+    /// New name of matching files, when `action` is a rename
+    pub rename_to: Option<RenameTo>,
+}
+
+/// How a conditional pattern is written in config and plan files:
+/// durations such as `"7d"`, sizes as byte counts or strings such as `"10M"`
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawConditionalPattern {
+    pattern: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    ignore_case: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    older_than: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    newer_than: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min_size: Option<Size>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_size: Option<Size>,
+    #[serde(default, skip_serializing_if = "is_delete")]
+    action: Action,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rename_to: Option<RenameTo>,
+}
+
+fn is_delete(action: &Action) -> bool {
+    *action == Action::Delete
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Size {
+    Bytes(u64),
+    Text(String),
+}
+
+impl Size {
+    fn bytes(&self) -> Result<u64> {
+        match self {
+            Size::Bytes(bytes) => Ok(*bytes),
+            Size::Text(text) => parse_size(text),
+        }
+    }
+}
+
+impl TryFrom<RawConditionalPattern> for ConditionalPattern {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawConditionalPattern) -> Result<Self> {
+        let conditions = Conditions {
+            older_than: raw.older_than.as_deref().map(parse_duration).transpose()?,
+            newer_than: raw.newer_than.as_deref().map(parse_duration).transpose()?,
+            min_size: raw.min_size.as_ref().map(Size::bytes).transpose()?,
+            max_size: raw.max_size.as_ref().map(Size::bytes).transpose()?,
+        };
+        conditions
+            .validate()
+            .map_err(|e| anyhow!("Pattern '{}' can never match: {}", raw.pattern, e))?;
+        raw.action
+            .check_rename_to(raw.rename_to.as_ref())
+            .map_err(|e| anyhow!("Pattern '{}': {}", raw.pattern, e))?;
+        Ok(Self {
+            pattern: raw.pattern,
+            ignore_case: raw.ignore_case,
+            conditions,
+            action: raw.action,
+            rename_to: raw.rename_to,
+        })
+    }
+}
+
+impl From<ConditionalPattern> for RawConditionalPattern {
+    fn from(pattern: ConditionalPattern) -> Self {
+        let conditions = pattern.conditions;
+        Self {
+            pattern: pattern.pattern,
+            ignore_case: pattern.ignore_case,
+            older_than: conditions.older_than.map(format_duration),
+            newer_than: conditions.newer_than.map(format_duration),
+            min_size: conditions.min_size.map(Size::Bytes),
+            max_size: conditions.max_size.map(Size::Bytes),
+            action: pattern.action,
+            rename_to: pattern.rename_to,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(86400);
+
+    fn file(size: u64, age: Duration, now: SystemTime) -> FileMetadata {
+        FileMetadata {
+            size,
+            modified: now - age,
+            inode: 1,
+            dev: 0,
+        }
+    }
+
+    #[test]
+    fn test_empty_conditions_always_met() {
+        let now = SystemTime::now();
+        let conditions = Conditions::default();
+        assert!(conditions.is_empty());
+        assert!(conditions.is_met(&file(0, Duration::ZERO, now), now));
+    }
+
+    #[test]
+    fn test_age_conditions() {
+        let now = SystemTime::now();
+        let conditions = Conditions {
+            older_than: Some(DAY),
+            newer_than: Some(DAY * 7),
+            ..Default::default()
+        };
+        assert!(!conditions.is_met(&file(0, DAY / 2, now), now));
+        assert!(conditions.is_met(&file(0, DAY * 2, now), now));
+        assert!(!conditions.is_met(&file(0, DAY * 8, now), now));
+    }
+
+    #[test]
+    fn test_future_mtime_counts_as_new() {
+        let now = SystemTime::now();
+        let conditions = Conditions {
+            older_than: Some(DAY),
+            ..Default::default()
+        };
+        let metadata = FileMetadata {
+            size: 0,
+            modified: now + DAY,
+            inode: 1,
+            dev: 0,
+        };
+        assert!(!conditions.is_met(&metadata, now));
+    }
+
+    #[test]
+    fn test_size_conditions() {
+        let now = SystemTime::now();
+        let conditions = Conditions {
+            min_size: Some(10),
+            max_size: Some(100),
+            ..Default::default()
+        };
+        assert!(!conditions.is_met(&file(9, DAY, now), now));
+        assert!(conditions.is_met(&file(10, DAY, now), now));
+        assert!(conditions.is_met(&file(100, DAY, now), now));
+        assert!(!conditions.is_met(&file(101, DAY, now), now));
+    }
+
+    #[test]
+    fn test_checks_name_each_condition() {
+        let now = SystemTime::now();
+        let conditions = Conditions {
+            older_than: Some(DAY),
+            max_size: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(
+            conditions.checks(&file(200, DAY * 2, now), now),
+            vec![
+                ("older than 1d".to_string(), true),
+                ("at most 100 B".to_string(), false),
+            ]
+        );
+        assert!(Conditions::default()
+            .checks(&file(0, DAY, now), now)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_display() {
+        let conditions = Conditions {
+            older_than: Some(DAY),
+            max_size: Some(10 * 1024 * 1024),
+            ..Default::default()
+        };
+        assert_eq!(conditions.to_string(), "older than 1d, at most 10.0 MiB");
+    }
+
+    #[test]
+    fn test_conditional_pattern_round_trip() {
+        let json = r#"{"pattern":"*.tmp","older_than":"1d","max_size":"10M"}"#;
+        let pattern: ConditionalPattern = serde_json::from_str(json).unwrap();
+        assert_eq!(pattern.pattern, "*.tmp");
+        assert_eq!(pattern.conditions.older_than, Some(DAY));
+        assert_eq!(pattern.conditions.max_size, Some(10 * 1024 * 1024));
+
+        let written = serde_json::to_string(&pattern).unwrap();
+        assert_eq!(
+            written,
+            r#"{"pattern":"*.tmp","older_than":"1d","max_size":10485760}"#
+        );
+        let reread: ConditionalPattern = serde_json::from_str(&written).unwrap();
+        assert_eq!(reread, pattern);
+    }
+
+    #[test]
+    fn test_conditional_pattern_action() {
+        let json = r#"{"pattern":"Thumbs.db","action":"rename","rename_to":".{name}.old"}"#;
+        let pattern: ConditionalPattern = serde_json::from_str(json).unwrap();
+        assert!(pattern.conditions.is_empty());
+        assert_eq!(pattern.action, Action::Rename);
+        assert_eq!(
+            pattern
+                .rename_to
+                .as_ref()
+                .unwrap()
+                .apply(std::ffi::OsStr::new("Thumbs.db")),
+            ".Thumbs.db.old"
+        );
+        assert_eq!(serde_json::to_string(&pattern).unwrap(), json);
+
+        let json = r#"{"pattern":"Thumbs.db","action":"rename"}"#;
+        let err = serde_json::from_str::<ConditionalPattern>(json)
+            .err()
+            .unwrap();
+        assert!(err
+            .to_string()
+            .contains("Pattern 'Thumbs.db': action 'rename' needs rename_to"));
+    }
+
+    #[test]
+    fn test_conditional_pattern_ignore_case() {
+        let json = r#"{"pattern":"thumbs.db","ignore_case":true}"#;
+        let pattern: ConditionalPattern = serde_json::from_str(json).unwrap();
+        assert!(pattern.ignore_case);
+        assert_eq!(serde_json::to_string(&pattern).unwrap(), json);
+
+        let pattern: ConditionalPattern = serde_json::from_str(r#"{"pattern":"a"}"#).unwrap();
+        assert!(!pattern.ignore_case);
+    }
+
+    #[test]
+    fn test_conditional_pattern_rejects_bad_values() {
+        let bad = [
+            r#"{"pattern":"*.tmp","older_than":"soon"}"#,
+            r#"{"pattern":"*.tmp","min_size":"lots"}"#,
+            r#"{"pattern":"*.tmp","older-than":"1d"}"#,
+        ];
+        for json in bad {
+            assert!(serde_json::from_str::<ConditionalPattern>(json).is_err());
+        }
+    }
+
+    #[test]
+    fn test_conditional_pattern_rejects_impossible_ranges() {
+        let json = r#"{"pattern":"*.tmp","older_than":"7d","newer_than":"1d"}"#;
+        let err = serde_json::from_str::<ConditionalPattern>(json)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("can never match"));
+
+        let json = r#"{"pattern":"*.log","min_size":"2M","max_size":"1M"}"#;
+        assert!(serde_json::from_str::<ConditionalPattern>(json).is_err());
+    }
+}
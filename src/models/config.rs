@@ -1,10 +1,53 @@
-use std::path::PathBuf;
+use anyhow::{bail, Result};
+use std::path::{Path, PathBuf};
+
+use crate::core::CompiledRule;
+
+use super::{
+    dangerous_pattern_reason, dangerous_root_reason, Action, ConditionalPattern, DeletionLimits,
+    Preset, RuleConfig,
+};
+
+/// Extended attributes that Macs leave on files copied via netatalk, Samba
+/// vfs_fruit or rsync: the xattr equivalent of `.DS_Store`
+pub const DEFAULT_XATTR_PATTERNS: &[&str] = &["user.com.apple.*", "user.DosStream.*AFP_*"];
 
 pub struct CleanConfig {
     pub root_dir: PathBuf,
     pub patterns: Vec<String>,
     pub ignore_patterns: Vec<String>,
     pub dry_run: bool,
+    pub limits: DeletionLimits,
+    /// Skip the dangerous root and pattern guard rails
+    pub allow_dangerous: bool,
+    /// Check headers of well-known artifacts before deleting them
+    pub verify_signatures: bool,
+    /// Write a leak audit of .DS_Store files here before cleaning
+    pub audit_report: Option<PathBuf>,
+    /// Handle `._*` files by AppleDouble content and sibling presence
+    pub apple_double: bool,
+    /// Directory names removed recursively (e.g. `__MACOSX`)
+    pub dir_patterns: Vec<String>,
+    /// Remove directories left empty by the cleanup
+    pub prune_empty_dirs: bool,
+    /// Extended attribute names stripped from kept files
+    pub xattr_patterns: Vec<String>,
+    /// Also match names after decoding Samba/netatalk encodings
+    pub decode_names: bool,
+    /// Match file and directory patterns regardless of case
+    pub ignore_case: bool,
+    /// Patterns that only match files whose header carries the expected signature
+    pub validated_patterns: Vec<String>,
+    /// Skip files held open by any process
+    pub skip_in_use: bool,
+    /// Delete editor swap and lock files whose owning process has exited
+    pub editor_artifacts: bool,
+    /// Patterns that only match files meeting age and size conditions
+    pub conditional_patterns: Vec<ConditionalPattern>,
+    /// Rules in the rule language, each with its own action
+    pub rules: Vec<RuleConfig>,
+    /// Where quarantined files are moved
+    pub quarantine_dir: Option<PathBuf>,
 }
 
 impl CleanConfig {
@@ -21,8 +64,213 @@ impl CleanConfig {
             patterns: all_patterns,
             ignore_patterns,
             dry_run,
+            limits: DeletionLimits::default(),
+            allow_dangerous: false,
+            verify_signatures: false,
+            audit_report: None,
+            apple_double: false,
+            dir_patterns: Vec::new(),
+            prune_empty_dirs: false,
+            xattr_patterns: Vec::new(),
+            decode_names: false,
+            ignore_case: false,
+            validated_patterns: Vec::new(),
+            skip_in_use: false,
+            editor_artifacts: false,
+            conditional_patterns: Vec::new(),
+            rules: Vec::new(),
+            quarantine_dir: None,
         }
     }
+
+    pub fn with_limits(mut self, limits: DeletionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn with_allow_dangerous(mut self, allow_dangerous: bool) -> Self {
+        self.allow_dangerous = allow_dangerous;
+        self
+    }
+
+    pub fn with_signature_checks(mut self, verify_signatures: bool) -> Self {
+        self.verify_signatures = verify_signatures;
+        self
+    }
+
+    pub fn with_audit_report(mut self, audit_report: Option<PathBuf>) -> Self {
+        self.audit_report = audit_report;
+        self
+    }
+
+    pub fn with_apple_double(mut self, apple_double: bool) -> Self {
+        self.apple_double = apple_double;
+        self
+    }
+
+    pub fn with_dir_patterns(mut self, dir_patterns: Vec<String>) -> Self {
+        self.dir_patterns = dir_patterns;
+        self
+    }
+
+    pub fn with_prune_empty_dirs(mut self, prune_empty_dirs: bool) -> Self {
+        self.prune_empty_dirs = prune_empty_dirs;
+        self
+    }
+
+    pub fn with_xattr_patterns(mut self, xattr_patterns: Vec<String>) -> Self {
+        self.xattr_patterns = xattr_patterns;
+        self
+    }
+
+    pub fn with_ignore_case(mut self, ignore_case: bool) -> Self {
+        self.ignore_case = ignore_case;
+        self
+    }
+
+    pub fn with_in_use_check(mut self, skip_in_use: bool) -> Self {
+        self.skip_in_use = skip_in_use;
+        self
+    }
+
+    pub fn with_editor_artifacts(mut self, editor_artifacts: bool) -> Self {
+        self.editor_artifacts = editor_artifacts;
+        self
+    }
+
+    pub fn with_conditional_patterns(mut self, patterns: Vec<ConditionalPattern>) -> Self {
+        self.conditional_patterns = patterns;
+        self
+    }
+
+    pub fn with_rules(mut self, rules: Vec<RuleConfig>) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    pub fn with_quarantine_dir(mut self, quarantine_dir: Option<PathBuf>) -> Self {
+        self.quarantine_dir = quarantine_dir;
+        self
+    }
+
+    /// Add the patterns and settings of each preset
+    pub fn with_presets(mut self, presets: &[Preset]) -> Self {
+        for preset in presets {
+            extend_unique(&mut self.patterns, preset.patterns());
+            extend_unique(&mut self.dir_patterns, preset.dir_patterns());
+            extend_unique(&mut self.validated_patterns, preset.validated_patterns());
+            self.decode_names |= preset.decodes_names();
+        }
+        self
+    }
+
+    /// Refuse system roots, the home directory and overly broad patterns
+    /// unless `allow_dangerous` is set.
+    pub fn validate(&self) -> Result<()> {
+        self.validate_quarantine()?;
+        if self.allow_dangerous {
+            return Ok(());
+        }
+
+        let root = canonical(&self.root_dir);
+        let home = std::env::var_os("HOME").map(|h| canonical(Path::new(&h)));
+        if let Some(reason) = dangerous_root_reason(&root, home.as_deref()) {
+            bail!(
+                "Refusing to clean: {}. Pass --allow-dangerous to proceed anyway",
+                reason
+            );
+        }
+
+        let all_patterns = self
+            .patterns
+            .iter()
+            .chain(&self.dir_patterns)
+            .chain(&self.validated_patterns)
+            .chain(self.conditional_patterns.iter().map(|p| &p.pattern))
+            .chain(&self.xattr_patterns);
+        for pattern in all_patterns {
+            if let Some(reason) = dangerous_pattern_reason(pattern) {
+                bail!(
+                    "Refusing to clean: {}. Use a more specific pattern or pass \
+                     --allow-dangerous to proceed anyway",
+                    reason
+                );
+            }
+        }
+
+        for rule in self.rules.iter().filter(|r| r.action.removes_file()) {
+            let compiled = CompiledRule::compile(rule)?;
+            if !compiled.requires_name() {
+                bail!(
+                    "Refusing to clean: rule '{}' can {} files whatever their name. Add a \
+                     name(...) condition or pass --allow-dangerous to proceed anyway",
+                    rule.when,
+                    rule.action
+                );
+            }
+            for pattern in compiled.name_patterns() {
+                if let Some(reason) = dangerous_pattern_reason(pattern) {
+                    bail!(
+                        "Refusing to clean: {} in rule '{}'. Use a more specific pattern or \
+                         pass --allow-dangerous to proceed anyway",
+                        reason,
+                        rule.when
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl CleanConfig {
+    /// Quarantining needs a directory, and one inside the root would be cleaned again
+    fn validate_quarantine(&self) -> Result<()> {
+        match &self.quarantine_dir {
+            None => {
+                if let Some(rule) = self.rules.iter().find(|r| r.action == Action::Quarantine) {
+                    bail!(
+                        "Rule '{}' quarantines files but no quarantine_dir is configured",
+                        rule.when
+                    );
+                }
+                if let Some(pattern) = self
+                    .conditional_patterns
+                    .iter()
+                    .find(|p| p.action == Action::Quarantine)
+                {
+                    bail!(
+                        "Pattern '{}' quarantines files but no quarantine_dir is configured",
+                        pattern.pattern
+                    );
+                }
+            }
+            Some(dir) => {
+                if canonical(dir).starts_with(canonical(&self.root_dir)) {
+                    bail!(
+                        "Quarantine directory {} must be outside the cleaned root {}",
+                        dir.display(),
+                        self.root_dir.display()
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn extend_unique(patterns: &mut Vec<String>, extra: &[&str]) {
+    for pattern in extra {
+        if !patterns.iter().any(|p| p == pattern) {
+            patterns.push(pattern.to_string());
+        }
+    }
+}
+
+/// Resolve symlinks and `..` so `/tmp/../etc` is recognised as `/etc`.
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
 }
 
 #[cfg(test)]
@@ -67,4 +315,161 @@ mod tests {
         assert!(config.ignore_patterns.contains(&"node_modules".to_string()));
         assert!(config.ignore_patterns.contains(&".git".to_string()));
     }
+
+    #[test]
+    fn test_config_has_no_limits_by_default() {
+        let config = CleanConfig::new(PathBuf::from("/test"), vec![], vec![], false);
+        assert!(config.limits.max_deletions.is_none());
+        assert!(config.limits.max_bytes.is_none());
+        assert!(!config.limits.force);
+    }
+
+    #[test]
+    fn test_validate_accepts_ordinary_config() {
+        let config = CleanConfig::new(
+            PathBuf::from("/srv/share/photos"),
+            vec!["Thumbs.db".to_string(), "*.bak".to_string()],
+            vec![],
+            false,
+        );
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_system_root() {
+        let config = CleanConfig::new(PathBuf::from("/etc"), vec![], vec![], false);
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("system directory"));
+        assert!(err.contains("--allow-dangerous"));
+    }
+
+    #[test]
+    fn test_validate_rejects_broad_pattern() {
+        let config = CleanConfig::new(
+            PathBuf::from("/srv/share"),
+            vec!["*.txt".to_string()],
+            vec![],
+            false,
+        );
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("'*.txt'"));
+    }
+
+    #[test]
+    fn test_validate_rejects_broad_dir_pattern() {
+        let config = CleanConfig::new(PathBuf::from("/srv/share"), vec![], vec![], false)
+            .with_dir_patterns(vec!["__MACOSX".to_string(), "*".to_string()]);
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("'*'"));
+    }
+
+    #[test]
+    fn test_validate_rejects_broad_xattr_pattern() {
+        let config = CleanConfig::new(PathBuf::from("/srv/share"), vec![], vec![], false)
+            .with_xattr_patterns(vec!["*".to_string()]);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_broad_conditional_pattern() {
+        let config = CleanConfig::new(PathBuf::from("/srv/share"), vec![], vec![], false)
+            .with_conditional_patterns(vec![ConditionalPattern {
+                pattern: "*.txt".to_string(),
+                ..Default::default()
+            }]);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_broad_rule() {
+        let rule = |when: &str, action| RuleConfig {
+            when: when.to_string(),
+            action,
+            rename_to: None,
+        };
+        let config = CleanConfig::new(PathBuf::from("/srv/share"), vec![], vec![], false)
+            .with_rules(vec![rule(r#"name("*")"#, Action::Delete)]);
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains(r#"in rule 'name("*")'"#));
+
+        // Excluded by `not`, or only reported
+        let config = config.with_rules(vec![
+            rule(r#"iname("*.bak") and not name("*")"#, Action::Delete),
+            rule(r#"name("*")"#, Action::ReportOnly),
+        ]);
+        assert!(config.validate().is_ok());
+
+        let config = config.with_rules(vec![rule(r#"size > 1M or iname("*")"#, Action::Delete)]);
+        assert!(config.validate().is_err());
+
+        // No name glob bounds what these can select
+        for when in ["size > 0", r#"not name(".keep")"#, r#"path("**")"#] {
+            let config = CleanConfig::new(PathBuf::from("/srv/share"), vec![], vec![], false)
+                .with_rules(vec![rule(when, Action::Delete)]);
+            let err = config.validate().unwrap_err().to_string();
+            assert!(err.contains("whatever their name"), "{}: {}", when, err);
+            assert!(config.with_allow_dangerous(true).validate().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_validate_quarantine() {
+        let quarantine_rule = RuleConfig {
+            when: r#"name("*.bak")"#.to_string(),
+            action: Action::Quarantine,
+            rename_to: None,
+        };
+        let config = CleanConfig::new(PathBuf::from("/srv/share"), vec![], vec![], false)
+            .with_rules(vec![quarantine_rule]);
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("no quarantine_dir is configured"));
+
+        let config = config.with_quarantine_dir(Some(PathBuf::from("/srv/share/held")));
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("must be outside the cleaned root"));
+
+        let config = config.with_quarantine_dir(Some(PathBuf::from("/srv/quarantine")));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_quarantine_pattern_needs_dir() {
+        let config = CleanConfig::new(PathBuf::from("/srv/share"), vec![], vec![], false)
+            .with_conditional_patterns(vec![ConditionalPattern {
+                pattern: "*.bak".to_string(),
+                action: Action::Quarantine,
+                ..Default::default()
+            }]);
+        let err = config.validate().unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "Pattern '*.bak' quarantines files but no quarantine_dir is configured"
+        );
+    }
+
+    #[test]
+    fn test_samba_preset() {
+        let config = CleanConfig::new(PathBuf::from("/srv/share"), vec![], vec![], false)
+            .with_dir_patterns(vec![".AppleDB".to_string()])
+            .with_presets(&[Preset::Samba]);
+
+        assert_eq!(config.patterns, vec![".DS_Store".to_string()]);
+        assert_eq!(
+            config.dir_patterns,
+            vec![
+                ".AppleDB".to_string(),
+                ".AppleDouble".to_string(),
+                ".AppleDesktop".to_string()
+            ]
+        );
+        assert!(config.decode_names);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_allow_dangerous_overrides() {
+        let config = CleanConfig::new(PathBuf::from("/"), vec!["*".to_string()], vec![], false)
+            .with_allow_dangerous(true);
+        assert!(config.validate().is_ok());
+    }
 }
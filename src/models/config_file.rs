@@ -0,0 +1,193 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::{Action, ConditionalPattern, RenameTo};
+
+/// Settings read from a `--config` TOML file:
+///
+/// ```toml
+/// quarantine_dir = "/var/quarantine"
+///
+/// [[patterns]]
+/// pattern = "*.tmp"
+/// older_than = "1d"
+///
+/// [[patterns]]
+/// pattern = "Thumbs.db"
+/// action = "rename"
+/// rename_to = ".{name}.old"
+///
+/// [[rules]]
+/// when = 'name("*.orig") and age > 3d and not path("vendor/**")'
+/// action = "quarantine"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    /// File patterns, each with optional age and size conditions
+    #[serde(default)]
+    pub patterns: Vec<ConditionalPattern>,
+    /// Rules in the rule language, checked in order before the patterns
+    #[serde(default)]
+    pub rules: Vec<RuleConfig>,
+    /// Where quarantined files are moved; relative paths are resolved
+    /// against the config file's directory
+    #[serde(default)]
+    pub quarantine_dir: Option<PathBuf>,
+}
+
+/// A rule as written in the config file: a condition and what to do with files meeting it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleConfig {
+    pub when: String,
+    #[serde(default)]
+    pub action: Action,
+    /// New name of matching files, when `action` is a rename
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rename_to: Option<RenameTo>,
+}
+
+impl ConfigFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config {}", path.display()))?;
+        let mut config =
+            Self::parse(&text).with_context(|| format!("Invalid config {}", path.display()))?;
+        if let (Some(dir), Some(base)) = (&config.quarantine_dir, path.parent()) {
+            config.quarantine_dir = Some(base.join(dir));
+        }
+        Ok(config)
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_patterns() {
+        let config = ConfigFile::parse(
+            r#"
+            [[patterns]]
+            pattern = "*.tmp"
+            older_than = "1d"
+            max_size = "10M"
+
+            [[patterns]]
+            pattern = "*.log"
+            min_size = 1048576
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.patterns.len(), 2);
+        assert_eq!(config.patterns[0].pattern, "*.tmp");
+        assert_eq!(
+            config.patterns[0].conditions.older_than,
+            Some(Duration::from_secs(86400))
+        );
+        assert_eq!(
+            config.patterns[0].conditions.max_size,
+            Some(10 * 1024 * 1024)
+        );
+        assert_eq!(config.patterns[1].conditions.min_size, Some(1024 * 1024));
+    }
+
+    #[test]
+    fn test_parse_pattern_actions() {
+        let config = ConfigFile::parse(
+            r#"
+            [[patterns]]
+            pattern = "*.orig"
+            action = "report-only"
+
+            [[patterns]]
+            pattern = "Thumbs.db"
+            action = "rename"
+            rename_to = ".{name}.old"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.patterns[0].action, Action::ReportOnly);
+        assert_eq!(config.patterns[1].action, Action::Rename);
+        assert_eq!(
+            config.patterns[1].rename_to,
+            Some(RenameTo::new(".{name}.old").unwrap())
+        );
+        assert!(ConfigFile::parse(
+            "[[patterns]]\npattern = \"*.bak\"\nrename_to = \"{name}.old\"\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let config = ConfigFile::parse(
+            r#"
+            quarantine_dir = "/var/quarantine"
+
+            [[rules]]
+            when = 'name("*.orig") and age > 3d'
+            action = "report-only"
+
+            [[rules]]
+            when = 'name("*.rej")'
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.quarantine_dir,
+            Some(PathBuf::from("/var/quarantine"))
+        );
+        assert_eq!(
+            config.rules,
+            vec![
+                RuleConfig {
+                    when: r#"name("*.orig") and age > 3d"#.to_string(),
+                    action: Action::ReportOnly,
+                    rename_to: None,
+                },
+                RuleConfig {
+                    when: r#"name("*.rej")"#.to_string(),
+                    action: Action::Delete,
+                    rename_to: None,
+                },
+            ]
+        );
+        assert!(ConfigFile::parse("[[rules]]\nwhen = 'name(\"a\")'\naction = \"shred\"").is_err());
+    }
+
+    #[test]
+    fn test_load_resolves_quarantine_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cleanup.toml");
+        std::fs::write(&path, "quarantine_dir = \"held\"\n").unwrap();
+
+        let config = ConfigFile::load(&path).unwrap();
+
+        assert_eq!(config.quarantine_dir, Some(dir.path().join("held")));
+    }
+
+    #[test]
+    fn test_empty_config() {
+        assert_eq!(ConfigFile::parse("").unwrap(), ConfigFile::default());
+    }
+
+    #[test]
+    fn test_rejects_unknown_keys_and_bad_values() {
+        assert!(ConfigFile::parse("[[pattern]]\npattern = \"*.tmp\"").is_err());
+        let err = ConfigFile::parse("[[patterns]]\npattern = \"*.tmp\"\nolder_than = \"soon\"")
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("Invalid duration 'soon'"));
+    }
+}
@@ -0,0 +1,245 @@
+//! Lossless text for paths, and other OS strings such as xattr names, that
+//! aren't valid UTF-8.
+//!
+//! UTF-8 paths are written unchanged. Any other path is written with each
+//! invalid byte as `\xNN` and each backslash doubled, so logs and reports can
+//! name the exact file. In JSON such paths become `{"escaped": "..."}`, which
+//! a plain string can't be confused with.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::ffi::{OsStr, OsString};
+use std::fmt::{self, Write as _};
+use std::path::{Path, PathBuf};
+
+/// Displays a path losslessly, see the module docs
+pub struct Escaped<'a>(&'a Path);
+
+pub trait EscapePath {
+    /// Lossless replacement for `Path::display`
+    fn escaped(&self) -> Escaped<'_>;
+}
+
+impl EscapePath for Path {
+    fn escaped(&self) -> Escaped<'_> {
+        Escaped(self)
+    }
+}
+
+impl EscapePath for OsStr {
+    fn escaped(&self) -> Escaped<'_> {
+        Escaped(Path::new(self))
+    }
+}
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.to_str() {
+            Some(text) => f.write_str(text),
+            None => f.write_str(&escape(self.0)),
+        }
+    }
+}
+
+fn escape(path: &Path) -> String {
+    let mut text = String::new();
+    for chunk in path.as_os_str().as_encoded_bytes().utf8_chunks() {
+        for c in chunk.valid().chars() {
+            if c == '\\' {
+                text.push_str("\\\\");
+            } else {
+                text.push(c);
+            }
+        }
+        for byte in chunk.invalid() {
+            let _ = write!(text, "\\x{:02X}", byte);
+        }
+    }
+    text
+}
+
+fn unescape(text: &str) -> Result<PathBuf, String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        rest = tail;
+        if b != b'\\' {
+            bytes.push(b);
+            continue;
+        }
+        match rest {
+            [b'\\', tail @ ..] => {
+                bytes.push(b'\\');
+                rest = tail;
+            }
+            [b'x', hi, lo, tail @ ..] => {
+                let hex = std::str::from_utf8(&[*hi, *lo])
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| format!("invalid escape in path '{}'", text))?;
+                bytes.push(hex);
+                rest = tail;
+            }
+            _ => return Err(format!("invalid escape in path '{}'", text)),
+        }
+    }
+    path_from_bytes(bytes).ok_or_else(|| format!("path '{}' can't be represented here", text))
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> Option<PathBuf> {
+    use std::os::unix::ffi::OsStringExt;
+    Some(PathBuf::from(std::ffi::OsString::from_vec(bytes)))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> Option<PathBuf> {
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Repr<'a> {
+    Plain(std::borrow::Cow<'a, str>),
+    Escaped { escaped: String },
+}
+
+/// Serde helpers for a `PathBuf` field: `#[serde(with = "escaped_path")]`
+pub fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    match path.to_str() {
+        Some(text) => Repr::Plain(text.into()),
+        None => Repr::Escaped {
+            escaped: escape(path),
+        },
+    }
+    .serialize(serializer)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+    match Repr::deserialize(deserializer)? {
+        Repr::Plain(text) => Ok(PathBuf::from(text.as_ref())),
+        Repr::Escaped { escaped } => unescape(&escaped).map_err(serde::de::Error::custom),
+    }
+}
+
+/// Serde helpers for an `Option<PathBuf>` field
+pub mod option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        path: &Option<PathBuf>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Wrap<'a>(#[serde(with = "super")] &'a Path);
+        path.as_deref().map(Wrap).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<PathBuf>, D::Error> {
+        #[derive(Deserialize)]
+        struct Wrap(#[serde(with = "super")] PathBuf);
+        Ok(Option::<Wrap>::deserialize(deserializer)?.map(|Wrap(path)| path))
+    }
+}
+
+/// Serde helpers for a `Vec<OsString>` field, written like paths
+pub mod os_strings {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(names: &[OsString], serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Wrap<'a>(#[serde(with = "super")] &'a Path);
+        serializer.collect_seq(names.iter().map(|name| Wrap(Path::new(name))))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<OsString>, D::Error> {
+        #[derive(Deserialize)]
+        struct Wrap(#[serde(with = "super")] PathBuf);
+        Ok(Vec::<Wrap>::deserialize(deserializer)?
+            .into_iter()
+            .map(|Wrap(name)| name.into_os_string())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Entry {
+        #[serde(with = "super")]
+        path: PathBuf,
+        #[serde(default, with = "option")]
+        moved: Option<PathBuf>,
+    }
+
+    #[test]
+    fn test_utf8_paths_unchanged() {
+        let path = Path::new("/srv/caf\u{e9}\\x.txt");
+        assert_eq!(path.escaped().to_string(), "/srv/caf\u{e9}\\x.txt");
+
+        let entry = Entry {
+            path: path.to_path_buf(),
+            moved: None,
+        };
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(json, r#"{"path":"/srv/café\\x.txt","moved":null}"#);
+        assert_eq!(serde_json::from_str::<Entry>(&json).unwrap(), entry);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_paths_round_trip() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(OsStr::from_bytes(b"/srv/caf\xe9\\x.txt"));
+        assert_eq!(path.escaped().to_string(), r"/srv/caf\xE9\\x.txt");
+
+        let entry = Entry {
+            path: path.to_path_buf(),
+            moved: Some(path.with_file_name(OsStr::from_bytes(b"\xff"))),
+        };
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(
+            json,
+            r#"{"path":{"escaped":"/srv/caf\\xE9\\\\x.txt"},"moved":{"escaped":"/srv/\\xFF"}}"#
+        );
+        assert_eq!(serde_json::from_str::<Entry>(&json).unwrap(), entry);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_os_strings_round_trip() {
+        use std::os::unix::ffi::OsStringExt;
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Names(#[serde(with = "os_strings")] Vec<OsString>);
+
+        let names = Names(vec![
+            OsString::from("user.com.apple.FinderInfo"),
+            OsString::from_vec(b"user.caf\xe9".to_vec()),
+        ]);
+        let json = serde_json::to_string(&names).unwrap();
+        assert_eq!(
+            json,
+            r#"["user.com.apple.FinderInfo",{"escaped":"user.caf\\xE9"}]"#
+        );
+        assert_eq!(serde_json::from_str::<Names>(&json).unwrap(), names);
+    }
+
+    #[test]
+    fn test_bad_escapes_rejected() {
+        for json in [
+            r#"{"path":{"escaped":"a\\"}}"#,
+            r#"{"path":{"escaped":"a\\q"}}"#,
+            r#"{"path":{"escaped":"a\\xZZ"}}"#,
+        ] {
+            assert!(serde_json::from_str::<Entry>(json).is_err(), "{}", json);
+        }
+    }
+}
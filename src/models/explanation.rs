@@ -0,0 +1,275 @@
+use std::ffi::OsString;
+use std::fmt;
+use std::path::PathBuf;
+
+use super::{Action, EscapePath, SkipReason};
+
+/// Why one path would or wouldn't be cleaned, as worked out by `Cleaner::explain`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Explanation {
+    pub path: PathBuf,
+    pub kind: EntryKind,
+    /// First ancestor the walk never enters, with the ignore entry naming it
+    pub ignored_by: Option<(PathBuf, String)>,
+    /// Outermost directory removed as a whole, with the directory pattern it matched
+    pub removed_with: Option<(PathBuf, String)>,
+    /// Every config rule, in order
+    pub rules: Vec<RuleCheck>,
+    /// Every pattern matching the name, in order
+    pub patterns: Vec<PatternCheck>,
+    /// Special handling the name gets, e.g. as an AppleDouble file
+    pub handled_as: Option<String>,
+    pub verdict: Verdict,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+}
+
+/// How a config rule fared against the file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuleCheck {
+    pub when: String,
+    pub action: Action,
+    /// `None` when the file couldn't be read to decide
+    pub matched: Option<bool>,
+}
+
+/// A pattern matching the file name, and whether the file meets the rest of it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatternCheck {
+    pub pattern: String,
+    /// "glob" or "regex"
+    pub kind: String,
+    /// Set when only the name decoded from a server encoding matched
+    pub decoded_name: Option<String>,
+    pub action: Action,
+    /// Each age or size condition and whether the file meets it
+    pub conditions: Vec<(String, bool)>,
+    /// Content check the file must pass, and whether it does
+    pub signature: Option<(String, bool)>,
+}
+
+impl PatternCheck {
+    pub fn passes(&self) -> bool {
+        self.conditions.iter().all(|(_, met)| *met)
+            && self.signature.as_ref().is_none_or(|(_, met)| *met)
+    }
+}
+
+/// What a cleanup would do with the path
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// Nothing, for the reason given
+    Keep(String),
+    /// Matched, but left in place
+    Skip(SkipReason),
+    /// Matched and acted on; `target` is where a quarantined or renamed file goes
+    Act {
+        action: Action,
+        target: Option<PathBuf>,
+    },
+    /// Kept, with these extended attributes stripped
+    StripXattrs(Vec<OsString>),
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Keep(reason) => write!(f, "keep ({})", reason),
+            Verdict::Skip(reason) => write!(f, "keep, although matched ({})", reason),
+            Verdict::Act {
+                action,
+                target: Some(target),
+            } => write!(f, "{} to {}", action, target.escaped()),
+            Verdict::Act { action, .. } => write!(f, "{}", action),
+            Verdict::StripXattrs(names) => {
+                let names: Vec<String> = names.iter().map(|n| n.escaped().to_string()).collect();
+                write!(f, "keep, stripping xattrs {}", names.join(", "))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            EntryKind::File => "file",
+            EntryKind::Directory => "directory",
+            EntryKind::Symlink => "symlink",
+        };
+        writeln!(f, "{} ({})", self.path.escaped(), kind)?;
+
+        match &self.ignored_by {
+            Some((dir, entry)) => writeln!(
+                f,
+                "  ignored: yes, {} is skipped by --ignore {}",
+                dir.escaped(),
+                entry
+            )?,
+            None => writeln!(f, "  ignored: no")?,
+        }
+        if let Some((dir, pattern)) = &self.removed_with {
+            writeln!(
+                f,
+                "  inside removed directory: {} (dir pattern '{}')",
+                dir.escaped(),
+                pattern
+            )?;
+        }
+
+        for rule in &self.rules {
+            let outcome = match rule.matched {
+                Some(true) => "matches",
+                Some(false) => "no match",
+                None => "undecided, file unreadable",
+            };
+            writeln!(f, "  rule '{}' ({}): {}", rule.when, rule.action, outcome)?;
+        }
+
+        if self.patterns.is_empty() {
+            writeln!(f, "  patterns: none match the name")?;
+        }
+        for pattern in &self.patterns {
+            write!(
+                f,
+                "  {} '{}' ({})",
+                pattern.kind, pattern.pattern, pattern.action
+            )?;
+            match &pattern.decoded_name {
+                Some(decoded) => writeln!(f, ": matches decoded name '{}'", decoded)?,
+                None => writeln!(f, ": matches name")?,
+            }
+            for (condition, met) in &pattern.conditions {
+                writeln!(f, "    {}: {}", condition, pass_fail(*met))?;
+            }
+            if let Some((signature, met)) = &pattern.signature {
+                writeln!(f, "    content is {}: {}", signature, pass_fail(*met))?;
+            }
+        }
+
+        if let Some(handling) = &self.handled_as {
+            writeln!(f, "  handled as: {}", handling)?;
+        }
+        write!(f, "  result: {}", self.verdict)
+    }
+}
+
+/// What a cleanup would do with a file, judged by its path alone
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NameVerdict {
+    pub path: PathBuf,
+    pub outcome: Outcome,
+    /// The ignore entry, pattern or rule that decided
+    pub reason: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Keep,
+    Act(Action),
+    /// Acted on only if the file's age, size, owner or content allow
+    Depends(Action),
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Keep => write!(f, "keep"),
+            Outcome::Act(action) => write!(f, "{}", action),
+            Outcome::Depends(action) => write!(f, "{}?", action),
+        }
+    }
+}
+
+/// One tab-separated line: outcome, path and reason
+impl fmt::Display for NameVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}",
+            self.outcome,
+            self.path.escaped(),
+            self.reason
+        )
+    }
+}
+
+fn pass_fail(met: bool) -> &'static str {
+    if met {
+        "pass"
+    } else {
+        "fail"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let explanation = Explanation {
+            path: PathBuf::from("/share/build/app.tmp"),
+            kind: EntryKind::File,
+            ignored_by: None,
+            removed_with: None,
+            rules: vec![RuleCheck {
+                when: r#"path("cache/**")"#.to_string(),
+                action: Action::Quarantine,
+                matched: Some(false),
+            }],
+            patterns: vec![PatternCheck {
+                pattern: "*.tmp".to_string(),
+                kind: "glob".to_string(),
+                decoded_name: None,
+                action: Action::Delete,
+                conditions: vec![("older than 7d".to_string(), false)],
+                signature: None,
+            }],
+            handled_as: None,
+            verdict: Verdict::Keep("no pattern or rule applies".to_string()),
+        };
+
+        assert_eq!(
+            explanation.to_string(),
+            "/share/build/app.tmp (file)\n\
+             \x20 ignored: no\n\
+             \x20 rule 'path(\"cache/**\")' (quarantine): no match\n\
+             \x20 glob '*.tmp' (delete): matches name\n\
+             \x20   older than 7d: fail\n\
+             \x20 result: keep (no pattern or rule applies)"
+        );
+        assert!(!explanation.patterns[0].passes());
+    }
+
+    #[test]
+    fn test_name_verdict_display() {
+        let verdict = NameVerdict {
+            path: PathBuf::from("build/app.tmp"),
+            outcome: Outcome::Depends(Action::Delete),
+            reason: "glob '*.tmp' if older than 1w".to_string(),
+        };
+        assert_eq!(
+            verdict.to_string(),
+            "delete?\tbuild/app.tmp\tglob '*.tmp' if older than 1w"
+        );
+        assert_eq!(Outcome::Keep.to_string(), "keep");
+    }
+
+    #[test]
+    fn test_verdict_display() {
+        let rename = Verdict::Act {
+            action: Action::Rename,
+            target: Some(PathBuf::from("/share/.Thumbs.db.old")),
+        };
+        assert_eq!(rename.to_string(), "rename to /share/.Thumbs.db.old");
+        assert_eq!(
+            Verdict::Skip(SkipReason::InUse).to_string(),
+            "keep, although matched (in use)"
+        );
+    }
+}
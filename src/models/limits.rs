@@ -0,0 +1,158 @@
+use anyhow::{bail, Result};
+
+use super::units::format_size;
+use super::{Action, DirMatch, EscapePath, FileMatch};
+
+/// Number of paths included in a limit violation message
+const SAMPLE_SIZE: usize = 5;
+
+/// Safety caps on how much a single run may delete.
+#[derive(Clone, Debug, Default)]
+pub struct DeletionLimits {
+    pub max_deletions: Option<usize>,
+    pub max_bytes: Option<u64>,
+    /// Ignore the limits above
+    pub force: bool,
+}
+
+impl DeletionLimits {
+    /// Fail if the scanned matches exceed either limit, unless forced.
+    /// Files inside matched directories count towards both limits; files that
+    /// are quarantined or only reported don't.
+    pub fn check(&self, matches: &[FileMatch], dirs: &[DirMatch]) -> Result<()> {
+        if self.force {
+            return Ok(());
+        }
+
+        let matches: Vec<&FileMatch> = matches
+            .iter()
+            .filter(|m| m.action == Action::Delete)
+            .collect();
+        let count = matches.len() + dirs.iter().map(|d| d.files).sum::<usize>();
+        let bytes: u64 = matches.iter().map(|m| m.metadata.size).sum::<u64>()
+            + dirs.iter().map(|d| d.bytes).sum::<u64>();
+
+        let violation = match (self.max_deletions, self.max_bytes) {
+            (Some(max), _) if count > max => format!("exceeds --max-deletions {}", max),
+            (_, Some(max)) if bytes > max => {
+                format!("exceeds --max-bytes {}", format_size(max))
+            }
+            _ => return Ok(()),
+        };
+
+        let sample: Vec<String> = dirs
+            .iter()
+            .map(|d| format!("{}/", d.path.escaped()))
+            .chain(matches.iter().map(|m| m.path.escaped().to_string()))
+            .take(SAMPLE_SIZE)
+            .collect();
+        bail!(
+            "Refusing to delete {} files ({}): {}. Sample: {}{}. Re-run with --force to override",
+            count,
+            format_size(bytes),
+            violation,
+            sample.join(", "),
+            if count > SAMPLE_SIZE { ", ..." } else { "" }
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::FileMetadata;
+    use std::path::PathBuf;
+    use std::time::SystemTime;
+
+    fn matches(count: usize, size: u64) -> Vec<FileMatch> {
+        (0..count)
+            .map(|i| FileMatch {
+                action: Action::Delete,
+                path: PathBuf::from(format!("/test/{}/.DS_Store", i)),
+                metadata: FileMetadata {
+                    size,
+                    modified: SystemTime::UNIX_EPOCH,
+                    inode: i as u64,
+                    dev: 0,
+                },
+                rename_to: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_no_limits_allows_everything() {
+        assert!(DeletionLimits::default()
+            .check(&matches(1000, 1), &[])
+            .is_ok());
+    }
+
+    #[test]
+    fn test_max_deletions_exceeded() {
+        let limits = DeletionLimits {
+            max_deletions: Some(3),
+            ..Default::default()
+        };
+
+        assert!(limits.check(&matches(3, 1), &[]).is_ok());
+        let err = limits.check(&matches(10, 1), &[]).unwrap_err().to_string();
+        assert!(err.contains("Refusing to delete 10 files"));
+        assert!(err.contains("--max-deletions 3"));
+        assert!(err.contains("/test/0/.DS_Store"));
+        assert!(!err.contains("/test/9/.DS_Store"));
+    }
+
+    #[test]
+    fn test_max_bytes_exceeded() {
+        let limits = DeletionLimits {
+            max_bytes: Some(1024),
+            ..Default::default()
+        };
+
+        assert!(limits.check(&matches(2, 512), &[]).is_ok());
+        let err = limits.check(&matches(3, 512), &[]).unwrap_err().to_string();
+        assert!(err.contains("--max-bytes 1.0 KiB"));
+    }
+
+    #[test]
+    fn test_directory_contents_count_towards_limits() {
+        let limits = DeletionLimits {
+            max_deletions: Some(5),
+            ..Default::default()
+        };
+        let dirs = [DirMatch {
+            path: PathBuf::from("/test/__MACOSX"),
+            files: 4,
+            bytes: 4096,
+        }];
+
+        assert!(limits.check(&matches(1, 1), &dirs).is_ok());
+        let err = limits.check(&matches(2, 1), &dirs).unwrap_err().to_string();
+        assert!(err.contains("Refusing to delete 6 files"));
+        assert!(err.contains("/test/__MACOSX/"));
+    }
+
+    #[test]
+    fn test_only_deletions_count_towards_limits() {
+        let limits = DeletionLimits {
+            max_deletions: Some(1),
+            ..Default::default()
+        };
+        let mut files = matches(3, 1);
+        files[1].action = Action::Quarantine;
+        files[2].action = Action::ReportOnly;
+
+        assert!(limits.check(&files, &[]).is_ok());
+    }
+
+    #[test]
+    fn test_force_overrides_limits() {
+        let limits = DeletionLimits {
+            max_deletions: Some(1),
+            max_bytes: Some(1),
+            force: true,
+        };
+
+        assert!(limits.check(&matches(10, 10), &[]).is_ok());
+    }
+}
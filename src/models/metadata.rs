@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// Identity of a file as observed at a point in time.
+///
+/// Two snapshots of the same path compare equal only if the file has not been
+/// replaced (device and inode), rewritten (mtime) or resized in between.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileMetadata {
+    pub size: u64,
+    pub modified: SystemTime,
+    pub inode: u64,
+    /// Device holding the inode
+    #[serde(default)]
+    pub dev: u64,
+}
+
+/// The user owning a file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileOwner {
+    pub uid: u32,
+    /// Account name, if the uid has one on this machine
+    pub name: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_metadata_equality_detects_changes() {
+        let original = FileMetadata {
+            size: 10,
+            modified: SystemTime::UNIX_EPOCH,
+            inode: 1,
+            dev: 0,
+        };
+
+        assert_eq!(original, original.clone());
+        assert_ne!(
+            original,
+            FileMetadata {
+                size: 11,
+                ..original.clone()
+            }
+        );
+        assert_ne!(
+            original,
+            FileMetadata {
+                modified: SystemTime::UNIX_EPOCH + Duration::from_secs(1),
+                ..original.clone()
+            }
+        );
+        assert_ne!(
+            original,
+            FileMetadata {
+                inode: 2,
+                ..original.clone()
+            }
+        );
+        assert_ne!(
+            original,
+            FileMetadata {
+                dev: 1,
+                ..original.clone()
+            }
+        );
+    }
+}
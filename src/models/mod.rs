@@ -1,5 +1,30 @@
+mod action;
+mod audit;
+mod conditions;
 mod config;
+mod config_file;
+pub mod escaped_path;
+mod explanation;
+mod limits;
+mod metadata;
+mod plan;
+mod preset;
 mod result;
+mod safety;
+pub mod units;
 
-pub use config::CleanConfig;
-pub use result::CleanResult;
+pub use action::{Action, RenameTo};
+pub use audit::{DsStoreAudit, Leak, LeakReason, LeakReport};
+pub use conditions::{ConditionalPattern, Conditions};
+pub use config::{CleanConfig, DEFAULT_XATTR_PATTERNS};
+pub use config_file::{ConfigFile, RuleConfig};
+pub use escaped_path::EscapePath;
+pub use explanation::{
+    EntryKind, Explanation, NameVerdict, Outcome, PatternCheck, RuleCheck, Verdict,
+};
+pub use limits::DeletionLimits;
+pub use metadata::{FileMetadata, FileOwner};
+pub use plan::{Plan, PLAN_VERSION};
+pub use preset::Preset;
+pub use result::{CleanResult, DirMatch, FileMatch, ScanReport, SkipReason, XattrMatch};
+pub use safety::{dangerous_pattern_reason, dangerous_root_reason};
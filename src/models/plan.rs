@@ -0,0 +1,381 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+use super::{
+    escaped_path, CleanConfig, ConditionalPattern, DirMatch, EscapePath, FileMatch, RuleConfig,
+    XattrMatch,
+};
+
+/// Current plan file format version
+pub const PLAN_VERSION: u32 = 1;
+
+/// A reviewable list of actions produced by `plan` and executed by `apply`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Plan {
+    pub version: u32,
+    #[serde(with = "escaped_path")]
+    pub root_dir: PathBuf,
+    pub patterns: Vec<String>,
+    /// Patterns that also require the file's header to match when applying
+    #[serde(default)]
+    pub validated_patterns: Vec<String>,
+    pub ignore_patterns: Vec<String>,
+    /// Patterns that only matched files meeting age and size conditions
+    #[serde(default)]
+    pub conditional_patterns: Vec<ConditionalPattern>,
+    /// Rules that selected files and their actions
+    #[serde(default)]
+    pub rules: Vec<RuleConfig>,
+    /// Where quarantined files are moved when applying
+    #[serde(default, with = "escaped_path::option")]
+    pub quarantine_dir: Option<PathBuf>,
+    /// Re-check artifact headers when applying
+    #[serde(default)]
+    pub verify_signatures: bool,
+    /// Match names after decoding Samba/netatalk encodings when applying
+    #[serde(default)]
+    pub decode_names: bool,
+    /// Match file and directory patterns regardless of case when applying
+    #[serde(default)]
+    pub ignore_case: bool,
+    /// Skip files held open by any process when applying
+    #[serde(default)]
+    pub skip_in_use: bool,
+    /// Files to act on, each with its action and identity at plan time
+    pub actions: Vec<FileMatch>,
+    #[serde(default)]
+    pub dir_patterns: Vec<String>,
+    /// Directories to remove recursively, with their contents at plan time
+    #[serde(default)]
+    pub dir_deletions: Vec<DirMatch>,
+    /// Remove directories left empty when applying
+    #[serde(default)]
+    pub prune_empty_dirs: bool,
+    #[serde(default)]
+    pub xattr_patterns: Vec<String>,
+    /// Extended attributes to strip from kept files
+    #[serde(default)]
+    pub xattr_removals: Vec<XattrMatch>,
+}
+
+impl Plan {
+    pub fn new(
+        root_dir: PathBuf,
+        patterns: Vec<String>,
+        ignore_patterns: Vec<String>,
+        matches: Vec<FileMatch>,
+    ) -> Self {
+        Self {
+            version: PLAN_VERSION,
+            root_dir,
+            patterns,
+            validated_patterns: Vec::new(),
+            ignore_patterns,
+            conditional_patterns: Vec::new(),
+            rules: Vec::new(),
+            quarantine_dir: None,
+            verify_signatures: false,
+            decode_names: false,
+            ignore_case: false,
+            skip_in_use: false,
+            actions: matches,
+            dir_patterns: Vec::new(),
+            dir_deletions: Vec::new(),
+            prune_empty_dirs: false,
+            xattr_patterns: Vec::new(),
+            xattr_removals: Vec::new(),
+        }
+    }
+
+    pub fn with_validated_patterns(mut self, validated_patterns: Vec<String>) -> Self {
+        self.validated_patterns = validated_patterns;
+        self
+    }
+
+    pub fn with_conditional_patterns(mut self, patterns: Vec<ConditionalPattern>) -> Self {
+        self.conditional_patterns = patterns;
+        self
+    }
+
+    pub fn with_rules(mut self, rules: Vec<RuleConfig>, quarantine_dir: Option<PathBuf>) -> Self {
+        self.rules = rules;
+        self.quarantine_dir = quarantine_dir;
+        self
+    }
+
+    pub fn with_in_use_check(mut self, skip_in_use: bool) -> Self {
+        self.skip_in_use = skip_in_use;
+        self
+    }
+
+    pub fn with_ignore_case(mut self, ignore_case: bool) -> Self {
+        self.ignore_case = ignore_case;
+        self
+    }
+
+    pub fn with_name_decoding(mut self, decode_names: bool) -> Self {
+        self.decode_names = decode_names;
+        self
+    }
+
+    pub fn with_prune_empty_dirs(mut self, prune_empty_dirs: bool) -> Self {
+        self.prune_empty_dirs = prune_empty_dirs;
+        self
+    }
+
+    pub fn with_xattrs(mut self, xattr_patterns: Vec<String>, xattrs: Vec<XattrMatch>) -> Self {
+        self.xattr_patterns = xattr_patterns;
+        self.xattr_removals = xattrs;
+        self
+    }
+
+    pub fn with_dirs(mut self, dir_patterns: Vec<String>, dirs: Vec<DirMatch>) -> Self {
+        self.dir_patterns = dir_patterns;
+        self.dir_deletions = dirs;
+        self
+    }
+
+    pub fn with_signature_checks(mut self, verify_signatures: bool) -> Self {
+        self.verify_signatures = verify_signatures;
+        self
+    }
+
+    /// The settings the plan was made with, for the same safety checks as a run
+    pub fn to_config(&self, dry_run: bool) -> CleanConfig {
+        CleanConfig {
+            patterns: self.patterns.clone(),
+            validated_patterns: self.validated_patterns.clone(),
+            decode_names: self.decode_names,
+            ..CleanConfig::new(
+                self.root_dir.clone(),
+                Vec::new(),
+                self.ignore_patterns.clone(),
+                dry_run,
+            )
+        }
+        .with_signature_checks(self.verify_signatures)
+        .with_conditional_patterns(self.conditional_patterns.clone())
+        .with_rules(self.rules.clone())
+        .with_quarantine_dir(self.quarantine_dir.clone())
+        .with_ignore_case(self.ignore_case)
+        .with_in_use_check(self.skip_in_use)
+        .with_prune_empty_dirs(self.prune_empty_dirs)
+        .with_dir_patterns(self.dir_patterns.clone())
+        .with_xattr_patterns(self.xattr_patterns.clone())
+    }
+
+    /// Refuse plans that act on anything outside their root, as only an edited
+    /// plan would
+    pub fn check_paths(&self) -> Result<()> {
+        if !self.root_dir.is_absolute() {
+            bail!(
+                "Plan root {} is not an absolute path",
+                self.root_dir.escaped()
+            );
+        }
+        let paths = self
+            .actions
+            .iter()
+            .flat_map(|m| std::iter::once(&m.path).chain(&m.rename_to))
+            .chain(self.dir_deletions.iter().map(|d| &d.path))
+            .chain(self.xattr_removals.iter().map(|x| &x.path));
+        for path in paths {
+            if !is_inside(path, &self.root_dir) {
+                bail!(
+                    "Plan acts on {}, which is outside its root {}",
+                    path.escaped(),
+                    self.root_dir.escaped()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let plan: Plan = serde_json::from_str(json)?;
+        if plan.version != PLAN_VERSION {
+            bail!(
+                "Unsupported plan version {} (expected {})",
+                plan.version,
+                PLAN_VERSION
+            );
+        }
+        Ok(plan)
+    }
+}
+
+/// Whether absolute `path` lies below `root`, without `..` leading back out of it
+fn is_inside(path: &Path, root: &Path) -> bool {
+    path.is_absolute()
+        && path != root
+        && path.starts_with(root)
+        && !path.components().any(|c| c == Component::ParentDir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Action, FileMetadata};
+    use std::time::{Duration, SystemTime};
+
+    fn sample_plan() -> Plan {
+        Plan::new(
+            PathBuf::from("/test"),
+            vec![".DS_Store".to_string()],
+            vec!["node_modules".to_string()],
+            vec![FileMatch {
+                action: Action::Delete,
+                path: PathBuf::from("/test/.DS_Store"),
+                metadata: FileMetadata {
+                    size: 6148,
+                    modified: SystemTime::UNIX_EPOCH + Duration::from_nanos(1_700_000_000_123),
+                    inode: 42,
+                    dev: 0,
+                },
+                rename_to: None,
+            }],
+        )
+    }
+
+    #[test]
+    fn test_plan_round_trip() {
+        let plan = sample_plan();
+
+        let parsed = Plan::from_json(&plan.to_json().unwrap()).unwrap();
+
+        assert_eq!(parsed.root_dir, PathBuf::from("/test"));
+        assert_eq!(parsed.patterns, vec![".DS_Store".to_string()]);
+        assert_eq!(parsed.ignore_patterns, vec!["node_modules".to_string()]);
+        assert_eq!(parsed.actions.len(), 1);
+        assert_eq!(parsed.actions[0].action, Action::Delete);
+        assert_eq!(parsed.actions[0].path, PathBuf::from("/test/.DS_Store"));
+        assert_eq!(parsed.actions[0].metadata, plan.actions[0].metadata);
+    }
+
+    #[test]
+    fn test_plan_json_lists_action_and_identity() {
+        let json: serde_json::Value =
+            serde_json::from_str(&sample_plan().to_json().unwrap()).unwrap();
+
+        let action = &json["actions"][0];
+        assert_eq!(action["action"], "delete");
+        assert_eq!(action["path"], "/test/.DS_Store");
+        assert_eq!(action["metadata"]["size"], 6148);
+        assert_eq!(action["metadata"]["inode"], 42);
+    }
+
+    #[test]
+    fn test_plan_rejects_paths_outside_root() {
+        assert!(sample_plan().check_paths().is_ok());
+
+        for outside in [
+            "/etc/passwd",
+            "/test/../etc/passwd",
+            "/test",
+            "/testing/.DS_Store",
+            "test/.DS_Store",
+        ] {
+            let mut plan = sample_plan();
+            plan.actions[0].path = PathBuf::from(outside);
+            let err = plan.check_paths().unwrap_err().to_string();
+            assert!(err.contains("outside its root /test"), "{}", outside);
+        }
+
+        let mut plan = sample_plan();
+        plan.actions[0].rename_to = Some(PathBuf::from("/etc/cron.d/job"));
+        assert!(plan.check_paths().is_err());
+
+        let plan = sample_plan().with_dirs(
+            Vec::new(),
+            vec![DirMatch {
+                path: PathBuf::from("/home"),
+                files: 1,
+                bytes: 1,
+            }],
+        );
+        assert!(plan.check_paths().is_err());
+
+        // A relative root would resolve against wherever `apply` runs
+        let mut plan = sample_plan();
+        plan.root_dir = PathBuf::from("test");
+        plan.actions[0].path = PathBuf::from("test/.DS_Store");
+        let err = plan.check_paths().unwrap_err().to_string();
+        assert!(err.contains("not an absolute path"));
+    }
+
+    #[test]
+    fn test_plan_to_config_keeps_patterns() {
+        let config = sample_plan().to_config(true);
+
+        assert_eq!(config.root_dir, PathBuf::from("/test"));
+        assert_eq!(config.patterns, vec![".DS_Store".to_string()]);
+        assert_eq!(config.ignore_patterns, vec!["node_modules".to_string()]);
+        assert!(config.dry_run);
+    }
+
+    #[test]
+    fn test_plan_round_trips_directories() {
+        let dir = DirMatch {
+            path: PathBuf::from("/test/__MACOSX"),
+            files: 3,
+            bytes: 1234,
+        };
+        let plan = sample_plan().with_dirs(vec!["__MACOSX".to_string()], vec![dir.clone()]);
+
+        let parsed = Plan::from_json(&plan.to_json().unwrap()).unwrap();
+
+        assert_eq!(parsed.dir_patterns, vec!["__MACOSX".to_string()]);
+        assert_eq!(parsed.dir_deletions, vec![dir]);
+    }
+
+    #[test]
+    fn test_plan_records_conditional_patterns() {
+        let pattern = ConditionalPattern {
+            pattern: "*.tmp".to_string(),
+            conditions: crate::models::Conditions {
+                older_than: Some(std::time::Duration::from_secs(86400)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let plan = sample_plan().with_conditional_patterns(vec![pattern.clone()]);
+
+        let json = plan.to_json().unwrap();
+        assert!(json.contains(r#""older_than": "1d""#));
+        let parsed = Plan::from_json(&json).unwrap();
+        assert_eq!(parsed.conditional_patterns, vec![pattern]);
+    }
+
+    #[test]
+    fn test_plan_without_directories_still_parses() {
+        let mut json: serde_json::Value =
+            serde_json::from_str(&sample_plan().to_json().unwrap()).unwrap();
+        let object = json.as_object_mut().unwrap();
+        object.remove("dir_patterns");
+        object.remove("dir_deletions");
+        object.remove("xattr_patterns");
+        object.remove("xattr_removals");
+        object.remove("conditional_patterns");
+        object.remove("ignore_case");
+
+        let parsed = Plan::from_json(&json.to_string()).unwrap();
+
+        assert!(parsed.dir_deletions.is_empty());
+        assert!(parsed.xattr_removals.is_empty());
+        assert!(!parsed.ignore_case);
+    }
+
+    #[test]
+    fn test_plan_rejects_unknown_version() {
+        let mut plan = sample_plan();
+        plan.version = 99;
+
+        let err = Plan::from_json(&plan.to_json().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("Unsupported plan version"));
+    }
+}
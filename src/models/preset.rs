@@ -0,0 +1,108 @@
+use anyhow::{bail, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// A bundle of patterns for the artifacts one kind of environment leaves behind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    /// Mac metadata stored by Samba (vfs_fruit/catia) and netatalk file servers
+    Samba,
+    /// Download markers and thumbnail caches from Windows and WSL
+    Windows,
+}
+
+impl Preset {
+    pub const ALL: &'static [Preset] = &[Preset::Samba, Preset::Windows];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Samba => "samba",
+            Preset::Windows => "windows",
+        }
+    }
+
+    /// File name patterns added by this preset
+    pub fn patterns(&self) -> &'static [&'static str] {
+        match self {
+            Preset::Samba => &[".DS_Store"],
+            Preset::Windows => &["Thumbs.db", "desktop.ini"],
+        }
+    }
+
+    /// File name patterns that only match when the file's header carries the
+    /// signature known for the pattern
+    pub fn validated_patterns(&self) -> &'static [&'static str] {
+        match self {
+            Preset::Samba => &[],
+            // Alternate data streams spilled into `name:Zone.Identifier` files by
+            // WSL and Samba; real files can have colons in their name too
+            Preset::Windows => &["*:Zone.Identifier"],
+        }
+    }
+
+    /// Directory name patterns removed as a unit
+    pub fn dir_patterns(&self) -> &'static [&'static str] {
+        match self {
+            Preset::Samba => &[".AppleDouble", ".AppleDB", ".AppleDesktop"],
+            Preset::Windows => &[],
+        }
+    }
+
+    /// Whether names are also matched after decoding server-side encodings
+    /// such as `:2eDS_Store`
+    pub fn decodes_names(&self) -> bool {
+        match self {
+            Preset::Samba => true,
+            Preset::Windows => false,
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Preset {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let found = Preset::ALL
+            .iter()
+            .find(|p| p.name().eq_ignore_ascii_case(value));
+        match found {
+            Some(preset) => Ok(*preset),
+            None => {
+                let names: Vec<_> = Preset::ALL.iter().map(|p| p.name()).collect();
+                bail!(
+                    "unknown preset '{}' (expected one of: {})",
+                    value,
+                    names.join(", ")
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_preset() {
+        assert_eq!("samba".parse::<Preset>().unwrap(), Preset::Samba);
+        assert_eq!("Samba".parse::<Preset>().unwrap(), Preset::Samba);
+        assert_eq!("windows".parse::<Preset>().unwrap(), Preset::Windows);
+
+        let err = "nfs".parse::<Preset>().unwrap_err().to_string();
+        assert!(err.contains("expected one of: samba, windows"));
+    }
+
+    #[test]
+    fn test_samba_preset_treats_apple_double_dirs_as_units() {
+        assert!(Preset::Samba.dir_patterns().contains(&".AppleDouble"));
+        assert!(Preset::Samba.dir_patterns().contains(&".AppleDB"));
+        assert!(Preset::Samba.decodes_names());
+    }
+}
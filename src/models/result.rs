@@ -1,9 +1,114 @@
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fmt;
 use std::path::PathBuf;
 
+use super::{escaped_path, Action, FileMetadata};
+
+/// A file selected for cleanup, together with its identity at scan time.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FileMatch {
+    #[serde(default)]
+    pub action: Action,
+    #[serde(with = "escaped_path")]
+    pub path: PathBuf,
+    pub metadata: FileMetadata,
+    /// New path of a file whose action is a rename
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "escaped_path::option"
+    )]
+    pub rename_to: Option<PathBuf>,
+}
+
+/// A directory selected for recursive removal, with what it held at scan time.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirMatch {
+    #[serde(with = "escaped_path")]
+    pub path: PathBuf,
+    /// Number of files anywhere below the directory
+    pub files: usize,
+    /// Total size of those files
+    pub bytes: u64,
+}
+
+/// Extended attributes to strip from a file that is otherwise kept.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct XattrMatch {
+    #[serde(with = "escaped_path")]
+    pub path: PathBuf,
+    #[serde(with = "escaped_path::os_strings")]
+    pub names: Vec<OsString>,
+}
+
+/// Outcome of a scan: files, directories and xattrs to clean, and matched files that must be kept
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    pub matches: Vec<FileMatch>,
+    pub dirs: Vec<DirMatch>,
+    pub xattrs: Vec<XattrMatch>,
+    pub skipped: Vec<(PathBuf, SkipReason)>,
+}
+
+/// Why a matched file was left in place.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Size, mtime or inode differ from what the scan recorded
+    ModifiedSinceScan,
+    /// A well-known artifact lacked its expected header (described by the payload)
+    SignatureMismatch(String),
+    /// An AppleDouble whose base file exists and which still carries metadata
+    PairedAppleDouble,
+    /// Held open by a running process
+    InUse,
+    /// An editor swap or lock file whose owning session (described by the payload)
+    /// is still running or ran on another machine
+    LiveEditorSession(String),
+    /// An Emacs auto-save file with no lock naming its session, which may be the
+    /// only copy of edits from a crashed editor
+    UnownedAutoSave,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::ModifiedSinceScan => write!(f, "modified since scan"),
+            SkipReason::SignatureMismatch(expected) => {
+                write!(f, "signature mismatch: expected {}", expected)
+            }
+            SkipReason::PairedAppleDouble => {
+                write!(f, "AppleDouble holds data for an existing file")
+            }
+            SkipReason::InUse => write!(f, "in use"),
+            SkipReason::LiveEditorSession(owner) => write!(f, "live editor session ({})", owner),
+            SkipReason::UnownedAutoSave => write!(f, "unowned auto-save"),
+        }
+    }
+}
+
+#[derive(Debug)]
 pub struct CleanResult {
     pub files_found: usize,
     pub files_deleted: usize,
+    /// Files moved to the quarantine directory (or that would be, in dry-run mode)
+    pub files_quarantined: usize,
+    /// Matched files left in place by a report-only rule
+    pub files_reported: usize,
+    /// Files given a new name (or that would be, in dry-run mode)
+    pub files_renamed: usize,
     pub files_failed: Vec<(PathBuf, String)>,
+    pub files_skipped: Vec<(PathBuf, SkipReason)>,
+    pub dirs_found: usize,
+    /// Directories removed (or that would be, in dry-run mode) with their contents
+    pub dirs_deleted: Vec<DirMatch>,
+    /// Directories removed (or that would be, in dry-run mode) because this run
+    /// left them empty
+    pub dirs_pruned: Vec<PathBuf>,
+    /// Matching extended attributes found on kept files
+    pub xattrs_found: usize,
+    /// Extended attributes removed (or that would be, in dry-run mode)
+    pub xattrs_removed: usize,
     pub dry_run: bool,
 }
 
@@ -12,7 +117,16 @@ impl CleanResult {
         Self {
             files_found,
             files_deleted: 0,
+            files_quarantined: 0,
+            files_reported: 0,
+            files_renamed: 0,
             files_failed: Vec::new(),
+            files_skipped: Vec::new(),
+            dirs_found: 0,
+            dirs_deleted: Vec::new(),
+            dirs_pruned: Vec::new(),
+            xattrs_found: 0,
+            xattrs_removed: 0,
             dry_run,
         }
     }
@@ -28,6 +142,9 @@ mod tests {
         assert_eq!(result.files_found, 5);
         assert_eq!(result.files_deleted, 0);
         assert!(result.files_failed.is_empty());
+        assert!(result.files_skipped.is_empty());
+        assert_eq!(result.dirs_found, 0);
+        assert!(result.dirs_deleted.is_empty());
         assert!(!result.dry_run);
     }
 
@@ -36,4 +153,22 @@ mod tests {
         let result = CleanResult::new(3, true);
         assert!(result.dry_run);
     }
+
+    #[test]
+    fn test_skip_reason_display() {
+        assert_eq!(
+            SkipReason::ModifiedSinceScan.to_string(),
+            "modified since scan"
+        );
+        assert_eq!(
+            SkipReason::SignatureMismatch("AppleDouble header".to_string()).to_string(),
+            "signature mismatch: expected AppleDouble header"
+        );
+        assert_eq!(SkipReason::InUse.to_string(), "in use");
+        assert_eq!(
+            SkipReason::LiveEditorSession("pid 7 on build01".to_string()).to_string(),
+            "live editor session (pid 7 on build01)"
+        );
+        assert_eq!(SkipReason::UnownedAutoSave.to_string(), "unowned auto-save");
+    }
 }
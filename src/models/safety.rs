@@ -0,0 +1,293 @@
+use regex::bytes::Regex;
+use std::path::Path;
+
+use crate::core::{NameGlob, REGEX_PREFIX};
+
+/// System directories that are never a sensible cleanup root.
+const SYSTEM_ROOTS: &[&str] = &[
+    "/",
+    "/Applications",
+    "/Library",
+    "/System",
+    "/Users",
+    "/Volumes",
+    "/bin",
+    "/boot",
+    "/dev",
+    "/etc",
+    "/home",
+    "/lib",
+    "/lib64",
+    "/opt",
+    "/private",
+    "/proc",
+    "/root",
+    "/sbin",
+    "/sys",
+    "/usr",
+    "/var",
+];
+
+/// Extensions that are junk by convention, so `*.<ext>` is a reasonable pattern.
+const JUNK_EXTENSIONS: &[&str] = &[
+    "bak", "old", "orig", "pyc", "pyo", "rej", "swo", "swp", "temp", "tmp",
+];
+
+/// Ordinary user files. A regex that matches all of them matches nearly anything.
+const PROBE_NAMES: &[&str] = &[
+    "a",
+    "notes.txt",
+    "IMG_0001.JPG",
+    "Report 2024.docx",
+    "main.rs",
+];
+
+/// Explain why `root` is too dangerous to clean, if it is.
+/// `home` is the invoking user's home directory.
+pub fn dangerous_root_reason(root: &Path, home: Option<&Path>) -> Option<String> {
+    if home == Some(root) {
+        return Some(format!(
+            "'{}' is your home directory; a bad pattern here would delete personal files \
+             everywhere under it. Clean a specific subdirectory instead",
+            root.display()
+        ));
+    }
+    if root.parent().is_none() {
+        return Some(format!(
+            "'{}' is the filesystem root; a bad pattern here would delete files across the \
+             whole system",
+            root.display()
+        ));
+    }
+    if SYSTEM_ROOTS.iter().any(|r| Path::new(r) == root) {
+        return Some(format!(
+            "'{}' is a system directory; deleting files under it can break the operating \
+             system or other users' data",
+            root.display()
+        ));
+    }
+    None
+}
+
+/// Explain why `pattern` is too broad to delete with, if it is.
+pub fn dangerous_pattern_reason(pattern: &str) -> Option<String> {
+    if let Some(source) = pattern.strip_prefix(REGEX_PREFIX) {
+        // Regexes can't be reasoned about like globs, so probe them instead
+        let matches_all = Regex::new(source)
+            .is_ok_and(|re| PROBE_NAMES.iter().all(|name| re.is_match(name.as_bytes())));
+        return matches_all
+            .then(|| format!("pattern '{}' matches almost every file name", pattern));
+    }
+
+    // Each alternative of `{a,b}` is judged on its own
+    expand_braces(pattern)
+        .iter()
+        .find_map(|glob| dangerous_glob_reason(pattern, glob))
+}
+
+/// Judge one brace-free `glob`, naming the `pattern` it came from
+fn dangerous_glob_reason(pattern: &str, glob: &str) -> Option<String> {
+    // A class such as `[!.]` stands in for one character, like `?`
+    let tokens = glob_tokens(glob);
+    let is_wildcard = |t: &&str| matches!(*t, "*" | "?") || t.starts_with('[');
+
+    if let Some(dot) = tokens.iter().rposition(|t| *t == ".") {
+        let (stem, extension) = (&tokens[..dot], &tokens[dot + 1..]);
+        let literal_extension =
+            !extension.is_empty() && !extension.iter().any(|t| matches!(*t, "*" | "?"));
+        let wildcard_stem = !stem.is_empty() && stem.iter().all(is_wildcard);
+        let extension = extension.concat();
+        let is_junk = NameGlob::new(&extension, true)
+            .is_ok_and(|glob| JUNK_EXTENSIONS.iter().any(|junk| glob.matches(junk)));
+        if wildcard_stem && literal_extension {
+            return (!is_junk).then(|| {
+                format!(
+                    "pattern '{}' matches every '.{}' file, which is usually user data rather \
+                     than clutter",
+                    pattern, extension
+                )
+            });
+        }
+    }
+
+    if tokens.iter().all(|t| is_wildcard(t) || *t == ".") {
+        return Some(format!(
+            "pattern '{}' matches almost every file name",
+            pattern
+        ));
+    }
+
+    None
+}
+
+/// Split a brace-free glob into single characters, escaped characters and
+/// whole `[...]` classes
+fn glob_tokens(glob: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut rest = glob;
+    while let Some(c) = rest.chars().next() {
+        let len = match c {
+            '\\' => rest[1..]
+                .chars()
+                .next()
+                .map_or(1, |next| 1 + next.len_utf8()),
+            '[' => class_len(rest).unwrap_or(1),
+            c => c.len_utf8(),
+        };
+        let (token, tail) = rest.split_at(len);
+        tokens.push(token);
+        rest = tail;
+    }
+    tokens
+}
+
+/// Length of the class `glob` starts with, read as the glob translator does:
+/// a `]` right after the opening (and any negation) is a member
+fn class_len(glob: &str) -> Option<usize> {
+    let mut chars = glob.char_indices().skip(1).peekable();
+    chars.next_if(|(_, c)| matches!(c, '!' | '^'));
+    chars.next_if(|(_, c)| *c == ']');
+    chars
+        .find(|(_, c)| *c == ']')
+        .map(|(i, _)| i + ']'.len_utf8())
+}
+
+/// Every brace-free glob that `pattern` stands for: `*.{a,b}` gives `*.a` and `*.b`
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let mut depth = 0;
+    let mut open = None;
+    let mut commas = Vec::new();
+    let mut escaped = false;
+    for (i, c) in pattern.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '{' => {
+                depth += 1;
+                open.get_or_insert(i);
+            }
+            ',' if depth == 1 => commas.push(i),
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    let start = open.expect("set at depth 1");
+                    let bounds: Vec<usize> = std::iter::once(start)
+                        .chain(commas)
+                        .chain(std::iter::once(i))
+                        .collect();
+                    let (prefix, suffix) = (&pattern[..start], &pattern[i + 1..]);
+                    return bounds
+                        .windows(2)
+                        .flat_map(|w| {
+                            expand_braces(&format!(
+                                "{}{}{}",
+                                prefix,
+                                &pattern[w[0] + 1..w[1]],
+                                suffix
+                            ))
+                        })
+                        .collect();
+                }
+            }
+            _ => {}
+        }
+    }
+    vec![pattern.to_string()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filesystem_root_is_dangerous() {
+        let reason = dangerous_root_reason(Path::new("/"), None).unwrap();
+        assert!(reason.contains("filesystem root"));
+    }
+
+    #[test]
+    fn test_system_roots_are_dangerous() {
+        for root in ["/etc", "/usr", "/home", "/Users", "/System"] {
+            let reason = dangerous_root_reason(Path::new(root), None).unwrap();
+            assert!(reason.contains("system directory"), "{}", root);
+        }
+    }
+
+    #[test]
+    fn test_home_is_dangerous() {
+        let home = Path::new("/home/alex");
+        let reason = dangerous_root_reason(home, Some(home)).unwrap();
+        assert!(reason.contains("home directory"));
+    }
+
+    #[test]
+    fn test_subdirectories_are_allowed() {
+        let home = Path::new("/home/alex");
+        assert!(dangerous_root_reason(Path::new("/home/alex/photos"), Some(home)).is_none());
+        assert!(dangerous_root_reason(Path::new("/etc/nginx"), Some(home)).is_none());
+        assert!(dangerous_root_reason(Path::new("/srv/share"), Some(home)).is_none());
+    }
+
+    #[test]
+    fn test_wildcard_only_patterns_are_dangerous() {
+        for pattern in ["*", "*.*", "?*", "**"] {
+            let reason = dangerous_pattern_reason(pattern).unwrap();
+            assert!(reason.contains("almost every file"), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn test_user_data_extension_is_dangerous() {
+        let reason = dangerous_pattern_reason("*.txt").unwrap();
+        assert!(reason.contains("every '.txt' file"));
+        assert!(dangerous_pattern_reason("*.jpg").is_some());
+    }
+
+    #[test]
+    fn test_classes_count_as_wildcards() {
+        for pattern in ["[!.]*", "*[a-z]*", "[[]*", "[]]?*"] {
+            let reason = dangerous_pattern_reason(pattern).unwrap();
+            assert!(reason.contains("almost every file"), "{}", pattern);
+        }
+        let reason = dangerous_pattern_reason("*.[jJ][pP][gG]").unwrap();
+        assert!(reason.contains("every '.[jJ][pP][gG]' file"));
+        assert!(dangerous_pattern_reason("[!.]*.txt").is_some());
+        assert!(dangerous_pattern_reason("*.[tT][mM][pP]").is_none());
+        assert!(dangerous_pattern_reason("[._]*.swp").is_none());
+        assert!(dangerous_pattern_reason(r"\[*").is_none());
+    }
+
+    #[test]
+    fn test_specific_patterns_are_allowed() {
+        for pattern in [
+            ".DS_Store",
+            "Thumbs.db",
+            "*.bak",
+            "*.TMP",
+            "._*",
+            "~$*.docx",
+            r"re:^\.~lock\..*#$",
+            r"re:^~\$.*\.(docx|xlsx)$",
+            "*.{tmp,bak}",
+            "{Thumbs,ehthumbs}.db",
+        ] {
+            assert!(dangerous_pattern_reason(pattern).is_none(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn test_each_brace_alternative_is_checked() {
+        let reason = dangerous_pattern_reason("*.{tmp,jpg}").unwrap();
+        assert!(reason.contains("pattern '*.{tmp,jpg}' matches every '.jpg' file"));
+        assert!(dangerous_pattern_reason("{.DS_Store,*}").is_some());
+        assert!(dangerous_pattern_reason("*.{{bak,old},swp}").is_none());
+    }
+
+    #[test]
+    fn test_catch_all_regexes_are_dangerous() {
+        for pattern in ["re:", "re:.*", "re:^", "re:(?i)[a-z]"] {
+            let reason = dangerous_pattern_reason(pattern).unwrap();
+            assert!(reason.contains("almost every file"), "{}", pattern);
+        }
+    }
+}
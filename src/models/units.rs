@@ -0,0 +1,155 @@
+use anyhow::{anyhow, bail, Result};
+use std::time::Duration;
+
+const KIB: u64 = 1024;
+const MIB: u64 = KIB * 1024;
+const GIB: u64 = MIB * 1024;
+const TIB: u64 = GIB * 1024;
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = MINUTE * 60;
+const DAY: u64 = HOUR * 24;
+const WEEK: u64 = DAY * 7;
+
+/// Parse a human-readable size such as `500`, `10K`, `1.5M`, `2GiB` or `3 GB`.
+/// Suffixes are binary (1K = 1024 bytes) and case-insensitive.
+pub fn parse_size(input: &str) -> Result<u64> {
+    let trimmed = input.trim();
+    let split = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    let (number, suffix) = trimmed.split_at(split);
+    if number.is_empty() {
+        bail!("Invalid size '{}': expected a number", input);
+    }
+    let value: f64 = number
+        .parse()
+        .map_err(|_| anyhow!("Invalid size '{}': bad number", input))?;
+
+    let multiplier = match suffix.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => KIB,
+        "m" | "mb" | "mib" => MIB,
+        "g" | "gb" | "gib" => GIB,
+        "t" | "tb" | "tib" => TIB,
+        other => bail!("Invalid size '{}': unknown unit '{}'", input, other),
+    };
+    Ok((value * multiplier as f64) as u64)
+}
+
+/// Parse a human-readable duration such as `90s`, `30m`, `12h`, `7d`, `2w` or `1.5d`.
+/// A bare number is seconds; units are case-insensitive.
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let trimmed = input.trim();
+    let split = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    let (number, suffix) = trimmed.split_at(split);
+    if number.is_empty() {
+        bail!("Invalid duration '{}': expected a number", input);
+    }
+    let value: f64 = number
+        .parse()
+        .map_err(|_| anyhow!("Invalid duration '{}': bad number", input))?;
+
+    let multiplier = match suffix.trim().to_ascii_lowercase().as_str() {
+        "" | "s" | "sec" | "secs" => 1,
+        "m" | "min" | "mins" => MINUTE,
+        "h" | "hr" | "hrs" => HOUR,
+        "d" | "day" | "days" => DAY,
+        "w" | "week" | "weeks" => WEEK,
+        other => bail!("Invalid duration '{}': unknown unit '{}'", input, other),
+    };
+    Ok(Duration::from_secs((value * multiplier as f64) as u64))
+}
+
+/// Format a duration in the largest unit that represents it exactly, e.g. `7d` or
+/// `90m`, so that `parse_duration` reads it back unchanged.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let units = [(WEEK, "w"), (DAY, "d"), (HOUR, "h"), (MINUTE, "m")];
+    for (size, unit) in units {
+        if secs > 0 && secs.is_multiple_of(size) {
+            return format!("{}{}", secs / size, unit);
+        }
+    }
+    format!("{}s", secs)
+}
+
+/// Format a byte count for log and error messages, e.g. `1.5 MiB`.
+pub fn format_size(bytes: u64) -> String {
+    let units = [(TIB, "TiB"), (GIB, "GiB"), (MIB, "MiB"), (KIB, "KiB")];
+    for (size, unit) in units {
+        if bytes >= size {
+            return format!("{:.1} {}", bytes as f64 / size as f64, unit);
+        }
+    }
+    format!("{} B", bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size_plain_bytes() {
+        assert_eq!(parse_size("500").unwrap(), 500);
+        assert_eq!(parse_size("500B").unwrap(), 500);
+    }
+
+    #[test]
+    fn test_parse_size_suffixes() {
+        assert_eq!(parse_size("10K").unwrap(), 10 * 1024);
+        assert_eq!(parse_size("1.5M").unwrap(), 1024 * 1024 * 3 / 2);
+        assert_eq!(parse_size("2GiB").unwrap(), 2 * 1024 * 1024 * 1024);
+        assert_eq!(parse_size("3 gb").unwrap(), 3 * 1024 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_parse_size_rejects_garbage() {
+        assert!(parse_size("").is_err());
+        assert!(parse_size("lots").is_err());
+        assert!(parse_size("10X").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("30m").unwrap(), Duration::from_secs(30 * 60));
+        assert_eq!(
+            parse_duration("12H").unwrap(),
+            Duration::from_secs(12 * 3600)
+        );
+        assert_eq!(
+            parse_duration("7d").unwrap(),
+            Duration::from_secs(7 * 86400)
+        );
+        assert_eq!(
+            parse_duration("1.5 days").unwrap(),
+            Duration::from_secs(36 * 3600)
+        );
+        assert_eq!(
+            parse_duration("2w").unwrap(),
+            Duration::from_secs(14 * 86400)
+        );
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("3y").is_err());
+    }
+
+    #[test]
+    fn test_format_duration_round_trips() {
+        for input in ["0s", "45s", "90m", "36h", "1d", "2w"] {
+            let duration = parse_duration(input).unwrap();
+            assert_eq!(format_duration(duration), input);
+        }
+        assert_eq!(format_duration(Duration::from_secs(7 * 86400)), "1w");
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(10 * 1024 * 1024), "10.0 MiB");
+    }
+}
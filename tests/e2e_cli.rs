@@ -364,3 +364,1444 @@ fn test_monitor_help_shows_ignore_option() {
         .stdout(predicate::str::contains("--ignore"))
         .stdout(predicate::str::contains("Directory to ignore"));
 }
+
+// =============================================================================
+// Plan/Apply Tests
+// =============================================================================
+
+#[test]
+fn test_plan_writes_plan_without_deleting() {
+    let temp_dir = setup_test_dir();
+    let ds_store = create_file(&temp_dir, "project/.DS_Store");
+    let plan_dir = setup_test_dir();
+    let plan_path = plan_dir.path().join("plan.json");
+
+    cmd!()
+        .arg("plan")
+        .arg(temp_dir.path().join("project"))
+        .arg("-o")
+        .arg(&plan_path)
+        .assert()
+        .success();
+
+    assert!(ds_store.exists());
+    let plan: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&plan_path).unwrap()).unwrap();
+    let actions = plan["actions"].as_array().unwrap();
+    assert_eq!(actions.len(), 1);
+    assert_eq!(actions[0]["action"], "delete");
+    assert_eq!(actions[0]["path"], ds_store.to_str().unwrap());
+    assert!(actions[0]["metadata"]["inode"].is_u64());
+}
+
+#[test]
+fn test_apply_executes_only_planned_actions() {
+    let temp_dir = setup_test_dir();
+    let planned = create_file(&temp_dir, ".DS_Store");
+    let plan_dir = setup_test_dir();
+    let plan_path = plan_dir.path().join("plan.json");
+
+    cmd!()
+        .arg("plan")
+        .arg(temp_dir.path())
+        .arg("-o")
+        .arg(&plan_path)
+        .assert()
+        .success();
+
+    // Created after the plan was reviewed, so must survive apply
+    let unplanned = create_file(&temp_dir, "later/.DS_Store");
+
+    cmd!().arg("apply").arg(&plan_path).assert().success();
+
+    assert!(!planned.exists());
+    assert!(unplanned.exists());
+}
+
+#[test]
+fn test_plan_with_relative_root_applies_from_elsewhere() {
+    let temp_dir = setup_test_dir();
+    let ds_store = create_file(&temp_dir, "project/.DS_Store");
+    let plan_dir = setup_test_dir();
+    let plan_path = plan_dir.path().join("plan.json");
+
+    cmd!()
+        .current_dir(temp_dir.path())
+        .arg("plan")
+        .arg("project")
+        .arg("-o")
+        .arg(&plan_path)
+        .assert()
+        .success();
+
+    cmd!()
+        .current_dir(plan_dir.path())
+        .arg("apply")
+        .arg(&plan_path)
+        .assert()
+        .success();
+
+    assert!(!ds_store.exists());
+}
+
+#[test]
+fn test_apply_skips_file_changed_since_plan() {
+    let temp_dir = setup_test_dir();
+    let ds_store = create_file(&temp_dir, ".DS_Store");
+    let plan_dir = setup_test_dir();
+    let plan_path = plan_dir.path().join("plan.json");
+
+    cmd!()
+        .arg("plan")
+        .arg(temp_dir.path())
+        .arg("-o")
+        .arg(&plan_path)
+        .assert()
+        .success();
+
+    fs::write(&ds_store, b"rewritten after review").unwrap();
+
+    cmd!().arg("apply").arg(&plan_path).assert().success();
+
+    assert!(ds_store.exists());
+}
+
+#[test]
+fn test_apply_dry_run_preserves_files() {
+    let temp_dir = setup_test_dir();
+    let ds_store = create_file(&temp_dir, ".DS_Store");
+    let plan_dir = setup_test_dir();
+    let plan_path = plan_dir.path().join("plan.json");
+
+    cmd!()
+        .arg("plan")
+        .arg(temp_dir.path())
+        .arg("-o")
+        .arg(&plan_path)
+        .assert()
+        .success();
+
+    cmd!()
+        .arg("apply")
+        .arg(&plan_path)
+        .arg("--dry-run")
+        .assert()
+        .success();
+
+    assert!(ds_store.exists());
+}
+
+#[test]
+fn test_apply_rejects_invalid_plan() {
+    let plan_dir = setup_test_dir();
+    let plan_path = plan_dir.path().join("plan.json");
+    fs::write(&plan_path, "not a plan").unwrap();
+
+    cmd!()
+        .arg("apply")
+        .arg(&plan_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid plan"));
+}
+
+#[test]
+fn test_apply_rejects_action_outside_root() {
+    let temp_dir = setup_test_dir();
+    create_file(&temp_dir, "share/.DS_Store");
+    let outside = create_file(&temp_dir, "elsewhere/.DS_Store");
+    let plan_path = temp_dir.path().join("plan.json");
+
+    cmd!()
+        .arg("plan")
+        .arg(temp_dir.path().join("share"))
+        .arg("-o")
+        .arg(&plan_path)
+        .assert()
+        .success();
+
+    let mut plan: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&plan_path).unwrap()).unwrap();
+    plan["actions"][0]["path"] = outside.to_str().unwrap().into();
+    fs::write(&plan_path, plan.to_string()).unwrap();
+
+    cmd!()
+        .arg("apply")
+        .arg(&plan_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("outside its root"));
+
+    assert!(outside.exists());
+}
+
+#[test]
+fn test_apply_rejects_dangerous_plan() {
+    let temp_dir = setup_test_dir();
+    create_file(&temp_dir, "notes.txt");
+    let plan_path = temp_dir.path().join("plan.json");
+
+    cmd!()
+        .arg("plan")
+        .arg(temp_dir.path())
+        .arg("-p")
+        .arg("*")
+        .arg("--allow-dangerous")
+        .arg("-o")
+        .arg(&plan_path)
+        .assert()
+        .success();
+
+    cmd!()
+        .arg("apply")
+        .arg(&plan_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Refusing to clean"));
+
+    cmd!()
+        .arg("apply")
+        .arg(&plan_path)
+        .arg("--allow-dangerous")
+        .assert()
+        .success();
+}
+
+// =============================================================================
+// Deletion Limit Tests
+// =============================================================================
+
+#[test]
+fn test_run_max_deletions_aborts_before_deleting() {
+    let temp_dir = setup_test_dir();
+    let first = create_file(&temp_dir, ".DS_Store");
+    let second = create_file(&temp_dir, "a/.DS_Store");
+    let third = create_file(&temp_dir, "b/.DS_Store");
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("--max-deletions")
+        .arg("2")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Refusing to delete 3 files"))
+        .stderr(predicate::str::contains("--force"));
+
+    assert!(first.exists());
+    assert!(second.exists());
+    assert!(third.exists());
+}
+
+#[test]
+fn test_run_max_bytes_aborts_before_deleting() {
+    let temp_dir = setup_test_dir();
+    let ds_store = create_file(&temp_dir, ".DS_Store");
+    fs::write(&ds_store, vec![0u8; 4096]).unwrap();
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("--max-bytes")
+        .arg("1K")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--max-bytes 1.0 KiB"));
+
+    assert!(ds_store.exists());
+}
+
+#[test]
+fn test_run_force_overrides_limits() {
+    let temp_dir = setup_test_dir();
+    let first = create_file(&temp_dir, ".DS_Store");
+    let second = create_file(&temp_dir, "a/.DS_Store");
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("--max-deletions")
+        .arg("1")
+        .arg("--force")
+        .assert()
+        .success();
+
+    assert!(!first.exists());
+    assert!(!second.exists());
+}
+
+#[test]
+fn test_run_rejects_invalid_max_bytes() {
+    let temp_dir = setup_test_dir();
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("--max-bytes")
+        .arg("lots")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid size"));
+}
+
+// =============================================================================
+// Guard Rail Tests
+// =============================================================================
+
+#[test]
+fn test_run_refuses_home_directory() {
+    let home = setup_test_dir();
+    let ds_store = create_file(&home, ".DS_Store");
+
+    cmd!()
+        .arg("run")
+        .arg(home.path())
+        .env("HOME", home.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("home directory"))
+        .stderr(predicate::str::contains("--allow-dangerous"));
+
+    assert!(ds_store.exists());
+}
+
+#[test]
+fn test_run_refuses_broad_pattern() {
+    let temp_dir = setup_test_dir();
+    let keep = create_file(&temp_dir, "important.txt");
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("-p")
+        .arg("*")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("matches almost every file name"));
+
+    assert!(keep.exists());
+}
+
+#[test]
+fn test_run_allow_dangerous_confirms_broad_pattern() {
+    let temp_dir = setup_test_dir();
+    let notes = create_file(&temp_dir, "notes.txt");
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("-p")
+        .arg("*.txt")
+        .arg("--allow-dangerous")
+        .assert()
+        .success();
+
+    assert!(!notes.exists());
+}
+
+#[test]
+fn test_monitor_refuses_system_root() {
+    cmd!()
+        .arg("monitor")
+        .arg("/etc")
+        .arg("--timeout")
+        .arg("1")
+        .timeout(Duration::from_secs(10))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("system directory"));
+}
+
+// =============================================================================
+// Signature Verification Tests
+// =============================================================================
+
+#[test]
+fn test_run_verify_signatures_skips_impostor() {
+    let temp_dir = setup_test_dir();
+    let real = create_file(&temp_dir, ".DS_Store");
+    fs::write(&real, b"\x00\x00\x00\x01Bud1\x00\x00\x10\x00").unwrap();
+    let impostor = create_file(&temp_dir, "notes/.DS_Store");
+    fs::write(&impostor, b"shopping list").unwrap();
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("--verify-signatures")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("signature mismatch"));
+
+    assert!(!real.exists());
+    assert!(impostor.exists());
+}
+
+#[test]
+fn test_run_without_verify_signatures_deletes_impostor() {
+    let temp_dir = setup_test_dir();
+    let impostor = create_file(&temp_dir, ".DS_Store");
+    fs::write(&impostor, b"shopping list").unwrap();
+
+    cmd!().arg("run").arg(temp_dir.path()).assert().success();
+
+    assert!(!impostor.exists());
+}
+
+// =============================================================================
+// Inspect Command Tests
+// =============================================================================
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+#[test]
+fn test_inspect_prints_records_as_json() {
+    let output = cmd!()
+        .arg("inspect")
+        .arg(fixture("sample.DS_Store"))
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let records: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let records = records.as_array().unwrap();
+    assert_eq!(records.len(), 4);
+
+    assert_eq!(records[0]["filename"], ".");
+    assert_eq!(records[0]["code"], "bwsp");
+    assert_eq!(records[0]["value"]["ShowSidebar"], true);
+
+    assert_eq!(records[2]["filename"], "notes.txt");
+    assert_eq!(records[2]["code"], "Iloc");
+    assert_eq!(records[2]["value"]["x"], 64);
+    assert_eq!(records[2]["value"]["y"], 96);
+}
+
+#[test]
+fn test_inspect_rejects_non_ds_store() {
+    let temp_dir = setup_test_dir();
+    let fake = create_file(&temp_dir, ".DS_Store");
+    fs::write(&fake, b"not really").unwrap();
+
+    cmd!()
+        .arg("inspect")
+        .arg(&fake)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to parse"));
+}
+
+// =============================================================================
+// Leak Audit Tests
+// =============================================================================
+
+/// Copy the sample .DS_Store (records: ".", "notes.txt", "secret-plans.key") into `dir`
+fn install_sample_ds_store(dir: &TempDir, relative_dir: &str) -> PathBuf {
+    let target = dir.path().join(relative_dir).join(".DS_Store");
+    fs::create_dir_all(target.parent().unwrap()).unwrap();
+    fs::copy(fixture("sample.DS_Store"), &target).unwrap();
+    target
+}
+
+#[test]
+fn test_audit_leaks_reports_absent_sensitive_names() {
+    let temp_dir = setup_test_dir();
+    let ds_store = install_sample_ds_store(&temp_dir, "site");
+    create_file(&temp_dir, "site/notes.txt");
+
+    let output = cmd!()
+        .arg("audit-leaks")
+        .arg(temp_dir.path())
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let files = report["files"].as_array().unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0]["path"], ds_store.to_str().unwrap());
+    assert_eq!(files[0]["recorded_names"], 2);
+    let leaks = files[0]["leaks"].as_array().unwrap();
+    assert_eq!(leaks.len(), 1);
+    assert_eq!(leaks[0]["name"], "secret-plans.key");
+    assert_eq!(
+        leaks[0]["reasons"],
+        serde_json::json!(["absent", "sensitive"])
+    );
+    assert!(ds_store.exists()); // Audit never deletes
+}
+
+#[test]
+fn test_audit_leaks_flags_ignored_directory() {
+    let temp_dir = setup_test_dir();
+    install_sample_ds_store(&temp_dir, "build");
+    create_file(&temp_dir, "build/notes.txt");
+
+    let output = cmd!()
+        .arg("audit-leaks")
+        .arg(temp_dir.path())
+        .arg("--ignore")
+        .arg("build")
+        .output()
+        .unwrap();
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let leaks = report["files"][0]["leaks"].as_array().unwrap();
+    assert_eq!(leaks.len(), 2);
+    assert_eq!(leaks[0]["name"], "notes.txt");
+    assert_eq!(leaks[0]["reasons"], serde_json::json!(["ignored"]));
+}
+
+#[test]
+fn test_run_writes_audit_before_cleanup() {
+    let temp_dir = setup_test_dir();
+    let ds_store = install_sample_ds_store(&temp_dir, "site");
+    let report_dir = setup_test_dir();
+    let report_path = report_dir.path().join("leaks.json");
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("--audit-leaks")
+        .arg(&report_path)
+        .assert()
+        .success();
+
+    assert!(!ds_store.exists());
+    let report: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&report_path).unwrap()).unwrap();
+    assert_eq!(report["files"][0]["leaks"].as_array().unwrap().len(), 2);
+}
+
+// =============================================================================
+// AppleDouble Mode Tests
+// =============================================================================
+
+/// AppleDouble with one Finder info entry of `finder_info` bytes
+fn apple_double(finder_info: &[u8]) -> Vec<u8> {
+    let mut data = vec![0x00, 0x05, 0x16, 0x07, 0x00, 0x02, 0x00, 0x00];
+    data.extend_from_slice(b"Mac OS X        ");
+    data.extend_from_slice(&1u16.to_be_bytes());
+    data.extend_from_slice(&9u32.to_be_bytes());
+    data.extend_from_slice(&38u32.to_be_bytes());
+    data.extend_from_slice(&(finder_info.len() as u32).to_be_bytes());
+    data.extend_from_slice(finder_info);
+    data
+}
+
+#[test]
+fn test_run_apple_double_mode() {
+    let temp_dir = setup_test_dir();
+    let orphan = create_file(&temp_dir, "._deleted.jpg");
+    fs::write(&orphan, apple_double(&[1; 32])).unwrap();
+    create_file(&temp_dir, "empty.jpg");
+    let empty = create_file(&temp_dir, "._empty.jpg");
+    fs::write(&empty, apple_double(&[0; 32])).unwrap();
+    let labelled_base = create_file(&temp_dir, "labelled.jpg");
+    let labelled = create_file(&temp_dir, "._labelled.jpg");
+    fs::write(&labelled, apple_double(&[1; 32])).unwrap();
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("--apple-double")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "AppleDouble holds data for an existing file",
+        ));
+
+    assert!(!orphan.exists());
+    assert!(!empty.exists());
+    assert!(labelled.exists());
+    assert!(labelled_base.exists());
+}
+
+#[test]
+fn test_run_without_apple_double_mode_leaves_apple_double() {
+    let temp_dir = setup_test_dir();
+    let orphan = create_file(&temp_dir, "._deleted.jpg");
+    fs::write(&orphan, apple_double(&[0; 32])).unwrap();
+
+    cmd!().arg("run").arg(temp_dir.path()).assert().success();
+
+    assert!(orphan.exists());
+}
+
+// =============================================================================
+// Directory Artifact Tests
+// =============================================================================
+
+#[test]
+fn test_run_removes_matching_directories() {
+    let temp_dir = setup_test_dir();
+    create_file(&temp_dir, "archive/__MACOSX/._photo.jpg");
+    create_file(&temp_dir, "archive/__MACOSX/nested/.DS_Store");
+    let photo = create_file(&temp_dir, "archive/photo.jpg");
+    let ignored = create_file(&temp_dir, "node_modules/__MACOSX/._x");
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .args(["-d", "__MACOSX", "--ignore", "node_modules"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Deleted directory"));
+
+    assert!(!temp_dir.path().join("archive/__MACOSX").exists());
+    assert!(photo.exists());
+    assert!(ignored.exists());
+}
+
+#[test]
+fn test_run_dry_run_keeps_matching_directories() {
+    let temp_dir = setup_test_dir();
+    let fork = create_file(&temp_dir, "__MACOSX/._photo.jpg");
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .args(["--dir-pattern", "__MACOSX", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Would delete directory"));
+
+    assert!(fork.exists());
+}
+
+#[test]
+fn test_run_prune_empty_dirs() {
+    let temp_dir = setup_test_dir();
+    create_file(&temp_dir, "import/2024/.DS_Store");
+    let kept = create_file(&temp_dir, "photos/a.jpg");
+    create_file(&temp_dir, "photos/.DS_Store");
+    fs::create_dir(temp_dir.path().join("already-empty")).unwrap();
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("--prune-empty-dirs")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Pruned empty directory"));
+
+    assert!(!temp_dir.path().join("import").exists());
+    assert!(kept.exists());
+    assert!(temp_dir.path().join("already-empty").exists());
+    assert!(temp_dir.path().exists());
+}
+
+#[test]
+fn test_run_prune_empty_dirs_dry_run() {
+    let temp_dir = setup_test_dir();
+    let ds_store = create_file(&temp_dir, "import/2024/.DS_Store");
+    create_file(&temp_dir, "photos/a.jpg");
+    create_file(&temp_dir, "photos/.DS_Store");
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("--prune-empty-dirs")
+        .arg("--dry-run")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Would prune empty directory"));
+
+    assert!(ds_store.exists());
+    assert!(temp_dir.path().join("import/2024").exists());
+}
+
+// =============================================================================
+// Extended Attribute Tests
+// =============================================================================
+
+#[test]
+fn test_run_strip_xattrs() {
+    let temp_dir = setup_test_dir();
+    let photo = create_file(&temp_dir, "photo.jpg");
+    // Not every filesystem backing the temp dir supports user xattrs
+    if xattr::set(&photo, "user.com.apple.FinderInfo", b"x").is_err() {
+        return;
+    }
+    xattr::set(&photo, "user.keep", b"x").unwrap();
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("--strip-xattrs")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Removed xattr"));
+
+    let names: Vec<_> = xattr::list(&photo).unwrap().collect();
+    assert_eq!(names, vec![std::ffi::OsString::from("user.keep")]);
+}
+
+#[test]
+fn test_run_strip_xattrs_dry_run() {
+    let temp_dir = setup_test_dir();
+    let photo = create_file(&temp_dir, "photo.jpg");
+    if xattr::set(&photo, "user.com.apple.FinderInfo", b"x").is_err() {
+        return;
+    }
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .args(["--xattr-pattern", "user.com.apple.*", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Would remove xattr"));
+
+    assert!(xattr::get(&photo, "user.com.apple.FinderInfo")
+        .unwrap()
+        .is_some());
+}
+
+// =============================================================================
+// Preset Tests
+// =============================================================================
+
+#[test]
+fn test_run_samba_preset() {
+    let temp_dir = setup_test_dir();
+    let encoded = create_file(&temp_dir, "share/:2eDS_Store");
+    create_file(&temp_dir, "share/photos/.AppleDouble/a.jpg");
+    create_file(&temp_dir, "share/.AppleDB/cnid2.db");
+    let photo = create_file(&temp_dir, "share/photos/a.jpg");
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .args(["--preset", "samba"])
+        .assert()
+        .success();
+
+    assert!(!encoded.exists());
+    assert!(!temp_dir.path().join("share/photos/.AppleDouble").exists());
+    assert!(!temp_dir.path().join("share/.AppleDB").exists());
+    assert!(photo.exists());
+}
+
+#[test]
+fn test_run_unknown_preset_is_rejected() {
+    let temp_dir = setup_test_dir();
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .args(["--preset", "nfs"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown preset 'nfs'"));
+}
+
+#[test]
+fn test_run_windows_preset() {
+    let temp_dir = setup_test_dir();
+    let marker = create_file(&temp_dir, "downloads/setup.exe:Zone.Identifier");
+    fs::write(&marker, "[ZoneTransfer]\r\nZoneId=3\r\n").unwrap();
+    let look_alike = create_file(&temp_dir, "downloads/meeting:Zone.Identifier");
+    fs::write(&look_alike, "agenda").unwrap();
+    let desktop_ini = create_file(&temp_dir, "downloads/desktop.ini");
+    let download = create_file(&temp_dir, "downloads/setup.exe");
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .args(["--preset", "windows"])
+        .assert()
+        .success();
+
+    assert!(!marker.exists());
+    assert!(!desktop_ini.exists());
+    assert!(look_alike.exists());
+    assert!(download.exists());
+}
+
+// =============================================================================
+// Open File Tests
+// =============================================================================
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_run_skip_in_use() {
+    let temp_dir = setup_test_dir();
+    let open_file = create_file(&temp_dir, "open/.DS_Store");
+    let closed_file = create_file(&temp_dir, "closed/.DS_Store");
+    // This test process holds the file open while the CLI runs
+    let _handle = fs::File::open(&open_file).unwrap();
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("--skip-in-use")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("in use"));
+
+    assert!(open_file.exists());
+    assert!(!closed_file.exists());
+}
+
+// =============================================================================
+// Editor Artifact Tests
+// =============================================================================
+
+#[cfg(target_os = "linux")]
+fn vim_swap(host: &str, pid: u32) -> Vec<u8> {
+    let mut block = vec![0u8; 1024];
+    block[0..2].copy_from_slice(b"b0");
+    block[2..9].copy_from_slice(b"VIM 9.0");
+    block[24..28].copy_from_slice(&pid.to_le_bytes());
+    block[68..68 + host.len()].copy_from_slice(host.as_bytes());
+    block
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_run_editor_artifacts() {
+    let temp_dir = setup_test_dir();
+    let host = fs::read_to_string("/proc/sys/kernel/hostname").unwrap();
+    let host = host.trim();
+
+    let stale_swap = temp_dir.path().join(".notes.txt.swp");
+    fs::write(&stale_swap, vim_swap(host, 0x7fff_fff0)).unwrap();
+    // The emacs lock is held by this test process, which is alive
+    let live_lock = temp_dir.path().join(".#draft.txt");
+    std::os::unix::fs::symlink(format!("user@{}.{}", host, std::process::id()), &live_lock)
+        .unwrap();
+    let auto_save = create_file(&temp_dir, "#draft.txt#");
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("--editor-artifacts")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("live editor session"));
+
+    assert!(!stale_swap.exists());
+    assert!(live_lock.symlink_metadata().is_ok());
+    assert!(auto_save.exists());
+}
+
+// =============================================================================
+// Config File Tests
+// =============================================================================
+
+#[test]
+fn test_run_config_file_conditions() {
+    let temp_dir = setup_test_dir();
+    let config_dir = setup_test_dir();
+    let config = config_dir.path().join("cleanup.toml");
+    fs::write(
+        &config,
+        "[[patterns]]\npattern = \"*.tmp\"\nolder_than = \"1d\"\n",
+    )
+    .unwrap();
+
+    let ds_store = create_file(&temp_dir, ".DS_Store");
+    let fresh = create_file(&temp_dir, "jobs/fresh.tmp");
+    let old = create_file(&temp_dir, "jobs/old.tmp");
+    File::options()
+        .write(true)
+        .open(&old)
+        .unwrap()
+        .set_modified(std::time::SystemTime::now() - Duration::from_secs(2 * 86400))
+        .unwrap();
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("--config")
+        .arg(&config)
+        .assert()
+        .success();
+
+    assert!(!ds_store.exists());
+    assert!(!old.exists());
+    assert!(fresh.exists());
+}
+
+#[test]
+fn test_run_invalid_config_file_is_rejected() {
+    let temp_dir = setup_test_dir();
+    let config = temp_dir.path().join("cleanup.toml");
+    fs::write(
+        &config,
+        "[[patterns]]\npattern = \"*.tmp\"\nolder_than = \"soon\"\n",
+    )
+    .unwrap();
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("--config")
+        .arg(&config)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid duration 'soon'"));
+}
+
+#[test]
+fn test_run_config_file_rules() {
+    let temp_dir = setup_test_dir();
+    let config_dir = setup_test_dir();
+    let quarantine = config_dir.path().join("quarantine");
+    let config = config_dir.path().join("cleanup.toml");
+    fs::write(
+        &config,
+        r#"quarantine_dir = "quarantine"
+
+[[rules]]
+when = 'name("*.orig") and not path("vendor/**")'
+
+[[rules]]
+when = 'name("core.*") and content("ELF")'
+action = "quarantine"
+
+[[rules]]
+when = 'name("*.iso")'
+action = "report-only"
+"#,
+    )
+    .unwrap();
+
+    let orig = create_file(&temp_dir, "src/main.rs.orig");
+    let vendored = create_file(&temp_dir, "vendor/lib.rs.orig");
+    let core = create_file(&temp_dir, "core.1234");
+    fs::write(&core, b"\x7fELF").unwrap();
+    let iso = create_file(&temp_dir, "disk.iso");
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("--config")
+        .arg(&config)
+        .assert()
+        .success();
+
+    assert!(!orig.exists());
+    assert!(vendored.exists());
+    assert!(!core.exists());
+    let relative: PathBuf = core
+        .components()
+        .filter(|c| matches!(c, std::path::Component::Normal(_)))
+        .collect();
+    assert!(quarantine.join(relative).exists());
+    assert!(iso.exists());
+}
+
+#[test]
+fn test_run_config_file_pattern_actions() {
+    let temp_dir = setup_test_dir();
+    let config_dir = setup_test_dir();
+    let config = config_dir.path().join("cleanup.toml");
+    fs::write(
+        &config,
+        r#"quarantine_dir = "quarantine"
+
+[[patterns]]
+pattern = "*.bak"
+action = "quarantine"
+
+[[patterns]]
+pattern = "*.orig"
+action = "report-only"
+
+[[patterns]]
+pattern = "Thumbs.db"
+action = "rename"
+rename_to = ".{name}.old"
+"#,
+    )
+    .unwrap();
+
+    let ds_store = create_file(&temp_dir, ".DS_Store");
+    let bak = create_file(&temp_dir, "notes.bak");
+    let orig = create_file(&temp_dir, "main.rs.orig");
+    let thumbs = create_file(&temp_dir, "photos/Thumbs.db");
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("--config")
+        .arg(&config)
+        .arg("--log-format")
+        .arg("json")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""renamed":1"#))
+        .stdout(predicate::str::contains(r#""quarantined":1"#))
+        .stdout(predicate::str::contains(r#""reported":1"#));
+
+    assert!(!ds_store.exists());
+    assert!(!bak.exists());
+    assert!(orig.exists());
+    assert!(!thumbs.exists());
+    assert!(temp_dir.path().join("photos/.Thumbs.db.old").exists());
+}
+
+#[test]
+fn test_run_quarantine_rule_requires_dir() {
+    let temp_dir = setup_test_dir();
+    let config_dir = setup_test_dir();
+    let config = config_dir.path().join("cleanup.toml");
+    fs::write(
+        &config,
+        "[[rules]]\nwhen = 'name(\"core.*\")'\naction = \"quarantine\"\n",
+    )
+    .unwrap();
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("--config")
+        .arg(&config)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no quarantine_dir is configured"));
+}
+
+// =============================================================================
+// Name Matching Tests
+// =============================================================================
+
+#[test]
+fn test_run_ignore_case() {
+    let temp_dir = setup_test_dir();
+    let upper = create_file(&temp_dir, "share/.DS_STORE");
+    let thumbs = create_file(&temp_dir, "share/THUMBS.DB");
+    let keep = create_file(&temp_dir, "share/notes.txt");
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("-p")
+        .arg("Thumbs.db")
+        .assert()
+        .success();
+    assert!(upper.exists());
+    assert!(thumbs.exists());
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("-p")
+        .arg("Thumbs.db")
+        .arg("--ignore-case")
+        .assert()
+        .success();
+    assert!(!upper.exists());
+    assert!(!thumbs.exists());
+    assert!(keep.exists());
+}
+
+#[test]
+fn test_run_matches_nfd_names() {
+    let temp_dir = setup_test_dir();
+    // "Café.tmp" as written by macOS, decomposed into "e" and a combining accent
+    let decomposed = create_file(&temp_dir, "Cafe\u{301}.tmp");
+    let other = create_file(&temp_dir, "Cafe.tmp");
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("-p")
+        .arg("Caf\u{e9}.tmp")
+        .assert()
+        .success();
+
+    assert!(!decomposed.exists());
+    assert!(other.exists());
+}
+
+#[test]
+fn test_run_brace_and_class_patterns() {
+    let temp_dir = setup_test_dir();
+    let lock = create_file(&temp_dir, "docs/~$budget.xlsx");
+    let owner = create_file(&temp_dir, "docs/~$report.docx");
+    let swap = create_file(&temp_dir, "src/.main.rs.swo");
+    let pdf = create_file(&temp_dir, "docs/~$scan.pdf");
+    let swap_like = create_file(&temp_dir, "src/.main.rs.swz");
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("-p")
+        .arg("~$*.{docx,xlsx,pptx}")
+        .arg("-p")
+        .arg(".*.sw[a-p]")
+        .assert()
+        .success();
+
+    assert!(!lock.exists());
+    assert!(!owner.exists());
+    assert!(!swap.exists());
+    assert!(pdf.exists());
+    assert!(swap_like.exists());
+}
+
+#[test]
+fn test_run_regex_patterns() {
+    let temp_dir = setup_test_dir();
+    let lock = create_file(&temp_dir, "docs/~$budget.xlsx");
+    let conflict = create_file(&temp_dir, "docs/notes (conflicted copy 2024-01-02).txt");
+    let budget = create_file(&temp_dir, "docs/budget.xlsx");
+    let notes = create_file(&temp_dir, "docs/notes.txt");
+    let ds_store = create_file(&temp_dir, "docs/.DS_Store");
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("-p")
+        .arg(r"re:^~\$.*\.(docx|xlsx)$")
+        .arg("-p")
+        .arg(r"re:^.+ \(conflicted copy \d{4}-\d{2}-\d{2}\)(\.[^.]+)?$")
+        .arg("--verbose")
+        .arg("--log-format")
+        .arg("json")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""kind":"regex""#))
+        .stdout(predicate::str::contains(r#""kind":"glob""#));
+
+    assert!(!lock.exists());
+    assert!(!conflict.exists());
+    assert!(!ds_store.exists());
+    assert!(budget.exists());
+    assert!(notes.exists());
+}
+
+#[test]
+fn test_run_rejects_invalid_and_catch_all_regexes() {
+    let temp_dir = setup_test_dir();
+    create_file(&temp_dir, ".DS_Store");
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("-p")
+        .arg("re:(unclosed")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid regex pattern"));
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("-p")
+        .arg("re:.*")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("matches almost every file name"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_non_utf8_names_are_cleaned_and_reported() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let temp_dir = setup_test_dir();
+    // Latin-1 names, as copied from old volumes
+    let latin1_dir = temp_dir.path().join(OsStr::from_bytes(b"\xe9t\xe9"));
+    fs::create_dir(&latin1_dir).unwrap();
+    let ds_store = latin1_dir.join(".DS_Store");
+    File::create(&ds_store).unwrap();
+    let temp_file = temp_dir.path().join(OsStr::from_bytes(b"caf\xe9.tmp"));
+    File::create(&temp_file).unwrap();
+    let plan_dir = setup_test_dir();
+    let plan_path = plan_dir.path().join("plan.json");
+
+    cmd!()
+        .arg("plan")
+        .arg(temp_dir.path())
+        .arg("-p")
+        .arg("*.tmp")
+        .arg("-o")
+        .arg(&plan_path)
+        .assert()
+        .success();
+    let plan: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&plan_path).unwrap()).unwrap();
+    let paths: Vec<String> = plan["actions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|a| a["path"]["escaped"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(paths.len(), 2);
+    assert!(paths.iter().any(|p| p.ends_with(r"/caf\xE9.tmp")));
+
+    cmd!()
+        .arg("run")
+        .arg(temp_dir.path())
+        .arg("-p")
+        .arg("*.tmp")
+        .arg("--dry-run")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r"\xE9t\xE9/.DS_Store"));
+
+    cmd!().arg("apply").arg(&plan_path).assert().success();
+    assert!(!ds_store.exists());
+    assert!(!temp_file.exists());
+    assert!(latin1_dir.exists());
+}
+
+// =============================================================================
+// Explain Command Tests
+// =============================================================================
+
+#[test]
+fn test_explain_names_ignored_ancestor() {
+    let temp_dir = setup_test_dir();
+    let ds_store = create_file(&temp_dir, "app/node_modules/pkg/.DS_Store");
+
+    cmd!()
+        .arg("explain")
+        .arg(&ds_store)
+        .arg("--root")
+        .arg(temp_dir.path())
+        .arg("--ignore")
+        .arg("node_modules")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("ignore: node_modules"))
+        .stdout(predicate::str::contains(
+            "node_modules is skipped by --ignore node_modules",
+        ))
+        .stdout(predicate::str::contains(
+            "glob '.DS_Store' (delete): matches name",
+        ))
+        .stdout(predicate::str::contains("result: keep"));
+
+    assert!(ds_store.exists());
+}
+
+#[test]
+fn test_explain_relative_path_shows_conditions_and_result() {
+    let temp_dir = setup_test_dir();
+    let config = temp_dir.path().join("config.toml");
+    fs::write(
+        &config,
+        r#"
+[[patterns]]
+pattern = "*.tmp"
+older_than = "7d"
+
+[[rules]]
+when = 'name("*.log")'
+action = "report-only"
+"#,
+    )
+    .unwrap();
+    let temp_file = create_file(&temp_dir, "build/fresh.tmp");
+    create_file(&temp_dir, "build/app.log");
+
+    cmd!()
+        .current_dir(temp_dir.path())
+        .arg("explain")
+        .arg("build/fresh.tmp")
+        .arg("--config")
+        .arg(&config)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "rule 'name(\"*.log\")' (report-only): no match",
+        ))
+        .stdout(predicate::str::contains("older than 1w: fail"))
+        .stdout(predicate::str::contains("result: keep"));
+
+    cmd!()
+        .current_dir(temp_dir.path())
+        .arg("explain")
+        .arg("build/app.log")
+        .arg("--config")
+        .arg(&config)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("(report-only): matches"))
+        .stdout(predicate::str::contains("result: report-only"));
+
+    assert!(temp_file.exists());
+}
+
+#[test]
+fn test_explain_rejects_path_outside_root() {
+    let root = setup_test_dir();
+    let other = setup_test_dir();
+    let ds_store = create_file(&other, ".DS_Store");
+
+    cmd!()
+        .arg("explain")
+        .arg(&ds_store)
+        .arg("--root")
+        .arg(root.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is not under"));
+}
+
+// =============================================================================
+// Test Patterns Command Tests
+// =============================================================================
+
+#[test]
+fn test_test_patterns_judges_newline_separated_paths() {
+    let output = cmd!()
+        .arg("test-patterns")
+        .arg("-p")
+        .arg("*.tmp")
+        .arg("--ignore")
+        .arg("node_modules")
+        .write_stdin("./src/.DS_Store\nnode_modules/pkg/.DS_Store\nbuild/app.tmp\nREADME.md\n")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "delete\t./src/.DS_Store\tglob '.DS_Store'\n\
+         keep\tnode_modules/pkg/.DS_Store\tignored: node_modules\n\
+         delete\tbuild/app.tmp\tglob '*.tmp'\n\
+         keep\tREADME.md\tno match\n"
+    );
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("Patterns tested"));
+}
+
+#[test]
+fn test_test_patterns_reads_nul_separated_paths() {
+    cmd!()
+        .arg("test-patterns")
+        .arg("-d")
+        .arg("__MACOSX")
+        .arg("-0")
+        .write_stdin("a dir/.DS_Store\0zip/__MACOSX/photo.jpg\0")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "delete\ta dir/.DS_Store\tglob '.DS_Store'\n",
+        ))
+        .stdout(predicate::str::contains(
+            "delete\tzip/__MACOSX/photo.jpg\tdir pattern '__MACOSX' on zip/__MACOSX\n",
+        ));
+}
+
+#[test]
+fn test_test_patterns_marks_outcomes_that_depend_on_the_file() {
+    let temp_dir = setup_test_dir();
+    let config = temp_dir.path().join("config.toml");
+    fs::write(
+        &config,
+        r#"
+[[patterns]]
+pattern = "*.tmp"
+older_than = "1d"
+"#,
+    )
+    .unwrap();
+
+    cmd!()
+        .arg("test-patterns")
+        .arg("--config")
+        .arg(&config)
+        .write_stdin("cache/old.tmp\n")
+        .assert()
+        .success()
+        .stdout("delete?\tcache/old.tmp\tglob '*.tmp' if older than 1d\n");
+}
+
+// =============================================================================
+// List Command Tests
+// =============================================================================
+
+#[test]
+fn test_list_prints_matches_without_deleting() {
+    let temp_dir = setup_test_dir();
+    let ds_store = create_file(&temp_dir, "a/.DS_Store");
+    create_file(&temp_dir, "a/keep.txt");
+
+    let output = cmd!()
+        .arg("list")
+        .arg(temp_dir.path())
+        .arg("--verbose")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!("{}\n", ds_store.display())
+    );
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("Listed matches"));
+    assert!(ds_store.exists());
+}
+
+#[test]
+fn test_list_nul_separated_relative_paths() {
+    let temp_dir = setup_test_dir();
+    create_file(&temp_dir, "my docs/.DS_Store");
+    create_file(&temp_dir, "zip/__MACOSX/._photo.jpg");
+
+    cmd!()
+        .arg("list")
+        .arg(temp_dir.path())
+        .arg("-d")
+        .arg("__MACOSX")
+        .arg("-0")
+        .arg("--relative")
+        .assert()
+        .success()
+        .stdout("my docs/.DS_Store\0zip/__MACOSX\0");
+}
+
+#[test]
+fn test_list_leaves_out_signature_mismatches() {
+    let temp_dir = setup_test_dir();
+    let real = create_file(&temp_dir, "a/.DS_Store");
+    fs::write(&real, b"\x00\x00\x00\x01Bud1\x00\x00\x10\x00").unwrap();
+    let impostor = create_file(&temp_dir, ".DS_Store");
+    fs::write(&impostor, b"my notes\n").unwrap();
+
+    cmd!()
+        .arg("list")
+        .arg(temp_dir.path())
+        .arg("--verify-signatures")
+        .arg("--relative")
+        .assert()
+        .success()
+        .stdout("a/.DS_Store\n")
+        .stderr(predicate::str::contains("signature mismatch"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_list_prints_non_utf8_names_as_stored() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let temp_dir = setup_test_dir();
+    File::create(temp_dir.path().join(OsStr::from_bytes(b"caf\xe9.tmp"))).unwrap();
+
+    let output = cmd!()
+        .arg("list")
+        .arg(temp_dir.path())
+        .arg("-p")
+        .arg("*.tmp")
+        .arg("--relative")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"caf\xe9.tmp\n");
+}
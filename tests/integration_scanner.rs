@@ -286,3 +286,57 @@ async fn test_run_command_with_ignore() {
     assert!(!ds_store_src.exists());
     assert!(ds_store_ignored.exists()); // File in ignored dir should remain
 }
+
+// =============================================================================
+// Scan/Delete Identity Tests
+// =============================================================================
+
+#[tokio::test]
+async fn test_clean_skips_file_rewritten_after_scan() {
+    let temp_dir = setup_test_dir();
+
+    let ds_store = create_file(&temp_dir, ".DS_Store");
+    let other = create_file(&temp_dir, "sub/.DS_Store");
+
+    let fs = RealFileSystem;
+    let cleaner = Cleaner::new(fs, &[".DS_Store".to_string()], vec![]).unwrap();
+
+    let matches = cleaner.scan_matches(temp_dir.path()).await.unwrap();
+    fs::write(&ds_store, b"user wrote this after the scan").unwrap();
+
+    let result = cleaner.remove_matches(matches, false).await;
+
+    assert_eq!(result.files_found, 2);
+    assert_eq!(result.files_deleted, 1);
+    assert_eq!(result.files_skipped.len(), 1);
+    assert_eq!(result.files_skipped[0].0, ds_store);
+    assert_eq!(result.files_skipped[0].1.to_string(), "modified since scan");
+    assert!(ds_store.exists());
+    assert!(!other.exists());
+}
+
+// =============================================================================
+// Open File Tests
+// =============================================================================
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_clean_skips_files_held_open() {
+    let temp_dir = setup_test_dir();
+
+    let open_swap = create_file(&temp_dir, ".notes.txt.swp");
+    let stale_swap = create_file(&temp_dir, ".old.txt.swp");
+    let _handle = File::open(&open_swap).unwrap();
+
+    let cleaner = Cleaner::new(RealFileSystem, &["*.swp".to_string()], vec![])
+        .unwrap()
+        .with_in_use_check(true);
+    let result = cleaner.clean(temp_dir.path(), false).await.unwrap();
+
+    assert_eq!(result.files_deleted, 1);
+    assert_eq!(result.files_skipped.len(), 1);
+    assert_eq!(result.files_skipped[0].0, open_swap);
+    assert_eq!(result.files_skipped[0].1.to_string(), "in use");
+    assert!(open_swap.exists());
+    assert!(!stale_swap.exists());
+}